@@ -0,0 +1,29 @@
+use std::{env, str::FromStr};
+
+use chrono_tz::{Europe::Amsterdam, Tz};
+
+/// Returns `true` when the env var is set to `1` or `true` (case-insensitive).
+pub fn env_flag(name: &str) -> bool {
+    env_flag_or(name, false)
+}
+
+/// Like `env_flag`, but returns `default` when the env var is unset.
+pub fn env_flag_or(name: &str, default: bool) -> bool {
+    env::var(name)
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true"))
+        .unwrap_or(default)
+}
+
+/// Parses the env var into `T`, falling back to `default` when unset or invalid.
+pub fn env_parse<T: FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// The timezone that decides where a day starts, `DAY_TIMEZONE` (e.g. `Europe/London`)
+/// or Amsterdam, which the sheet's timestamps are written in.
+pub fn day_timezone() -> Tz {
+    env_parse("DAY_TIMEZONE", Amsterdam)
+}
@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serenity::all::{CreateInteractionResponse, CreateInteractionResponseMessage};
+
+use crate::config::env_parse;
+
+/// Commands that read whole sheets, so repeating them quickly is throttled.
+pub const COOLDOWN_COMMANDS: &[&str] = &[
+    "leaderboard",
+    "hall_of_shame",
+    "vs",
+    "mytimes",
+    "validate_records",
+    "fix_drift",
+];
+
+/// When each user last ran each of the `COOLDOWN_COMMANDS`.
+pub struct Cooldowns {
+    cooldown: Duration,
+    last_used: Mutex<HashMap<(u64, String), Instant>>,
+}
+
+impl Cooldowns {
+    pub fn new(cooldown: Duration) -> Self {
+        Cooldowns {
+            cooldown,
+            last_used: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `COMMAND_COOLDOWN_SECS` between runs of a command by the same user, 0 (the
+    /// default) disables the cooldown.
+    pub fn from_env() -> Self {
+        Self::new(Duration::from_secs(env_parse("COMMAND_COOLDOWN_SECS", 0)))
+    }
+
+    /// Starts the cooldown of the command for the user, or returns how long they still
+    /// have to wait when it's running.
+    pub fn try_start(&self, user_id: u64, command: &str, now: Instant) -> Option<Duration> {
+        if self.cooldown.is_zero() || !COOLDOWN_COMMANDS.contains(&command) {
+            return None;
+        }
+
+        let mut last_used = self
+            .last_used
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let key = (user_id, command.to_string());
+        if let Some(wait) = remaining(last_used.get(&key).copied(), now, self.cooldown) {
+            return Some(wait);
+        }
+
+        last_used.retain(|_, used| remaining(Some(*used), now, self.cooldown).is_some());
+        last_used.insert(key, now);
+        None
+    }
+}
+
+/// How much of the cooldown is left after a run at `last_used`, `None` once it's over.
+pub fn remaining(last_used: Option<Instant>, now: Instant, cooldown: Duration) -> Option<Duration> {
+    let elapsed = now.saturating_duration_since(last_used?);
+    (elapsed < cooldown).then(|| cooldown - elapsed)
+}
+
+pub fn wait_response(wait: Duration) -> CreateInteractionResponse {
+    // Rounded up, "please wait 0 seconds" would be confusing.
+    let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(format!("Please wait {seconds} seconds before using this command again"))
+            .ephemeral(true),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOLDOWN: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn repeat_within_the_cooldown_has_to_wait() {
+        let cooldowns = Cooldowns::new(COOLDOWN);
+        let start = Instant::now();
+
+        assert_eq!(cooldowns.try_start(1, "leaderboard", start), None);
+        assert_eq!(cooldowns.try_start(1, "leaderboard", start + Duration::from_secs(4)), Some(Duration::from_secs(6)));
+        assert_eq!(cooldowns.try_start(1, "leaderboard", start + COOLDOWN), None);
+    }
+
+    #[test]
+    fn cooldowns_are_per_user_and_command() {
+        let cooldowns = Cooldowns::new(COOLDOWN);
+        let start = Instant::now();
+
+        assert_eq!(cooldowns.try_start(1, "leaderboard", start), None);
+        assert_eq!(cooldowns.try_start(2, "leaderboard", start), None);
+        assert_eq!(cooldowns.try_start(1, "vs", start), None);
+    }
+
+    #[test]
+    fn other_commands_and_a_zero_cooldown_are_never_throttled() {
+        let start = Instant::now();
+
+        let cooldowns = Cooldowns::new(COOLDOWN);
+        cooldowns.try_start(1, "play", start);
+        assert_eq!(cooldowns.try_start(1, "play", start), None);
+
+        let disabled = Cooldowns::new(Duration::ZERO);
+        disabled.try_start(1, "leaderboard", start);
+        assert_eq!(disabled.try_start(1, "leaderboard", start), None);
+    }
+}
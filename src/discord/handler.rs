@@ -1,35 +1,247 @@
-use std::env;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    str::FromStr,
+    sync::{atomic::{AtomicBool, Ordering}, Mutex, OnceLock, RwLock},
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
+use futures::future::BoxFuture;
 use serenity::{
-    all::{Context, EventHandler, GuildId, Interaction, Message, Ready},
+    all::{ChannelId, Context, EventHandler, GuildId, Http, Interaction, Message, Ready, Webhook},
     async_trait,
 };
 
 use crate::{
-    discord::interactions::{self, autocompletes::track, commands::{play, refresh, update_time}, messages},
-    sheets::gsheet::GSheet,
+    discord::interactions::{self, messages::image::RecentUpload, autocompletes::track, commands::{audit_tracks, check, delete_record, describe, fix_drift, hall_of_shame, leaderboard, maintenance, mytimes, play, provider_stats, refresh, refresh_tracks, repost, schema, set_channel, set_track_color, show_track, store_mode, update_time, validate_records, vs}, messages},
+    config::env_flag,
+    discord::{
+        cooldown::{self, Cooldowns},
+        interactions::{components::component_name, messages::validation::records_channel_id},
+        output::{record_message_channels, resolve_record_webhook},
+        permissions::check_records_channel,
+    },
+    ocr::limiter::OcrLimiter,
+    sheets::{errors::DataFetchError, gsheet::GSheet, records::record::Record, settings::Settings},
 };
 
+const STARTUP_TRACK_ATTEMPTS: u32 = 4;
+const STARTUP_RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
 pub struct Handler {
     pub gsheet: GSheet,
-    pub track_name_list: Vec<String>,
+    /// The listed track names last read, see `Handler::track_names`.
+    pub track_name_list: RwLock<Vec<String>>,
+    /// When set, uploads that don't beat the driver's personal best are not stored.
+    pub store_only_pb: AtomicBool,
+    /// Records channel set at runtime through `/set_channel`, takes precedence over `CHANNEL_ID`.
+    pub records_channel: RwLock<Option<ChannelId>>,
+    /// Commands listed in `ENABLED_COMMANDS`, `None` means every command is enabled.
+    pub enabled_commands: Option<HashSet<String>>,
+    /// Guild membership per user and when it was checked, see `members::membership`.
+    pub member_cache: Mutex<HashMap<u64, (Instant, bool)>>,
+    /// Last stored screenshot per driver and track, see `image::duplicate_notice`.
+    pub recent_uploads: Mutex<HashMap<(u64, String), RecentUpload>>,
+    /// Bounds the uploads being read at once, see `OcrLimiter::from_env`.
+    pub ocr_limiter: OcrLimiter,
+    /// Writes are refused while set, through `MAINTENANCE_MODE` or `/maintenance`.
+    pub maintenance: AtomicBool,
+    /// Last runs of the heavy commands per user, see `Cooldowns::from_env`.
+    pub cooldowns: Cooldowns,
+    /// `RECORD_WEBHOOK_URL`, resolved once on the first ready, see `resolve_record_webhook`.
+    pub record_webhook: OnceLock<Webhook>,
 }
 
 impl Handler {
     pub async fn try_new(gsheet: GSheet) -> Result<Self> {
-        let track_name_list = gsheet
-            .tracks()
-            .get_all()
-            .await?
-            .into_iter()
-            .map(|t| t.name)
-            .collect();
+        let track_name_list = fetch_track_names(&gsheet).await?;
+        let store_only_pb = store_mode::initial_store_mode(
+            env_flag("STORE_ONLY_PB"),
+            env::var("RECORD_STORE_MODE").ok().as_deref(),
+        );
+        let records_channel = match gsheet.settings().get(Settings::RECORDS_CHANNEL_ID).await {
+            Ok(value) => value.and_then(|id| ChannelId::from_str(&id).ok()),
+            Err(why) => {
+                eprintln!("Could not read the records channel setting, using CHANNEL_ID: {why}");
+                None
+            }
+        };
         Ok(Handler {
             gsheet,
-            track_name_list,
+            track_name_list: RwLock::new(track_name_list),
+            store_only_pb: AtomicBool::new(store_only_pb),
+            records_channel: RwLock::new(records_channel),
+            enabled_commands: parse_enabled_commands(env::var("ENABLED_COMMANDS").ok()),
+            member_cache: Mutex::new(HashMap::new()),
+            recent_uploads: Mutex::new(HashMap::new()),
+            ocr_limiter: OcrLimiter::from_env(),
+            maintenance: AtomicBool::new(env_flag("MAINTENANCE_MODE")),
+            cooldowns: Cooldowns::from_env(),
+            record_webhook: OnceLock::new(),
         })
     }
+
+    /// Link to a record's upload, in `GUILD_ID` and the current records channel.
+    /// Uploads are only read from the records channel, but a record uploaded before
+    /// `/set_channel` moved it links to the new channel and won't resolve.
+    pub fn source_message_url(&self, record: &Record<'_>) -> Option<String> {
+        let guild_id = env::var("GUILD_ID").ok().and_then(|id| GuildId::from_str(&id).ok())?;
+        let channel_id = self.records_channel_id()?;
+        Some(record.source_message_url(guild_id, channel_id))
+    }
+
+    /// Where to look for a record's message, see `output::record_message_channels`.
+    pub fn record_message_channels(&self) -> Vec<ChannelId> {
+        let output_channel = self.record_webhook.get().and_then(|webhook| webhook.channel_id);
+        record_message_channels(output_channel, self.records_channel_id())
+    }
+
+    /// The records channel uploads are read from, see `records_channel_id`.
+    pub fn records_channel_id(&self) -> Option<ChannelId> {
+        let channel_override = *self
+            .records_channel
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        records_channel_id(channel_override).ok()
+    }
+
+    /// The listed track names, read through the tracks cache so tracks added to the
+    /// sheet show up within `TRACKS_REFRESH_SECS`. When the sheet can't be read the
+    /// names last read are used.
+    pub async fn track_names(&self) -> Vec<String> {
+        let tracks = self.gsheet.tracks();
+        match tracks.get_listed().await {
+            Ok(listed) => self.store_track_names(listed.into_iter().map(|t| t.name).collect()),
+            Err(why) => {
+                eprintln!("could not read the tracks, using the names last read: {why}");
+                self.track_name_list
+                    .read()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .clone()
+            }
+        }
+    }
+
+    /// Rereads the Tracks sheet right away, bypassing the cache. Returns the number of
+    /// listed tracks.
+    pub async fn reload_tracks(&self) -> Result<usize, DataFetchError> {
+        let tracks = self.gsheet.tracks();
+        tracks.refresh().await;
+        let listed = tracks.get_listed().await?;
+        let names = self.store_track_names(listed.into_iter().map(|t| t.name).collect());
+        Ok(names.len())
+    }
+
+    fn store_track_names(&self, names: Vec<String>) -> Vec<String> {
+        *self
+            .track_name_list
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = names.clone();
+        names
+    }
+
+    pub fn writes_paused(&self) -> bool {
+        self.maintenance.load(Ordering::Relaxed)
+    }
+
+    pub fn command_enabled(&self, name: &str) -> bool {
+        command_enabled_in(self.enabled_commands.as_ref(), name)
+    }
+}
+
+#[cfg(test)]
+impl Handler {
+    /// A handler over `gsheet` with nothing read from it yet and the env defaults.
+    pub fn with_gsheet(gsheet: GSheet) -> Self {
+        Handler {
+            gsheet,
+            track_name_list: RwLock::new(vec![]),
+            store_only_pb: AtomicBool::new(false),
+            records_channel: RwLock::new(None),
+            enabled_commands: None,
+            member_cache: Mutex::new(HashMap::new()),
+            recent_uploads: Mutex::new(HashMap::new()),
+            ocr_limiter: OcrLimiter::from_env(),
+            maintenance: AtomicBool::new(false),
+            cooldowns: Cooldowns::from_env(),
+            record_webhook: OnceLock::new(),
+        }
+    }
+}
+
+type RegisterFn = for<'a> fn(&'a Http, GuildId) -> BoxFuture<'a, serenity::Result<()>>;
+
+/// Every slash command with its registration, see `ready`.
+const COMMANDS: &[(&str, RegisterFn)] = &[
+    ("play", |http, guild| Box::pin(play::register(http, guild))),
+    ("refresh", |http, guild| Box::pin(refresh::register(http, guild))),
+    ("update_time", |http, guild| Box::pin(update_time::register(http, guild))),
+    ("store_mode", |http, guild| Box::pin(store_mode::register(http, guild))),
+    ("check", |http, guild| Box::pin(check::register(http, guild))),
+    ("describe", |http, guild| Box::pin(describe::register(http, guild))),
+    ("set_channel", |http, guild| Box::pin(set_channel::register(http, guild))),
+    ("show_track", |http, guild| Box::pin(show_track::register(http, guild))),
+    ("set_track_color", |http, guild| Box::pin(set_track_color::register(http, guild))),
+    ("vs", |http, guild| Box::pin(vs::register(http, guild))),
+    ("mytimes", |http, guild| Box::pin(mytimes::register(http, guild))),
+    ("maintenance", |http, guild| Box::pin(maintenance::register(http, guild))),
+    ("schema", |http, guild| Box::pin(schema::register(http, guild))),
+    ("leaderboard", |http, guild| Box::pin(leaderboard::register(http, guild))),
+    ("hall_of_shame", |http, guild| Box::pin(hall_of_shame::register(http, guild))),
+    ("fix_drift", |http, guild| Box::pin(fix_drift::register(http, guild))),
+    ("validate_records", |http, guild| Box::pin(validate_records::register(http, guild))),
+    ("delete_record", |http, guild| Box::pin(delete_record::register(http, guild))),
+    ("provider_stats", |http, guild| Box::pin(provider_stats::register(http, guild))),
+    ("refresh_tracks", |http, guild| Box::pin(refresh_tracks::register(http, guild))),
+    ("repost", |http, guild| Box::pin(repost::register(http, guild))),
+    ("audit_tracks", |http, guild| Box::pin(audit_tracks::register(http, guild))),
+];
+
+/// `enabled` is the parsed `ENABLED_COMMANDS`, `None` enables every command.
+pub fn command_enabled_in(enabled: Option<&HashSet<String>>, name: &str) -> bool {
+    enabled.is_none_or(|enabled| enabled.contains(name))
+}
+
+/// Whether `name` is one of the `COMMANDS` and enabled.
+fn is_registered(enabled: Option<&HashSet<String>>, name: &str) -> bool {
+    enabled_commands(enabled).any(|(registered, _)| *registered == name)
+}
+
+fn enabled_commands(enabled: Option<&HashSet<String>>) -> impl Iterator<Item = &'static (&'static str, RegisterFn)> + '_ {
+    COMMANDS.iter().filter(move |(name, _)| command_enabled_in(enabled, name))
+}
+
+/// Reads the track names, retrying with a doubling delay so a momentary Sheets
+/// hiccup during startup doesn't keep the bot from booting.
+async fn fetch_track_names(gsheet: &GSheet) -> Result<Vec<String>, DataFetchError> {
+    let mut delay = STARTUP_RETRY_BASE_DELAY;
+    let mut attempt = 1;
+    loop {
+        match gsheet.tracks().get_listed().await {
+            Ok(tracks) => return Ok(tracks.into_iter().map(|t| t.name).collect()),
+            Err(why) if attempt < STARTUP_TRACK_ATTEMPTS => {
+                eprintln!(
+                    "Fetching tracks failed (attempt {attempt}/{STARTUP_TRACK_ATTEMPTS}), retrying in {}s: {why}",
+                    delay.as_secs()
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(why) => return Err(why),
+        }
+    }
+}
+
+/// Parses a comma separated list of command names. An unset or blank list enables everything.
+pub fn parse_enabled_commands(raw: Option<String>) -> Option<HashSet<String>> {
+    let enabled: HashSet<String> = raw?
+        .split(',')
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+    if enabled.is_empty() { None } else { Some(enabled) }
 }
 
 #[async_trait]
@@ -43,9 +255,34 @@ impl EventHandler for Handler {
             .expect("GUILD_ID must be u64");
         let guild = GuildId::new(guild_id);
 
-        play::register(&ctx.http, guild).await.unwrap();
-        refresh::register(&ctx.http, guild).await.unwrap();
-        update_time::register(&ctx.http, guild).await.unwrap();
+        for (name, register) in enabled_commands(self.enabled_commands.as_ref()) {
+            if let Err(why) = register(&ctx.http, guild).await {
+                eprintln!("could not register /{name}: {why}");
+            }
+        }
+
+        if self.record_webhook.get().is_none()
+            && let Some(webhook) = resolve_record_webhook(&ctx.http, ready.application.id).await
+        {
+            let _ = self.record_webhook.set(webhook);
+        }
+
+        let records_channel = *self
+            .records_channel
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match records_channel_id(records_channel) {
+            Ok(channel_id) => check_records_channel(&ctx, guild, ready.user.id, channel_id).await,
+            Err(_) => eprintln!("WARNING: no valid records channel is configured, set CHANNEL_ID or use /set_channel"),
+        }
+
+        // Guild commands persist on Discord's side, so drop the ones that were disabled
+        // or renamed since.
+        if let Ok(existing) = guild.get_commands(&ctx.http).await {
+            for command in existing.iter().filter(|c| !is_registered(self.enabled_commands.as_ref(), &c.name)) {
+                let _ = guild.delete_command(&ctx.http, command.id).await;
+            }
+        }
     }
 
     async fn message(&self, ctx: Context, msg: Message) {
@@ -55,29 +292,105 @@ impl EventHandler for Handler {
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         match interaction {
-            Interaction::Command(cmd) => match cmd.data.name.as_str() {
-                "play" => play::handle(&ctx, &cmd, &self).await,
-                "refresh" => refresh::handle(&ctx, &cmd, &self).await,
-                "update_time" => update_time::handle(&ctx, &cmd, &self).await,
-                _ => {}
-            },
-            Interaction::Autocomplete(ac) => match ac
-                .data
-                .options
-                .get(0)
-                .and_then(|opt| Some(opt.name.clone()))
-                .unwrap_or_default()
-                .as_str()
+            Interaction::Command(cmd) | Interaction::Autocomplete(cmd)
+                if !self.command_enabled(&cmd.data.name) => {}
+            Interaction::Command(cmd) if maintenance::refuses(self.writes_paused(), &cmd.data.name) => {
+                let _ = cmd.create_response(&ctx.http, maintenance::paused_response()).await;
+            }
+            Interaction::Component(act)
+                if maintenance::refuses(self.writes_paused(), component_name(&act.data.custom_id)) =>
             {
-                "track" => track::handle(&ctx, &ac, &self).await,
-                _ => {}
-            },
-            Interaction::Component(act) => match act.data.custom_id.as_str() {
+                let _ = act.create_response(&ctx.http, maintenance::paused_response()).await;
+            }
+            Interaction::Modal(modal)
+                if maintenance::refuses(self.writes_paused(), &modal.data.custom_id) =>
+            {
+                let _ = modal.create_response(&ctx.http, maintenance::paused_response()).await;
+            }
+            Interaction::Command(cmd) => {
+                if let Some(wait) = self.cooldowns.try_start(cmd.user.id.get(), &cmd.data.name, Instant::now()) {
+                    let _ = cmd.create_response(&ctx.http, cooldown::wait_response(wait)).await;
+                    return;
+                }
+                match cmd.data.name.as_str() {
+                    "play" => play::handle(&ctx, &cmd, &self).await,
+                    "refresh" => refresh::handle(&ctx, &cmd, &self).await,
+                    "update_time" => update_time::handle(&ctx, &cmd, &self).await,
+                    "store_mode" => store_mode::handle(&ctx, &cmd, &self).await,
+                    "check" => check::handle(&ctx, &cmd, &self).await,
+                    "describe" => describe::handle(&ctx, &cmd, &self).await,
+                    "set_channel" => set_channel::handle(&ctx, &cmd, &self).await,
+                    "show_track" => show_track::handle(&ctx, &cmd, &self).await,
+                    "set_track_color" => set_track_color::handle(&ctx, &cmd, &self).await,
+                    "vs" => vs::handle(&ctx, &cmd, &self).await,
+                    "mytimes" => mytimes::handle(&ctx, &cmd, &self).await,
+                    "maintenance" => maintenance::handle(&ctx, &cmd, &self).await,
+                    "schema" => schema::handle(&ctx, &cmd, &self).await,
+                    "validate_records" => validate_records::handle(&ctx, &cmd, &self).await,
+                    "delete_record" => delete_record::handle(&ctx, &cmd, &self).await,
+                    "provider_stats" => provider_stats::handle(&ctx, &cmd, &self).await,
+                    "refresh_tracks" => refresh_tracks::handle(&ctx, &cmd, &self).await,
+                    "repost" => repost::handle(&ctx, &cmd, &self).await,
+                    "audit_tracks" => audit_tracks::handle(&ctx, &cmd, &self).await,
+                    "fix_drift" => fix_drift::handle(&ctx, &cmd, &self).await,
+                    "leaderboard" => leaderboard::handle(&ctx, &cmd, &self).await,
+                    "hall_of_shame" => hall_of_shame::handle(&ctx, &cmd, &self).await,
+                    _ => {}
+                }
+            }
+            Interaction::Autocomplete(ac) => {
+                let option_name = ac
+                    .data
+                    .options
+                    .first()
+                    .map(|opt| opt.name.clone())
+                    .unwrap_or_default();
+                if option_name == "track" {
+                    track::handle(&ctx, &ac, &self).await
+                }
+            }
+            Interaction::Component(act) => match component_name(&act.data.custom_id) {
+                "record_approve" => interactions::components::record::approve::handle(&ctx, &act, &self).await,
                 "record_change_driver" => interactions::components::record::change_driver::handle(&ctx, &act, &self).await,
                 "record_select_driver" => interactions::components::record::select_driver::handle(&ctx, &act, &self).await,
+                "record_change_track" => interactions::components::record::change_track::handle(&ctx, &act, &self).await,
+                "record_select_track" => interactions::components::record::select_track::handle(&ctx, &act, &self).await,
+                "record_change_time" => interactions::components::record::change_time::handle(&ctx, &act, &self).await,
                 _ => {}
             },
+            Interaction::Modal(modal) if modal.data.custom_id == "record_submit_time" => {
+                interactions::components::record::submit_time::handle(&ctx, &modal, &self).await
+            }
             _ => {}
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registered(enabled: Option<&HashSet<String>>) -> Vec<&'static str> {
+        enabled_commands(enabled).map(|(name, _)| *name).collect()
+    }
+
+    #[test]
+    fn disabled_commands_are_not_registered() {
+        let enabled = parse_enabled_commands(Some(" Play, vs ,".to_string()));
+        assert_eq!(registered(enabled.as_ref()), vec!["play", "vs"]);
+        assert!(!command_enabled_in(enabled.as_ref(), "repost"));
+    }
+
+    #[test]
+    fn every_command_is_enabled_without_a_list() {
+        assert_eq!(parse_enabled_commands(Some(" , ".to_string())), None);
+        assert_eq!(registered(None).len(), COMMANDS.len());
+        assert!(command_enabled_in(None, "repost"));
+    }
+
+    #[test]
+    fn renamed_commands_are_not_registered() {
+        assert!(is_registered(None, "show_track"));
+        assert!(!is_registered(None, "showtrack"));
+    }
+}
@@ -10,7 +10,8 @@ pub async fn handle(ctx: &Context, ac: &CommandInteraction, handler: &Handler) {
         .to_lowercase();
 
     let choices: Vec<AutocompleteChoice> = handler
-        .track_name_list
+        .track_names()
+        .await
         .iter()
         .filter(|n| n.to_lowercase().contains(&typed))
         .take(25)
@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use futures::future::join_all;
+use reqwest::{Client, header};
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    EditInteractionResponse, GuildId, Http, Permissions,
+};
+
+use crate::{
+    config::env_parse,
+    discord::{handler::Handler, templates::track::is_valid_icon_url},
+};
+
+// Discord rejects message content over 2000 characters.
+const MAX_RESPONSE_LEN: usize = 1900;
+const HEAD_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditTracksCmdError {
+    #[error("Something went wrong while fetching the tracks")]
+    FetchTracks,
+}
+
+/// What is wrong with a track's icon, if anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IconCheck {
+    Ok,
+    /// Not an absolute http(s) URL, the embed shows the fallback icon.
+    InvalidUrl,
+    Unreachable(String),
+    BadStatus(u16),
+    NotAnImage(String),
+    TooLarge(u64),
+}
+
+impl IconCheck {
+    fn describe(&self) -> String {
+        match self {
+            IconCheck::Ok => "ok".to_string(),
+            IconCheck::InvalidUrl => "not a valid http(s) URL, the fallback icon is shown".to_string(),
+            IconCheck::Unreachable(why) => format!("could not be fetched: {why}"),
+            IconCheck::BadStatus(status) => format!("answered with HTTP {status}"),
+            IconCheck::NotAnImage(content_type) => format!("is {content_type}, not an image"),
+            IconCheck::TooLarge(bytes) => format!("is {} KB, which is large for an embed", bytes / 1024),
+        }
+    }
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let response_content = match audit_tracks_command(cmd, handler).await {
+        Ok(findings) => format_findings(&findings),
+        Err(error) => error.to_string(),
+    };
+
+    let _ = cmd
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(response_content))
+        .await;
+}
+
+/// Checks the icon URL of every listed track. With `fetch_icons` each icon also gets a
+/// HEAD request, to spot missing, non-image and oversized files. Nothing is changed.
+pub async fn audit_tracks_command(
+    cmd: &CommandInteraction,
+    handler: &Handler,
+) -> Result<Vec<(String, IconCheck)>, AuditTracksCmdError> {
+    let fetch_icons = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "fetch_icons")
+        .and_then(|opt| opt.value.as_bool())
+        .unwrap_or(false);
+
+    let tracks = handler.gsheet.tracks();
+    let tracks = tracks
+        .get_listed()
+        .await
+        .map_err(|_| AuditTracksCmdError::FetchTracks)?;
+
+    // TRACK_ICON_MAX_BYTES (default 1 MB) is the size above which an icon is reported.
+    let max_bytes = env_parse("TRACK_ICON_MAX_BYTES", 1024 * 1024u64);
+    let client = Client::builder().timeout(HEAD_TIMEOUT).build().ok();
+
+    let checks = tracks.into_iter().map(|track| {
+        let client = client.clone();
+        async move {
+            let check = if !is_valid_icon_url(&track.icon_url) {
+                IconCheck::InvalidUrl
+            } else {
+                match (&client, fetch_icons) {
+                    (Some(client), true) => head_icon(client, track.icon_url.trim(), max_bytes).await,
+                    _ => IconCheck::Ok,
+                }
+            };
+            (track.name, check)
+        }
+    });
+
+    Ok(join_all(checks).await)
+}
+
+async fn head_icon(client: &Client, url: &str, max_bytes: u64) -> IconCheck {
+    let response = match client.head(url).send().await {
+        Ok(response) => response,
+        Err(why) => return IconCheck::Unreachable(why.to_string()),
+    };
+
+    let headers = response.headers();
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let content_length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+
+    classify_icon(response.status().as_u16(), content_type, content_length, max_bytes)
+}
+
+/// Judges an icon by the headers of its HEAD response. Missing headers aren't
+/// reported, plenty of hosts leave them out of HEAD responses.
+pub fn classify_icon(
+    status: u16,
+    content_type: Option<&str>,
+    content_length: Option<u64>,
+    max_bytes: u64,
+) -> IconCheck {
+    if !(200..300).contains(&status) {
+        return IconCheck::BadStatus(status);
+    }
+    if let Some(content_type) = content_type
+        && !content_type.trim().to_ascii_lowercase().starts_with("image/")
+    {
+        return IconCheck::NotAnImage(content_type.to_string());
+    }
+    match content_length {
+        Some(bytes) if bytes > max_bytes => IconCheck::TooLarge(bytes),
+        _ => IconCheck::Ok,
+    }
+}
+
+pub fn format_findings(findings: &[(String, IconCheck)]) -> String {
+    let problems: Vec<&(String, IconCheck)> =
+        findings.iter().filter(|(_, check)| *check != IconCheck::Ok).collect();
+    if problems.is_empty() {
+        return format!("All {} track icons look good!", findings.len());
+    }
+
+    let mut content = format!(
+        "Checked {} tracks, {} with icon problems:\n",
+        findings.len(),
+        problems.len()
+    );
+    for (track_name, check) in problems {
+        let line = format!("{track_name}: icon {}", check.describe());
+        if content.len() + line.len() + 1 > MAX_RESPONSE_LEN {
+            content.push('…');
+            break;
+        }
+        content.push_str(&line);
+        content.push('\n');
+    }
+    content
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let fetch_icons_option = CreateCommandOption::new(
+        CommandOptionType::Boolean,
+        "fetch_icons",
+        "Also request every icon to check its type and size",
+    );
+
+    let audit_tracks_command = CreateCommand::new("audit_tracks")
+        .description("Check the track icons for problems.")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(fetch_icons_option);
+
+    guild_id.create_command(http, audit_tracks_command).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_BYTES: u64 = 256 * 1024;
+
+    #[test]
+    fn small_images_pass() {
+        assert_eq!(classify_icon(200, Some("image/png"), Some(4096), MAX_BYTES), IconCheck::Ok);
+        assert_eq!(classify_icon(204, None, None, MAX_BYTES), IconCheck::Ok);
+    }
+
+    #[test]
+    fn bad_status_type_and_size_are_reported() {
+        assert_eq!(classify_icon(404, Some("image/png"), None, MAX_BYTES), IconCheck::BadStatus(404));
+        assert_eq!(
+            classify_icon(200, Some("text/html; charset=utf-8"), None, MAX_BYTES),
+            IconCheck::NotAnImage("text/html; charset=utf-8".to_string())
+        );
+        assert_eq!(classify_icon(200, Some("IMAGE/GIF"), Some(MAX_BYTES + 1), MAX_BYTES), IconCheck::TooLarge(MAX_BYTES + 1));
+    }
+
+    #[test]
+    fn findings_only_list_problems() {
+        let findings = vec![
+            ("Mario Circuit".to_string(), IconCheck::Ok),
+            ("Rainbow Road".to_string(), IconCheck::BadStatus(404)),
+        ];
+        assert_eq!(
+            format_findings(&findings),
+            "Checked 2 tracks, 1 with icon problems:\nRainbow Road: icon answered with HTTP 404\n"
+        );
+        assert_eq!(format_findings(&findings[..1]), "All 1 track icons look good!");
+    }
+}
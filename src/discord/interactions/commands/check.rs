@@ -0,0 +1,101 @@
+use serenity::all::{
+    CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, CreateCommand,
+    CreateCommandOption, EditInteractionResponse, GuildId, Http,
+};
+
+use crate::{
+    discord::handler::Handler,
+    ocr::{extract_time_detailed, ExtractError, ExtractOutcome},
+    time_format,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CheckCmdError {
+    #[error("Command option was missing: {0}")]
+    MissingOption(&'static str),
+
+    #[error("Command option was of an incorrect data type: {0}")]
+    InvalidOptionType(&'static str),
+
+    #[error("Something went wrong while downloading the image")]
+    DownloadFailed,
+
+    #[error("That doesn't look like a time trial result, the yellow result card wasn't found")]
+    NotResultScreen,
+
+    #[error("No time could be read from that image: {0}")]
+    Extract(#[from] ExtractError),
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, _handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let outcome = check_command(cmd).await;
+
+    let response_content = match outcome {
+        Ok(outcome) => format!(
+            "Detected time: {} (read by {} from `{}`)\nNothing was stored, use /play and upload the screenshot in the records channel to submit it.",
+            time_format::format(outcome.duration),
+            outcome.provider,
+            outcome.raw_text.replace('`', "'")
+        ),
+        Err(error) => error.to_string(),
+    };
+
+    let _ = cmd
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(response_content))
+        .await;
+}
+
+/// Runs the same extraction as an upload, but only reports the result: no player
+/// selection is needed and neither Players nor Records are touched.
+pub async fn check_command(cmd: &CommandInteraction) -> Result<ExtractOutcome, CheckCmdError> {
+    let attachment_id = match &cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "image")
+        .ok_or(CheckCmdError::MissingOption("image"))?
+        .value
+    {
+        CommandDataOptionValue::Attachment(id) => *id,
+        _ => return Err(CheckCmdError::InvalidOptionType("image")),
+    };
+
+    let attachment = cmd
+        .data
+        .resolved
+        .attachments
+        .get(&attachment_id)
+        .ok_or(CheckCmdError::MissingOption("image"))?;
+
+    let bytes = attachment
+        .download()
+        .await
+        .map_err(|_| CheckCmdError::DownloadFailed)?;
+
+    match extract_time_detailed(&bytes).await {
+        Ok(outcome) => Ok(outcome),
+        Err(ExtractError::NotResultScreen | ExtractError::YellowMissing) => {
+            Err(CheckCmdError::NotResultScreen)
+        }
+        Err(why) => Err(why.into()),
+    }
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let check_command_option = CreateCommandOption::new(
+        CommandOptionType::Attachment,
+        "image",
+        "The screenshot to read",
+    )
+    .required(true);
+
+    let check_command = CreateCommand::new("check")
+        .description("Preview the time read from a screenshot without storing anything.")
+        .add_option(check_command_option);
+
+    guild_id.create_command(http, check_command).await?;
+
+    Ok(())
+}
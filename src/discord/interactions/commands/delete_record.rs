@@ -0,0 +1,112 @@
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    EditInteractionResponse, GuildId, Http, MessageId, Permissions,
+};
+
+use crate::discord::{
+    handler::Handler,
+    output::delete_record_message_in,
+    permissions::is_admin,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DeleteRecordCmdError {
+    #[error("Command option was missing: {0}")]
+    MissingOption(&'static str),
+
+    #[error("Command option was of an incorrect data type: {0}")]
+    InvalidOptionType(&'static str),
+
+    #[error("Something went wrong while fetching the record")]
+    FetchRecord,
+
+    #[error("The record was not found")]
+    RecordNotFound,
+
+    #[error("This record is locked and can only be deleted by an admin")]
+    RecordLocked,
+
+    #[error("Something went wrong while deleting the record")]
+    DeleteFailed,
+
+    #[error("The record was deleted, but its message could not be removed")]
+    MessageDeleteFailed,
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let response_content = match delete_record_command(ctx, cmd, handler).await {
+        Ok(()) => "Record deleted successfully!".to_string(),
+        Err(error) => error.to_string(),
+    };
+
+    let _ = cmd
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(response_content))
+        .await;
+}
+
+/// Deletes the record's row, then the bot's message for it. The message is looked up
+/// where records are posted, see `record_message_channels`, not where the command is
+/// used.
+pub async fn delete_record_command(
+    ctx: &Context,
+    cmd: &CommandInteraction,
+    handler: &Handler,
+) -> Result<(), DeleteRecordCmdError> {
+    let bot_message_id = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "message_id")
+        .ok_or(DeleteRecordCmdError::MissingOption("message_id"))?
+        .value
+        .as_str()
+        .ok_or(DeleteRecordCmdError::InvalidOptionType("message_id"))?
+        .parse::<u64>()
+        .map_err(|_| DeleteRecordCmdError::InvalidOptionType("message_id"))?;
+
+    let records = handler.gsheet.records();
+
+    let record = records
+        .get_by_bot_message_id(bot_message_id)
+        .await
+        .map_err(|_| DeleteRecordCmdError::FetchRecord)?
+        .ok_or(DeleteRecordCmdError::RecordNotFound)?;
+
+    if record.is_locked_for(is_admin(cmd.member.as_deref())) {
+        return Err(DeleteRecordCmdError::RecordLocked);
+    }
+
+    records
+        .delete(&record)
+        .await
+        .map_err(|_| DeleteRecordCmdError::DeleteFailed)?;
+
+    delete_record_message_in(
+        &ctx.http,
+        handler.record_webhook.get(),
+        &handler.record_message_channels(),
+        MessageId::new(bot_message_id),
+    )
+    .await
+    .map_err(|_| DeleteRecordCmdError::MessageDeleteFailed)
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let message_option = CreateCommandOption::new(
+        CommandOptionType::String,
+        "message_id",
+        "Enter the id of the message of the record that needs to be deleted",
+    )
+    .required(true);
+
+    let delete_record_command = CreateCommand::new("delete_record")
+        .description("Delete a record and its message")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(message_option);
+
+    guild_id.create_command(http, delete_record_command).await?;
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use serenity::all::{
+    CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, CreateCommand,
+    CreateCommandOption, EditInteractionResponse, GuildId, Http, Permissions,
+};
+
+use crate::{discord::handler::Handler, ocr::describe_image};
+
+// Discord rejects message content over 2000 characters.
+const MAX_RESPONSE_LEN: usize = 1900;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DescribeCmdError {
+    #[error("Command option was missing: {0}")]
+    MissingOption(&'static str),
+
+    #[error("Command option was of an incorrect data type: {0}")]
+    InvalidOptionType(&'static str),
+
+    #[error("Something went wrong while downloading the image")]
+    DownloadFailed,
+
+    #[error("The provider could not describe the image: {0}")]
+    Provider(String),
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, _handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let outcome = describe_command(cmd).await;
+
+    let response_content = match outcome {
+        Ok(description) => truncate(&description, MAX_RESPONSE_LEN),
+        Err(error) => error.to_string(),
+    };
+
+    let _ = cmd
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(response_content))
+        .await;
+}
+
+pub async fn describe_command(cmd: &CommandInteraction) -> Result<String, DescribeCmdError> {
+    let attachment_id = match &cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "image")
+        .ok_or(DescribeCmdError::MissingOption("image"))?
+        .value
+    {
+        CommandDataOptionValue::Attachment(id) => *id,
+        _ => return Err(DescribeCmdError::InvalidOptionType("image")),
+    };
+
+    let attachment = cmd
+        .data
+        .resolved
+        .attachments
+        .get(&attachment_id)
+        .ok_or(DescribeCmdError::MissingOption("image"))?;
+
+    let bytes = attachment
+        .download()
+        .await
+        .map_err(|_| DescribeCmdError::DownloadFailed)?;
+
+    describe_image(&bytes)
+        .await
+        .map_err(|e| DescribeCmdError::Provider(e.to_string()))
+}
+
+fn truncate(text: &str, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text.to_string();
+    }
+    let mut end = max_len;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…", &text[..end])
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let describe_command_option = CreateCommandOption::new(
+        CommandOptionType::Attachment,
+        "image",
+        "The screenshot to describe",
+    )
+    .required(true);
+
+    let describe_command = CreateCommand::new("describe")
+        .description("Ask the OCR provider what it sees in an image.")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(describe_command_option);
+
+    guild_id.create_command(http, describe_command).await?;
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use serenity::all::{
+    CommandInteraction, Context, CreateCommand, EditInteractionResponse, GuildId, Http,
+    Permissions,
+};
+use serde_json::Value;
+
+use crate::{
+    discord::handler::Handler,
+    sheets::{
+        gsheet::CellWrite,
+        records::{record::Record, Records},
+        utils::{duration_to_value, serial_value_drifted, timestamp_to_value, DataRanges},
+    },
+};
+
+// Anything below this is floating point noise rather than drift worth a write.
+const DRIFT_TOLERANCE_SECS: f64 = 0.000_1;
+
+// Cells corrected per Sheets request, which keeps a request well below its size limit.
+const WRITE_BATCH_SIZE: usize = 500;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FixDriftCmdError {
+    #[error("Something went wrong while fetching the records")]
+    FetchRecords,
+
+    #[error("Fixed {0} cell(s) before a write failed, please run the command again")]
+    WriteFailed(usize),
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let outcome = fix_drift_command(handler).await;
+
+    let response_content = match outcome {
+        Ok(0) => "No drifted values found.".to_string(),
+        Ok(count) => format!("Corrected {count} drifted cell(s)."),
+        Err(error) => error.to_string(),
+    };
+
+    let _ = cmd
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(response_content))
+        .await;
+}
+
+pub async fn fix_drift_command(handler: &Handler) -> Result<usize, FixDriftCmdError> {
+    let rows = handler
+        .gsheet
+        .records()
+        .get_all_raw()
+        .await
+        .map_err(|_| FixDriftCmdError::FetchRecords)?;
+
+    let writes: Vec<CellWrite> = rows
+        .into_iter()
+        .flat_map(|(rownum, row)| drifted_cells(rownum, row, handler))
+        .collect();
+
+    let mut corrected = 0;
+    for batch in writes.chunks(WRITE_BATCH_SIZE) {
+        handler
+            .gsheet
+            .write_cells(batch.to_vec())
+            .await
+            .map_err(|_| FixDriftCmdError::WriteFailed(corrected))?;
+        corrected += batch.len();
+    }
+
+    Ok(corrected)
+}
+
+/// The canonical values of the duration and timestamp cells of a row whose stored
+/// serial numbers drifted. Rows that don't parse are left for `/validate_records`.
+pub fn drifted_cells(
+    rownum: usize,
+    row: Vec<Value>,
+    handler: &Handler,
+) -> Vec<CellWrite> {
+    let stored_timestamp = row.get(2).cloned().unwrap_or_default();
+    let stored_duration = row.get(5).cloned().unwrap_or_default();
+
+    let Ok(record) = Record::from_row(rownum, row, &handler.gsheet) else {
+        return vec![];
+    };
+
+    let mut cells = vec![];
+    if let Ok(canonical) = timestamp_to_value(record.report_timestamp)
+        && serial_value_drifted(&stored_timestamp, &canonical, DRIFT_TOLERANCE_SECS)
+    {
+        cells.push(Records::cell_write(rownum, record.bot_message_id, Records::REPORT_TIMESTAMP_COLUMN, canonical));
+    }
+    if let Ok(canonical) = duration_to_value(record.race_duration)
+        && serial_value_drifted(&stored_duration, &canonical, DRIFT_TOLERANCE_SECS)
+    {
+        cells.push(Records::cell_write(rownum, record.bot_message_id, Records::RACE_DURATION_COLUMN, canonical));
+    }
+    cells
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let fix_drift_command = CreateCommand::new("fix_drift")
+        .description("Round drifted times and timestamps in the Records sheet.")
+        .default_member_permissions(Permissions::ADMINISTRATOR);
+
+    guild_id.create_command(http, fix_drift_command).await?;
+
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use std::cmp::Reverse;
+
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    EditInteractionResponse, GuildId, Http,
+};
+
+use crate::{
+    discord::{handler::Handler, templates::leaderboard::send_leaderboard},
+    sheets::records::{approval_required, record::Record},
+};
+
+const HALL_OF_SHAME_SIZE: usize = 10;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HallOfShameCmdError {
+    #[error("Command option was missing: {0}")]
+    MissingOption(&'static str),
+
+    #[error("Something went wrong while fetching the records")]
+    FetchRecords,
+
+    #[error("No records on {0} yet, nothing to be ashamed of!")]
+    NoRecords(String),
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer(&ctx.http).await;
+
+    match hall_of_shame_command(cmd, handler).await {
+        Ok((title, records)) => send_leaderboard(ctx, cmd, handler, &title, &records).await,
+        Err(error) => {
+            let _ = cmd
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(error.to_string()))
+                .await;
+        }
+    }
+}
+
+pub async fn hall_of_shame_command<'a>(
+    cmd: &CommandInteraction,
+    handler: &'a Handler,
+) -> Result<(String, Vec<Record<'a>>), HallOfShameCmdError> {
+    let track_name = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "track")
+        .and_then(|opt| opt.value.as_str())
+        .ok_or(HallOfShameCmdError::MissingOption("track"))?;
+
+    let approved_only = approval_required();
+    let records = handler
+        .gsheet
+        .records()
+        .get_by_track(track_name)
+        .await
+        .map_err(|_| HallOfShameCmdError::FetchRecords)?
+        .into_iter()
+        .filter(|r| r.is_visible(approved_only));
+
+    let slowest = slowest(records, HALL_OF_SHAME_SIZE);
+    if slowest.is_empty() {
+        return Err(HallOfShameCmdError::NoRecords(track_name.to_string()));
+    }
+
+    Ok((format!("{track_name} — hall of shame"), slowest))
+}
+
+/// The `limit` slowest records, slowest first. Every record counts, not just each
+/// driver's best; on equal times the earlier upload ranks first.
+pub fn slowest<'a>(records: impl IntoIterator<Item = Record<'a>>, limit: usize) -> Vec<Record<'a>> {
+    let mut records: Vec<Record<'a>> = records.into_iter().collect();
+    records.sort_by_key(|r| (Reverse(r.race_duration.as_millis()), r.report_timestamp));
+    records.truncate(limit);
+    records
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let track_option =
+        CreateCommandOption::new(CommandOptionType::String, "track", "Enter a track name")
+            .set_autocomplete(true)
+            .required(true);
+
+    let hall_of_shame_command = CreateCommand::new("hall_of_shame")
+        .description("Show the slowest times ever recorded on a track.")
+        .add_option(track_option);
+
+    guild_id.create_command(http, hall_of_shame_command).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::Value;
+    use serenity::all::Timestamp;
+
+    use super::*;
+    use crate::sheets::{gsheet::GSheet, records::record::NewRecord};
+
+    /// Records by bot message id, with their time and upload minute.
+    fn records<'a>(gsheet: &'a GSheet, runs: &[(u64, u64, u32)]) -> Vec<Record<'a>> {
+        runs.iter()
+            .map(|&(id, millis, minute)| {
+                let new_record = NewRecord {
+                    user_message_id: id,
+                    bot_message_id: id,
+                    report_timestamp: Timestamp::parse(&format!("2026-05-01T18:{minute:02}:00Z")).unwrap(),
+                    driver_user_id: 7,
+                    track_name: "Mario Bros. Circuit".to_string(),
+                    race_duration: Duration::from_millis(millis),
+                    category: None,
+                    approved: true,
+                    platform: None,
+                };
+                let values = Vec::<Value>::try_from(new_record).unwrap();
+                Record::from_row(id as usize + 1, values, gsheet).unwrap()
+            })
+            .collect()
+    }
+
+    fn ids(records: &[Record<'_>]) -> Vec<u64> {
+        records.iter().map(|r| r.bot_message_id).collect()
+    }
+
+    #[test]
+    fn slowest_come_first() {
+        let gsheet = GSheet::unreachable();
+        let records = records(&gsheet, &[(1, 83_000, 0), (2, 95_500, 1), (3, 88_250, 2)]);
+        assert_eq!(ids(&slowest(records, 10)), vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn equal_times_rank_the_earlier_upload_first() {
+        let gsheet = GSheet::unreachable();
+        let records = records(&gsheet, &[(1, 90_000, 5), (2, 90_000, 3), (3, 91_000, 9)]);
+        assert_eq!(ids(&slowest(records, 10)), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn result_is_cut_to_the_limit() {
+        let gsheet = GSheet::unreachable();
+        let runs: Vec<_> = (1..=15).map(|id| (id, 80_000 + id * 100, id as u32)).collect();
+        let shamed = slowest(records(&gsheet, &runs), HALL_OF_SHAME_SIZE);
+        assert_eq!(ids(&shamed), (6..=15).rev().collect::<Vec<_>>());
+        assert!(slowest(vec![], HALL_OF_SHAME_SIZE).is_empty());
+    }
+}
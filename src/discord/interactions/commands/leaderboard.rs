@@ -0,0 +1,158 @@
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    EditInteractionResponse, GuildId, Http,
+};
+
+use crate::{
+    discord::{handler::Handler, templates::leaderboard::send_leaderboard},
+    seasons::{find_season, read_seasons},
+    sheets::{
+        players::player::Selection,
+        records::{approval_required, best_per_driver, record::Record},
+    },
+};
+
+const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LeaderboardCmdError {
+    #[error("Command option was missing: {0}")]
+    MissingOption(&'static str),
+
+    #[error("Unknown season, known seasons are: {0}")]
+    UnknownSeason(String),
+
+    #[error("Something went wrong while fetching the records")]
+    FetchRecords,
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer(&ctx.http).await;
+
+    match leaderboard_command(cmd, handler).await {
+        Ok((title, records)) => send_leaderboard(ctx, cmd, handler, &title, &records).await,
+        Err(error) => {
+            let _ = cmd
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(error.to_string()))
+                .await;
+        }
+    }
+}
+
+/// Returns the leaderboard title and the ranked records of the track, limited to the
+/// season's window. Without a season every record counts ("all time").
+pub async fn leaderboard_command<'a>(
+    cmd: &CommandInteraction,
+    handler: &'a Handler,
+) -> Result<(String, Vec<Record<'a>>), LeaderboardCmdError> {
+    let track_name = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "track")
+        .and_then(|opt| opt.value.as_str())
+        .ok_or(LeaderboardCmdError::MissingOption("track"))?;
+    let season_name = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "season")
+        .and_then(|opt| opt.value.as_str());
+    let category = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "category")
+        .and_then(|opt| opt.value.as_str())
+        .map(str::trim)
+        .filter(|category| !category.is_empty());
+    let platform = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "platform")
+        .and_then(|opt| opt.value.as_str());
+    let track_label = Selection {
+        track_name: track_name.to_string(),
+        category: category.map(str::to_string),
+        platform: platform.map(str::to_string),
+    }
+    .label();
+
+    let records = handler.gsheet.records();
+    let (title, season_records) = match season_name {
+        None => (
+            format!("{track_label} — all time"),
+            records.get_all().await.map_err(|_| LeaderboardCmdError::FetchRecords)?,
+        ),
+        Some(season_name) => {
+            let seasons = read_seasons();
+            let (start, end) = find_season(&seasons, season_name)
+                .and_then(|season| season.bounds())
+                .ok_or_else(|| {
+                    let known: Vec<&str> = seasons.iter().map(|s| s.name.as_str()).collect();
+                    LeaderboardCmdError::UnknownSeason(if known.is_empty() {
+                        "none configured".to_string()
+                    } else {
+                        known.join(", ")
+                    })
+                })?;
+            (
+                format!("{track_label} — {season_name}"),
+                records
+                    .get_between(start, end)
+                    .await
+                    .map_err(|_| LeaderboardCmdError::FetchRecords)?,
+            )
+        }
+    };
+
+    let approved_only = approval_required();
+    let ranked = best_per_driver(
+        season_records
+            .into_iter()
+            .filter(|r| r.is_on(track_name, category) && r.is_visible(approved_only))
+            .filter(|r| platform.is_none_or(|platform| r.platform.as_deref() == Some(platform))),
+    )
+    .into_iter()
+    .take(LEADERBOARD_SIZE)
+    .collect();
+
+    Ok((title, ranked))
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let track_option =
+        CreateCommandOption::new(CommandOptionType::String, "track", "Enter a track name")
+            .set_autocomplete(true)
+            .required(true);
+    let season_option = CreateCommandOption::new(
+        CommandOptionType::String,
+        "season",
+        "Season name, leave empty for all time",
+    );
+
+    let leaderboard_command = CreateCommand::new("leaderboard")
+        .description("Show the fastest times on a track, optionally within a season.")
+        .add_option(track_option)
+        .add_option(season_option)
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "category",
+            "Category to rank, leave empty for uncategorised records",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "platform",
+                "Only rank times from this platform",
+            )
+            .add_string_choice("Console", "Console")
+            .add_string_choice("Emulator", "Emulator")
+            .add_string_choice("Cloud", "Cloud"),
+        );
+
+    guild_id.create_command(http, leaderboard_command).await?;
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use std::sync::atomic::Ordering;
+
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse, CreateInteractionResponseMessage, GuildId, Http, Permissions,
+};
+
+use crate::discord::handler::Handler;
+
+pub const PAUSED_MESSAGE: &str = "Records are temporarily paused, please try again later.";
+
+/// Commands and buttons that write to the spreadsheet, refused while paused.
+/// Reads such as leaderboards keep working.
+pub const WRITE_INTERACTIONS: &[&str] = &[
+    "play",
+    "update_time",
+    "set_channel",
+    "set_track_color",
+    "fix_drift",
+    "delete_record",
+    "repost",
+    "record_approve",
+    "record_select_driver",
+    "record_select_track",
+    "record_submit_time",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum MaintenanceCmdError {
+    #[error("Command option was missing: {0}")]
+    MissingOption(&'static str),
+
+    #[error("Unknown maintenance mode, expected `on` or `off`")]
+    UnknownMode,
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let outcome = maintenance_command(cmd, handler);
+
+    let response_content = match outcome {
+        Ok(true) => "Maintenance mode is on, nothing will be written until it is turned off.".to_string(),
+        Ok(false) => "Maintenance mode is off, records are stored again.".to_string(),
+        Err(error) => error.to_string(),
+    };
+
+    let _ = cmd
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(response_content)
+                    .ephemeral(true),
+            ),
+        )
+        .await;
+}
+
+/// Returns whether writes are now paused.
+pub fn maintenance_command(
+    cmd: &CommandInteraction,
+    handler: &Handler,
+) -> Result<bool, MaintenanceCmdError> {
+    let mode = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "mode")
+        .and_then(|opt| opt.value.as_str())
+        .ok_or(MaintenanceCmdError::MissingOption("mode"))?;
+
+    let paused = parse_maintenance_mode(mode).ok_or(MaintenanceCmdError::UnknownMode)?;
+    handler.maintenance.store(paused, Ordering::Relaxed);
+
+    Ok(paused)
+}
+
+pub fn parse_maintenance_mode(mode: &str) -> Option<bool> {
+    match mode.trim().to_ascii_lowercase().as_str() {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether the interaction has to be refused because writes are paused.
+pub fn refuses(paused: bool, interaction_name: &str) -> bool {
+    paused && WRITE_INTERACTIONS.contains(&interaction_name)
+}
+
+pub fn paused_response() -> CreateInteractionResponse {
+    CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(PAUSED_MESSAGE)
+            .ephemeral(true),
+    )
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let maintenance_command_option =
+        CreateCommandOption::new(CommandOptionType::String, "mode", "Pause or resume writes")
+            .add_string_choice("On", "on")
+            .add_string_choice("Off", "off")
+            .required(true);
+
+    let maintenance_command = CreateCommand::new("maintenance")
+        .description("Pause writing records, e.g. during sheet migrations.")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(maintenance_command_option);
+
+    guild_id.create_command(http, maintenance_command).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_are_on_and_off() {
+        assert_eq!(parse_maintenance_mode(" ON "), Some(true));
+        assert_eq!(parse_maintenance_mode("off"), Some(false));
+        assert_eq!(parse_maintenance_mode("pause"), None);
+    }
+
+    #[test]
+    fn only_writes_are_refused_while_paused() {
+        assert!(refuses(true, "update_time"));
+        assert!(!refuses(true, "leaderboard"));
+        assert!(!refuses(true, "maintenance"));
+        assert!(!refuses(false, "update_time"));
+    }
+}
@@ -1,3 +1,22 @@
+pub mod audit_tracks;
+pub mod check;
+pub mod delete_record;
+pub mod describe;
+pub mod fix_drift;
+pub mod hall_of_shame;
+pub mod leaderboard;
+pub mod maintenance;
+pub mod mytimes;
 pub mod play;
+pub mod provider_stats;
 pub mod refresh;
-pub mod update_time;
\ No newline at end of file
+pub mod refresh_tracks;
+pub mod repost;
+pub mod schema;
+pub mod set_channel;
+pub mod set_track_color;
+pub mod show_track;
+pub mod store_mode;
+pub mod update_time;
+pub mod validate_records;
+pub mod vs;
\ No newline at end of file
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use serenity::all::{
+    Colour, CommandInteraction, Context, CreateCommand, CreateEmbed, CreateEmbedFooter,
+    EditInteractionResponse, GuildId, Http,
+};
+
+use crate::{
+    discord::{
+        handler::Handler,
+        interactions::commands::vs::best_per_track,
+        templates::track::DEFAULT_COLOUR,
+    },
+    sheets::records::{approval_required, record::Record},
+    time_format,
+};
+
+// Discord allows at most 25 fields per embed.
+const MAX_FIELDS: usize = 25;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MyTimesCmdError {
+    #[error("Something went wrong while fetching the records")]
+    FetchRecords,
+
+    #[error("You haven't set any times yet, upload a screenshot to get started!")]
+    NoRecords,
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let response = match mytimes_command(cmd, handler).await {
+        Ok(best) => EditInteractionResponse::new().embed(mytimes_embed(&best)),
+        Err(error) => EditInteractionResponse::new().content(error.to_string()),
+    };
+
+    let _ = cmd.edit_response(&ctx.http, response).await;
+}
+
+/// The caller's best time on every track they drove, see `vs::best_per_track`.
+pub async fn mytimes_command(
+    cmd: &CommandInteraction,
+    handler: &Handler,
+) -> Result<Vec<(String, Duration)>, MyTimesCmdError> {
+    let approved_only = approval_required();
+    let records: Vec<Record> = handler
+        .gsheet
+        .records()
+        .get_by_driver(cmd.user.id.get())
+        .await
+        .map_err(|_| MyTimesCmdError::FetchRecords)?
+        .into_iter()
+        .filter(|r| r.is_visible(approved_only))
+        .collect();
+
+    let best: Vec<(String, Duration)> = best_per_track(&records, cmd.user.id.get())
+        .into_iter()
+        .collect();
+    if best.is_empty() {
+        return Err(MyTimesCmdError::NoRecords);
+    }
+
+    Ok(best)
+}
+
+/// Splits off what fits in one embed. Past `max_fields` the last field makes room
+/// for the footer, so either every track is shown or `max_fields - 1` are, together
+/// with the number left out.
+pub fn split_overflow<T>(items: &[T], max_fields: usize) -> (&[T], usize) {
+    if items.len() <= max_fields {
+        return (items, 0);
+    }
+    let shown = max_fields.saturating_sub(1);
+    (&items[..shown], items.len() - shown)
+}
+
+fn mytimes_embed(best: &[(String, Duration)]) -> CreateEmbed {
+    let (shown, hidden) = split_overflow(best, MAX_FIELDS);
+
+    let mut embed = CreateEmbed::default()
+        .title("Your best times")
+        .color(Colour::new(DEFAULT_COLOUR));
+
+    for (track, time) in shown {
+        embed = embed.field(track, time_format::format_display(*time), true);
+    }
+
+    if hidden > 0 {
+        let noun = if hidden == 1 { "track" } else { "tracks" };
+        embed = embed.footer(CreateEmbedFooter::new(format!(
+            "…and {hidden} more {noun}, use /leaderboard to see a track's times"
+        )));
+    }
+
+    embed
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let mytimes_command =
+        CreateCommand::new("mytimes").description("Show your best time on every track.");
+
+    guild_id.create_command(http, mytimes_command).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_track_fits_up_to_the_field_limit() {
+        let tracks: Vec<usize> = (0..MAX_FIELDS).collect();
+        assert_eq!(split_overflow(&tracks, MAX_FIELDS), (&tracks[..], 0));
+    }
+
+    #[test]
+    fn past_the_limit_the_last_field_becomes_the_footer() {
+        let tracks: Vec<usize> = (0..MAX_FIELDS + 1).collect();
+        let (shown, hidden) = split_overflow(&tracks, MAX_FIELDS);
+        assert_eq!((shown.len(), hidden), (24, 2));
+
+        let tracks: Vec<usize> = (0..40).collect();
+        let (shown, hidden) = split_overflow(&tracks, MAX_FIELDS);
+        assert_eq!((shown.len(), hidden), (24, 16));
+    }
+}
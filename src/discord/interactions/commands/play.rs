@@ -1,8 +1,11 @@
 use serenity::all::{
-    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage, GuildId, Http
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption, EditInteractionResponse, GuildId, Http
 };
 
-use crate::discord::handler::Handler;
+use crate::{
+    discord::handler::Handler,
+    sheets::{errors::DataUploadError, players::player::Selection, tracks::resolve_track_name},
+};
 
 pub enum PlayCmdOutcome {
     Success(String),
@@ -11,6 +14,9 @@ pub enum PlayCmdOutcome {
 }
 
 pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    // Acknowledge right away, the Sheets round-trips can exceed Discord's 3 second window.
+    let _ = cmd.defer(&ctx.http).await;
+
     let user_id = u64::from(cmd.user.id);
     let display_name = cmd.user.display_name();
     let track_name = cmd
@@ -21,14 +27,29 @@ pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler)
         .and_then(|opt| opt.value.as_str())
         .unwrap_or_default()
         .to_string();
+    let category = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "category")
+        .and_then(|opt| opt.value.as_str())
+        .map(|category| category.trim().to_string())
+        .filter(|category| !category.is_empty());
+    let platform = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "platform")
+        .and_then(|opt| opt.value.as_str())
+        .map(str::to_string);
 
-    let outcome = play_command(
-        user_id,
-        display_name.to_string(),
-        track_name.clone(),
-        handler,
-    )
-    .await;
+    let selection = Selection {
+        track_name,
+        category,
+        platform,
+    };
+
+    let outcome = play_command(user_id, display_name.to_string(), selection, handler).await;
 
     let response = match outcome {
         PlayCmdOutcome::Success(name) => format!("Now playing {}!", name),
@@ -37,42 +58,52 @@ pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler)
     };
 
     let _ = cmd
-        .create_response(
-            &ctx.http,
-            CreateInteractionResponse::Message(
-                CreateInteractionResponseMessage::new().content(response),
-            ),
-        )
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(response))
         .await;
 }
 
 pub async fn play_command(
     user_id: u64,
     display_name: String,
-    track_name: String,
+    mut selection: Selection,
     handler: &Handler,
 ) -> PlayCmdOutcome {
-    let is_valid = match handler.gsheet.tracks().get_all().await {
-        Ok(tracks) => tracks.iter().any(|t| t.name == track_name),
+    let track_names: Vec<String> = match handler.gsheet.tracks().get_listed().await {
+        Ok(tracks) => tracks.into_iter().map(|t| t.name).collect(),
         Err(_) => return PlayCmdOutcome::Failure,
     };
 
-    if !is_valid {
-        return PlayCmdOutcome::InvalidTrack;
+    match resolve_track_name(&selection.track_name, &track_names) {
+        Some(track_name) => selection.track_name = track_name.to_string(),
+        None => return PlayCmdOutcome::InvalidTrack,
     }
 
     let players = handler.gsheet.players();
     let result = match players.get_by_user_id(user_id).await {
         Err(_) => false,
-        Ok(Some(mut player)) => player.set_current_track(track_name.clone()).await.is_ok(),
-        Ok(None) => players
-            .create(user_id, display_name, Some(track_name.clone()))
+        Ok(Some(mut player)) => player.select(&selection).await.is_ok(),
+        Ok(None) => match players
+            .create(
+                user_id,
+                display_name,
+                Some(selection.track_name.clone()),
+                selection.category.clone(),
+                selection.platform.clone(),
+            )
             .await
-            .is_ok(),
+        {
+            Ok(_) => true,
+            // another /play created the player in the meantime, update that row instead
+            Err(DataUploadError::UniqueConstraint) => match players.get_by_user_id(user_id).await {
+                Ok(Some(mut player)) => player.select(&selection).await.is_ok(),
+                _ => false,
+            },
+            Err(_) => false,
+        },
     };
 
     if result {
-        PlayCmdOutcome::Success(track_name)
+        PlayCmdOutcome::Success(selection.label())
     } else {
         PlayCmdOutcome::Failure
     }
@@ -84,9 +115,26 @@ pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
             .set_autocomplete(true)
             .required(true);
 
+    let category_option = CreateCommandOption::new(
+        CommandOptionType::String,
+        "category",
+        "Engine class or other category, leave empty for none",
+    );
+
+    let platform_option = CreateCommandOption::new(
+        CommandOptionType::String,
+        "platform",
+        "Where you're playing, leave empty to not say",
+    )
+    .add_string_choice("Console", "Console")
+    .add_string_choice("Emulator", "Emulator")
+    .add_string_choice("Cloud", "Cloud");
+
     let play_command = CreateCommand::new("play")
         .description("Select a track to play.")
-        .add_option(play_command_option);
+        .add_option(play_command_option)
+        .add_option(category_option)
+        .add_option(platform_option);
 
     guild_id.create_command(http, play_command).await?;
 
@@ -0,0 +1,68 @@
+use serenity::all::{
+    CommandInteraction, Context, CreateCommand, EditInteractionResponse, GuildId, Http,
+    Permissions,
+};
+
+use crate::{
+    discord::handler::Handler,
+    ocr::{PROVIDER_STATS, stats::ProviderCounts},
+};
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, _handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let content = provider_stats_text(&PROVIDER_STATS.snapshot());
+
+    let _ = cmd
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(content))
+        .await;
+}
+
+/// One line per provider, providers that were never called are left out.
+pub fn provider_stats_text(stats: &[(&'static str, ProviderCounts)]) -> String {
+    let lines: Vec<String> = stats
+        .iter()
+        .filter(|(_, counts)| *counts != ProviderCounts::default())
+        .map(|(name, counts)| format!("**{name}**: {counts}"))
+        .collect();
+
+    if lines.is_empty() {
+        return "No provider has been called since the bot started".to_string();
+    }
+
+    format!("Provider calls since the bot started:\n{}", lines.join("\n"))
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let provider_stats_command = CreateCommand::new("provider_stats")
+        .description("Show how often each OCR provider succeeded or failed")
+        .default_member_permissions(Permissions::ADMINISTRATOR);
+
+    guild_id.create_command(http, provider_stats_command).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn providers_never_called_are_left_out() {
+        let stats = [
+            ("groq", ProviderCounts { success: 3, retryable: 1, fatal: 0 }),
+            ("gemini", ProviderCounts::default()),
+        ];
+
+        assert_eq!(
+            provider_stats_text(&stats),
+            "Provider calls since the bot started:\n**groq**: 3 ok, 1 retryable failures, 0 fatal failures"
+        );
+    }
+
+    #[test]
+    fn no_calls_says_so() {
+        let text = provider_stats_text(&[("groq", ProviderCounts::default())]);
+        assert_eq!(text, "No provider has been called since the bot started");
+    }
+}
@@ -1,9 +1,13 @@
 use serenity::all::{
     CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
-    CreateInteractionResponseMessage, EditMessage, GuildId, Http,
+    EditInteractionResponse, GuildId, Http, MessageId,
 };
 
-use crate::discord::{handler::Handler, templates::record::record_embed};
+use crate::discord::{
+    handler::Handler,
+    output::{edit_record_message_in, RecordMessage},
+    templates::record::record_embed,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum RefreshCmdError {
@@ -24,6 +28,8 @@ pub enum RefreshCmdError {
 }
 
 pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
     let outcome = refresh_command(ctx, cmd, handler).await;
 
     let response_content = match outcome {
@@ -32,14 +38,7 @@ pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler)
     };
 
     let _ = cmd
-        .create_response(
-            &ctx.http,
-            serenity::all::CreateInteractionResponse::Message(
-                CreateInteractionResponseMessage::new()
-                    .content(response_content)
-                    .ephemeral(true),
-            ),
-        )
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(response_content))
         .await;
 }
 
@@ -72,17 +71,17 @@ pub async fn refresh_command(
         .map_err(|_| RefreshCmdError::FetchRecord)?
         .ok_or(RefreshCmdError::RecordNotFound)?;
 
-    let (embed, components) = record_embed(record, handler).await;
-
-    let edit = EditMessage::new()
-        .content("")
-        .embed(embed)
-        .components(components);
+    let (embed, components) = record_embed(&ctx.http, record, handler, None, false).await;
 
-    cmd.channel_id
-        .edit_message(&ctx.http, bot_message_id, edit)
-        .await
-        .map_err(|_| RefreshCmdError::EditFailed)?;
+    edit_record_message_in(
+        &ctx.http,
+        handler.record_webhook.get(),
+        &handler.record_message_channels(),
+        MessageId::new(bot_message_id),
+        RecordMessage::embed(embed, components),
+    )
+    .await
+    .map_err(|_| RefreshCmdError::EditFailed)?;
 
     Ok(())
 }
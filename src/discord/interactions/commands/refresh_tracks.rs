@@ -0,0 +1,70 @@
+use serenity::all::{
+    CommandInteraction, Context, CreateCommand, EditInteractionResponse, GuildId, Http,
+    Permissions,
+};
+
+use crate::discord::handler::Handler;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RefreshTracksCmdError {
+    #[error("Something went wrong while fetching the tracks")]
+    FetchTracks,
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let response_content = match refresh_tracks_command(handler).await {
+        Ok(count) => format!("Tracks reloaded, {count} tracks are available"),
+        Err(error) => error.to_string(),
+    };
+
+    let _ = cmd
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(response_content))
+        .await;
+}
+
+/// Reloads the Tracks sheet without waiting for `TRACKS_REFRESH_SECS`, returning the
+/// number of listed tracks.
+pub async fn refresh_tracks_command(handler: &Handler) -> Result<usize, RefreshTracksCmdError> {
+    handler
+        .reload_tracks()
+        .await
+        .map_err(|_| RefreshTracksCmdError::FetchTracks)
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let refresh_tracks_command = CreateCommand::new("refresh_tracks")
+        .description("Reload the tracks from the sheet")
+        .default_member_permissions(Permissions::ADMINISTRATOR);
+
+    guild_id.create_command(http, refresh_tracks_command).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::sheets::gsheet::GSheet;
+
+    #[tokio::test]
+    async fn refresh_goes_past_the_cached_tracks() {
+        let handler = Handler::with_gsheet(GSheet::unreachable());
+        let row = vec![
+            Value::String("Mario Bros. Circuit".to_string()),
+            Value::String("https://example.com/mbc.png".to_string()),
+        ];
+        *handler.gsheet.tracks_cache.lock().await = Some((Instant::now(), vec![(2, row)]));
+
+        assert_eq!(handler.track_names().await, vec!["Mario Bros. Circuit"]);
+
+        // The sheet can't be reached, so only a read that skips the cache fails.
+        assert!(matches!(refresh_tracks_command(&handler).await, Err(RefreshTracksCmdError::FetchTracks)));
+        assert!(handler.gsheet.tracks_cache.lock().await.is_none());
+    }
+}
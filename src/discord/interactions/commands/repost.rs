@@ -0,0 +1,124 @@
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    EditInteractionResponse, GuildId, Http, MessageId, Permissions,
+};
+
+use crate::discord::{
+    handler::Handler,
+    output::{delete_record_message_in, post_record_message, RecordMessage},
+    templates::record::record_embed,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RepostCmdError {
+    #[error("Command option was missing: {0}")]
+    MissingOption(&'static str),
+
+    #[error("Command option was of an incorrect data type: {0}")]
+    InvalidOptionType(&'static str),
+
+    #[error("Something went wrong while fetching the record")]
+    FetchRecord,
+
+    #[error("The record was not found")]
+    RecordNotFound,
+
+    #[error("Something went wrong while posting the new message")]
+    PostFailed,
+
+    #[error("The record was reposted, but its new message id could not be saved")]
+    SaveFailed,
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let response_content = match repost_command(ctx, cmd, handler).await {
+        Ok(new_message_id) => format!("Record reposted, its message id is now {new_message_id}"),
+        Err(error) => error.to_string(),
+    };
+
+    let _ = cmd
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(response_content))
+        .await;
+}
+
+/// Posts a fresh message for a record whose message is gone, so `/refresh` and the
+/// buttons work again, and stores the new message id on the record. The message goes
+/// where new records go, through the webhook or in the records channel, not where the
+/// command is used. The old message is removed when it still exists, its buttons
+/// would no longer find the record.
+pub async fn repost_command(
+    ctx: &Context,
+    cmd: &CommandInteraction,
+    handler: &Handler,
+) -> Result<MessageId, RepostCmdError> {
+    let bot_message_id = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "message_id")
+        .ok_or(RepostCmdError::MissingOption("message_id"))?
+        .value
+        .as_str()
+        .ok_or(RepostCmdError::InvalidOptionType("message_id"))?
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| RepostCmdError::InvalidOptionType("message_id"))?;
+
+    let records = handler.gsheet.records();
+
+    let mut record = records
+        .get_by_bot_message_id(bot_message_id)
+        .await
+        .map_err(|_| RepostCmdError::FetchRecord)?
+        .ok_or(RepostCmdError::RecordNotFound)?;
+
+    let (embed, components) = record_embed(&ctx.http, record.clone(), handler, None, false).await;
+
+    // Without a configured records channel uploads aren't read at all, the command's
+    // channel is the only place left.
+    let records_channel = handler.records_channel_id().unwrap_or(cmd.channel_id);
+    let message = post_record_message(
+        &ctx.http,
+        handler.record_webhook.get(),
+        records_channel,
+        RecordMessage::embed(embed, components),
+    )
+    .await
+    .map_err(|_| RepostCmdError::PostFailed)?;
+
+    record
+        .set_bot_message_id(message.id.get(), cmd.user.id.get())
+        .await
+        .map_err(|_| RepostCmdError::SaveFailed)?;
+
+    // Usually the old message is already gone, which is why the record is reposted.
+    let _ = delete_record_message_in(
+        &ctx.http,
+        handler.record_webhook.get(),
+        &handler.record_message_channels(),
+        MessageId::new(bot_message_id),
+    )
+    .await;
+
+    Ok(message.id)
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let message_option = CreateCommandOption::new(
+        CommandOptionType::String,
+        "message_id",
+        "Enter the id of the record's current (possibly deleted) message",
+    )
+    .required(true);
+
+    let repost_command = CreateCommand::new("repost")
+        .description("Post a new message for a record and link the record to it.")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(message_option);
+
+    guild_id.create_command(http, repost_command).await?;
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use serenity::all::{
+    CommandInteraction, Context, CreateCommand, EditInteractionResponse, GuildId, Http,
+    Permissions,
+};
+
+use crate::{
+    discord::handler::Handler,
+    sheets::{
+        players::Players, records::Records, schema::expected_label, settings::Settings,
+        tracks::Tracks, utils::DataRanges,
+    },
+};
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, _handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let _ = cmd
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(schema_text()))
+        .await;
+}
+
+/// One sheet of the spreadsheet, as the bot reads it.
+struct SheetSchema {
+    name: &'static str,
+    first_column: &'static str,
+    last_column: &'static str,
+    columns: Vec<(&'static str, &'static str)>,
+}
+
+impl SheetSchema {
+    fn of<T: DataRanges>(columns: Vec<(&'static str, &'static str)>) -> Self {
+        SheetSchema {
+            name: T::SHEET_NAME,
+            first_column: T::FIRST_COLUMN,
+            last_column: T::LAST_COLUMN,
+            columns,
+        }
+    }
+}
+
+fn schemas() -> Vec<SheetSchema> {
+    vec![
+        SheetSchema::of::<Records>(vec![
+            (Records::USER_MESSAGE_ID_COLUMN, "upload message id"),
+            (Records::BOT_MESSAGE_ID_COLUMN, "bot message id"),
+            (Records::REPORT_TIMESTAMP_COLUMN, "upload timestamp"),
+            (Records::DRIVER_USER_ID_COLUMN, "driver user id"),
+            (Records::TRACK_NAME_COLUMN, "track name"),
+            (Records::RACE_DURATION_COLUMN, "race time"),
+            (Records::CATEGORY_COLUMN, "category (optional)"),
+            (Records::APPROVED_COLUMN, "approved, TRUE or FALSE (blank is approved)"),
+            (Records::PLATFORM_COLUMN, "platform (optional)"),
+            (Records::LAST_MODIFIED_COLUMN, "last edited through the bot (optional)"),
+        ]),
+        SheetSchema::of::<Players>(vec![
+            (Players::USER_ID_COLUMN, "user id"),
+            (Players::DISPLAY_NAME_COLUMN, "display name"),
+            (Players::CURRENT_TRACK_COLUMN, "selected track"),
+            (Players::CURRENT_CATEGORY_COLUMN, "selected category (optional)"),
+            (Players::CURRENT_PLATFORM_COLUMN, "selected platform (optional)"),
+        ]),
+        SheetSchema::of::<Tracks>(vec![
+            (Tracks::NAME_COLUMN, "track name"),
+            (Tracks::ICON_FILE_URL_COLUMN, "icon URL"),
+            (Tracks::COLOR_COLUMN, "embed colour like #FF8800 (optional)"),
+        ]),
+        SheetSchema::of::<Settings>(vec![
+            (Settings::KEY_COLUMN, "setting key"),
+            (Settings::VALUE_COLUMN, "setting value"),
+        ]),
+    ]
+}
+
+/// The sheets and columns the bot expects, built from the table constants so it
+/// can't drift from what is actually read. Data starts on row 2, below the headers.
+/// Headers are compared ignoring case, spaces and punctuation, see `check_headers`.
+pub fn schema_text() -> String {
+    let mut text = String::from("**Expected spreadsheet layout** (row 1 holds headers)\n");
+    for schema in schemas() {
+        text.push_str(&format!(
+            "\n**{}** ({}–{})\n",
+            schema.name, schema.first_column, schema.last_column
+        ));
+        for (column, description) in schema.columns {
+            match expected_label(schema.name, column) {
+                Some(label) => text.push_str(&format!("`{column}` {description}, header \"{label}\"\n")),
+                None => text.push_str(&format!("`{column}` {description}\n")),
+            }
+        }
+    }
+    text
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let schema_command = CreateCommand::new("schema")
+        .description("Show the sheets and columns the bot expects.")
+        .default_member_permissions(Permissions::ADMINISTRATOR);
+
+    guild_id.create_command(http, schema_command).await?;
+
+    Ok(())
+}
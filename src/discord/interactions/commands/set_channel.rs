@@ -0,0 +1,56 @@
+use serenity::all::{
+    CommandInteraction, Context, CreateCommand, EditInteractionResponse, GuildId, Http,
+    Permissions,
+};
+
+use crate::{discord::handler::Handler, sheets::settings::Settings};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SetChannelCmdError {
+    #[error("Something went wrong while saving the channel")]
+    SaveFailed,
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let outcome = set_channel_command(cmd, handler).await;
+
+    let response_content = match outcome {
+        Ok(_) => format!("Records will now be read from <#{}>.", cmd.channel_id),
+        Err(error) => error.to_string(),
+    };
+
+    let _ = cmd
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(response_content))
+        .await;
+}
+
+pub async fn set_channel_command(
+    cmd: &CommandInteraction,
+    handler: &Handler,
+) -> Result<(), SetChannelCmdError> {
+    handler
+        .gsheet
+        .settings()
+        .set(Settings::RECORDS_CHANNEL_ID, cmd.channel_id.get().to_string())
+        .await
+        .map_err(|_| SetChannelCmdError::SaveFailed)?;
+
+    *handler
+        .records_channel
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(cmd.channel_id);
+
+    Ok(())
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let set_channel_command = CreateCommand::new("set_channel")
+        .description("Use this channel as the records channel.")
+        .default_member_permissions(Permissions::ADMINISTRATOR);
+
+    guild_id.create_command(http, set_channel_command).await?;
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    EditInteractionResponse, GuildId, Http, Permissions,
+};
+
+use crate::{
+    discord::{handler::Handler, templates::track::track_embed},
+    sheets::tracks::parse_hex_color,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SetTrackColorCmdError {
+    #[error("Command option was missing: {0}")]
+    MissingOption(&'static str),
+
+    #[error("Please enter a colour like #FF8800, or \"none\" to use the default")]
+    InvalidColor,
+
+    #[error("Something went wrong while fetching the tracks")]
+    FetchTracks,
+
+    #[error("Please enter a valid track name")]
+    InvalidTrack,
+
+    #[error("Something went wrong while saving the colour")]
+    SaveFailed,
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let response = match set_track_color_command(cmd, handler).await {
+        Ok((track_name, icon_url, color)) => {
+            EditInteractionResponse::new().embed(track_embed(&track_name, Some(&icon_url), color))
+        }
+        Err(error) => EditInteractionResponse::new().content(error.to_string()),
+    };
+
+    let _ = cmd.edit_response(&ctx.http, response).await;
+}
+
+/// Stores the colour of a track and returns the updated track for the preview.
+/// Entering "none" clears the colour so records fall back to the default.
+pub async fn set_track_color_command(
+    cmd: &CommandInteraction,
+    handler: &Handler,
+) -> Result<(String, String, Option<u32>), SetTrackColorCmdError> {
+    let option = |name: &'static str| {
+        cmd.data
+            .options
+            .iter()
+            .find(|opt| opt.name == name)
+            .and_then(|opt| opt.value.as_str())
+            .ok_or(SetTrackColorCmdError::MissingOption(name))
+    };
+
+    let track_name = option("track")?;
+    let color_text = option("color")?;
+
+    let color = if color_text.trim().eq_ignore_ascii_case("none") {
+        None
+    } else {
+        Some(parse_hex_color(color_text).ok_or(SetTrackColorCmdError::InvalidColor)?)
+    };
+
+    let tracks = handler.gsheet.tracks();
+    let mut track = tracks
+        .get_by_name(track_name)
+        .await
+        .map_err(|_| SetTrackColorCmdError::FetchTracks)?
+        .ok_or(SetTrackColorCmdError::InvalidTrack)?;
+
+    track
+        .set_color(color)
+        .await
+        .map_err(|_| SetTrackColorCmdError::SaveFailed)?;
+
+    Ok((track.name, track.icon_url, track.color))
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let track_option =
+        CreateCommandOption::new(CommandOptionType::String, "track", "Enter a track name")
+            .set_autocomplete(true)
+            .required(true);
+
+    let color_option = CreateCommandOption::new(
+        CommandOptionType::String,
+        "color",
+        "Hex colour like #FF8800, or \"none\" for the default",
+    )
+    .required(true);
+
+    let set_track_color_command = CreateCommand::new("set_track_color")
+        .description("Set the embed colour of a track's records.")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(track_option)
+        .add_option(color_option);
+
+    guild_id.create_command(http, set_track_color_command).await?;
+
+    Ok(())
+}
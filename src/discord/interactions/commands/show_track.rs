@@ -0,0 +1,70 @@
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    EditInteractionResponse, GuildId, Http, Permissions,
+};
+
+use crate::discord::{handler::Handler, templates::track::track_embed};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShowTrackCmdError {
+    #[error("Command option was missing: {0}")]
+    MissingOption(&'static str),
+
+    #[error("Something went wrong while fetching the tracks")]
+    FetchTracks,
+
+    #[error("Please enter a valid track name")]
+    InvalidTrack,
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let response = match show_track_command(cmd, handler).await {
+        Ok((track_name, icon_url, color)) => {
+            EditInteractionResponse::new().embed(track_embed(&track_name, Some(&icon_url), color))
+        }
+        Err(error) => EditInteractionResponse::new().content(error.to_string()),
+    };
+
+    let _ = cmd.edit_response(&ctx.http, response).await;
+}
+
+/// Returns the name, stored icon URL and colour of the requested track.
+pub async fn show_track_command(
+    cmd: &CommandInteraction,
+    handler: &Handler,
+) -> Result<(String, String, Option<u32>), ShowTrackCmdError> {
+    let track_name = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "track")
+        .and_then(|opt| opt.value.as_str())
+        .ok_or(ShowTrackCmdError::MissingOption("track"))?;
+
+    let tracks = handler.gsheet.tracks();
+    let track = tracks
+        .get_by_name(track_name)
+        .await
+        .map_err(|_| ShowTrackCmdError::FetchTracks)?
+        .ok_or(ShowTrackCmdError::InvalidTrack)?;
+
+    Ok((track.name, track.icon_url, track.color))
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let show_track_command_option =
+        CreateCommandOption::new(CommandOptionType::String, "track", "Enter a track name")
+            .set_autocomplete(true)
+            .required(true);
+
+    let show_track_command = CreateCommand::new("show_track")
+        .description("Show a track's name and icon.")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(show_track_command_option);
+
+    guild_id.create_command(http, show_track_command).await?;
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::sync::atomic::Ordering;
+
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse, CreateInteractionResponseMessage, GuildId, Http, Permissions,
+};
+
+use crate::discord::handler::Handler;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreModeCmdError {
+    #[error("Command option was missing: {0}")]
+    MissingOption(&'static str),
+
+    #[error("Unknown store mode, expected `all` or `pb_only`")]
+    UnknownMode,
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let outcome = store_mode_command(cmd, handler);
+
+    let response_content = match outcome {
+        Ok(true) => "Only personal bests will be stored from now on.".to_string(),
+        Ok(false) => "Every upload will be stored from now on.".to_string(),
+        Err(error) => error.to_string(),
+    };
+
+    let _ = cmd
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(response_content)
+                    .ephemeral(true),
+            ),
+        )
+        .await;
+}
+
+/// Returns whether the bot is now in personal-best-only mode.
+pub fn store_mode_command(
+    cmd: &CommandInteraction,
+    handler: &Handler,
+) -> Result<bool, StoreModeCmdError> {
+    let mode = cmd
+        .data
+        .options
+        .iter()
+        .find(|opt| opt.name == "mode")
+        .and_then(|opt| opt.value.as_str())
+        .ok_or(StoreModeCmdError::MissingOption("mode"))?;
+
+    let store_only_pb = parse_store_mode(mode).ok_or(StoreModeCmdError::UnknownMode)?;
+    handler.store_only_pb.store(store_only_pb, Ordering::Relaxed);
+
+    Ok(store_only_pb)
+}
+
+/// Maps `all` / `pb_only` (or `store_all` / `store_only_pb`) to whether only personal
+/// bests should be stored.
+pub fn parse_store_mode(mode: &str) -> Option<bool> {
+    match mode.trim().to_ascii_lowercase().as_str() {
+        "all" | "store_all" => Some(false),
+        "pb_only" | "store_only_pb" => Some(true),
+        _ => None,
+    }
+}
+
+/// The mode at startup. `STORE_ONLY_PB=1` turns personal-best-only on; `RECORD_STORE_MODE`
+/// names the mode like `/store_mode` does and wins when it is set to a known mode.
+/// Without either every upload is stored.
+pub fn initial_store_mode(store_only_pb: bool, record_store_mode: Option<&str>) -> bool {
+    record_store_mode
+        .and_then(parse_store_mode)
+        .unwrap_or(store_only_pb)
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let store_mode_command_option =
+        CreateCommandOption::new(CommandOptionType::String, "mode", "Which uploads to store")
+            .add_string_choice("Every upload", "all")
+            .add_string_choice("Personal bests only", "pb_only")
+            .required(true);
+
+    let store_mode_command = CreateCommand::new("store_mode")
+        .description("Choose whether uploads that aren't a personal best are stored.")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(store_mode_command_option);
+
+    guild_id.create_command(http, store_mode_command).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_upload_is_stored_by_default() {
+        assert!(!initial_store_mode(false, None));
+    }
+
+    #[test]
+    fn store_only_pb_flag_turns_pb_only_on() {
+        assert!(initial_store_mode(true, None));
+    }
+
+    #[test]
+    fn record_store_mode_wins_over_the_flag() {
+        assert!(!initial_store_mode(true, Some("all")));
+        assert!(initial_store_mode(false, Some("STORE_ONLY_PB")));
+        assert!(initial_store_mode(true, Some("unknown")));
+    }
+}
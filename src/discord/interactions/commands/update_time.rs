@@ -1,11 +1,16 @@
 use serenity::all::{
     CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
-    CreateInteractionResponseMessage, EditMessage, GuildId, Http,
+    EditInteractionResponse, GuildId, Http, MessageId,
 };
 
 use crate::{
-    discord::{handler::Handler, templates::record::record_embed},
-    ocr::parse_duration,
+    discord::{
+        handler::Handler,
+        output::{edit_record_message_in, RecordMessage},
+        permissions::is_admin,
+        templates::record::record_embed,
+    },
+    time_format,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -25,6 +30,12 @@ pub enum UpdateTimeCmdError {
     #[error("The record was not found")]
     RecordNotFound,
 
+    #[error("This record is older than {0} days and can no longer be edited")]
+    RecordTooOld(i64),
+
+    #[error("This record is locked and can only be edited by an admin")]
+    RecordLocked,
+
     #[error("Something went wrong while updating the record time")]
     UpdateFailed,
 
@@ -33,6 +44,8 @@ pub enum UpdateTimeCmdError {
 }
 
 pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
     let outcome = update_time_command(ctx, cmd, handler).await;
 
     let response_content = match outcome {
@@ -41,14 +54,7 @@ pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler)
     };
 
     let _ = cmd
-        .create_response(
-            &ctx.http,
-            serenity::all::CreateInteractionResponse::Message(
-                CreateInteractionResponseMessage::new()
-                    .content(response_content)
-                    .ephemeral(true),
-            ),
-        )
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(response_content))
         .await;
 }
 
@@ -81,6 +87,14 @@ pub async fn update_time_command(
         .map_err(|_| UpdateTimeCmdError::FetchRecord)?
         .ok_or(UpdateTimeCmdError::RecordNotFound)?;
 
+    if record.is_locked_for(is_admin(cmd.member.as_deref())) {
+        return Err(UpdateTimeCmdError::RecordLocked);
+    }
+
+    if let Some(max_age_days) = record.edit_age_limit_for(is_admin(cmd.member.as_deref())) {
+        return Err(UpdateTimeCmdError::RecordTooOld(max_age_days));
+    }
+
     let duration_str = cmd
         .data
         .options
@@ -95,25 +109,25 @@ pub async fn update_time_command(
         .as_str()
         .ok_or(UpdateTimeCmdError::InvalidOptionType("record_time"))?;
 
-    let duration = parse_duration(duration_str)
+    let duration = time_format::parse(duration_str)
         .map_err(|e| UpdateTimeCmdError::InvalidTimeFormat(e.to_string()))?;
 
     record
-        .set_race_duration(duration)
+        .set_race_duration(duration, cmd.user.id.get())
         .await
         .map_err(|_| UpdateTimeCmdError::UpdateFailed)?;
 
-    let (embed, components) = record_embed(record, handler).await;
-
-    let edit = EditMessage::new()
-        .content("")
-        .embed(embed)
-        .components(components);
+    let (embed, components) = record_embed(&ctx.http, record, handler, None, false).await;
 
-    cmd.channel_id
-        .edit_message(&ctx.http, bot_message_id, edit)
-        .await
-        .map_err(|_| UpdateTimeCmdError::EditFailed)?;
+    edit_record_message_in(
+        &ctx.http,
+        handler.record_webhook.get(),
+        &handler.record_message_channels(),
+        MessageId::new(bot_message_id),
+        RecordMessage::embed(embed, components),
+    )
+    .await
+    .map_err(|_| UpdateTimeCmdError::EditFailed)?;
 
     Ok(())
 }
@@ -0,0 +1,152 @@
+use serenity::all::{
+    CommandInteraction, Context, CreateCommand, EditInteractionResponse, GuildId, Http,
+    Permissions,
+};
+
+use crate::{
+    discord::handler::Handler,
+    sheets::{
+        errors::DeserializeValueError,
+        records::{duplicate_keys, record::Record},
+    },
+};
+
+// Discord rejects message content over 2000 characters.
+const MAX_RESPONSE_LEN: usize = 1900;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ValidateRecordsCmdError {
+    #[error("Something went wrong while fetching the records")]
+    FetchRecords,
+}
+
+#[derive(Debug, Default)]
+pub struct RecordsReport {
+    pub total_rows: usize,
+    pub malformed: Vec<(usize, String)>,
+    pub duplicates: Vec<(u64, Vec<usize>)>,
+    /// Rows sharing a `bot_message_id`, only the first of them is used.
+    pub duplicate_bot_messages: Vec<(u64, Vec<usize>)>,
+    pub orphaned: Vec<(usize, String)>,
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer_ephemeral(&ctx.http).await;
+
+    let outcome = validate_records_command(handler).await;
+
+    let response_content = match outcome {
+        Ok(report) => format_report(&report),
+        Err(error) => error.to_string(),
+    };
+
+    let _ = cmd
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(response_content))
+        .await;
+}
+
+pub async fn validate_records_command(
+    handler: &Handler,
+) -> Result<RecordsReport, ValidateRecordsCmdError> {
+    let rows = handler
+        .gsheet
+        .records()
+        .get_all_strict()
+        .await
+        .map_err(|_| ValidateRecordsCmdError::FetchRecords)?;
+
+    Ok(build_report(&rows, &handler.track_names().await))
+}
+
+/// Collect malformed rows, records sharing a `user_message_id` or `bot_message_id`,
+/// and records pointing at a track that is not in the Tracks sheet.
+pub fn build_report(
+    rows: &[(usize, Result<Record<'_>, DeserializeValueError>)],
+    track_names: &[String],
+) -> RecordsReport {
+    let mut report = RecordsReport {
+        total_rows: rows.len(),
+        ..Default::default()
+    };
+    let mut user_messages = Vec::new();
+    let mut bot_messages = Vec::new();
+
+    for (rownum, row) in rows {
+        match row {
+            Ok(record) => {
+                user_messages.push((record.user_message_id, *rownum));
+                bot_messages.push((record.bot_message_id, *rownum));
+                if !track_names.contains(&record.track_name) {
+                    report.orphaned.push((*rownum, record.track_name.clone()));
+                }
+            }
+            Err(error) => report.malformed.push((*rownum, error.to_string())),
+        }
+    }
+
+    report.duplicates = duplicate_keys(user_messages);
+    report.duplicate_bot_messages = duplicate_keys(bot_messages);
+
+    report
+}
+
+pub fn format_report(report: &RecordsReport) -> String {
+    if report.malformed.is_empty()
+        && report.duplicates.is_empty()
+        && report.duplicate_bot_messages.is_empty()
+        && report.orphaned.is_empty()
+    {
+        return format!("All {} record rows look good!", report.total_rows);
+    }
+
+    let mut lines = vec![format!(
+        "Checked {} rows: {} malformed, {} duplicated, {} sharing a bot message, {} orphaned",
+        report.total_rows,
+        report.malformed.len(),
+        report.duplicates.len(),
+        report.duplicate_bot_messages.len(),
+        report.orphaned.len()
+    )];
+
+    for (rownum, error) in &report.malformed {
+        lines.push(format!("Row {rownum}: {error}"));
+    }
+    for (user_message_id, rownums) in &report.duplicates {
+        let rownums: Vec<String> = rownums.iter().map(|r| r.to_string()).collect();
+        lines.push(format!(
+            "Rows {} share user message {user_message_id}",
+            rownums.join(", ")
+        ));
+    }
+    for (bot_message_id, rownums) in &report.duplicate_bot_messages {
+        let rownums: Vec<String> = rownums.iter().map(|r| r.to_string()).collect();
+        lines.push(format!(
+            "Rows {} share bot message {bot_message_id}, only the first is used",
+            rownums.join(", ")
+        ));
+    }
+    for (rownum, track_name) in &report.orphaned {
+        lines.push(format!("Row {rownum}: unknown track \"{track_name}\""));
+    }
+
+    let mut content = String::new();
+    for line in lines {
+        if content.len() + line.len() + 1 > MAX_RESPONSE_LEN {
+            content.push('…');
+            break;
+        }
+        content.push_str(&line);
+        content.push('\n');
+    }
+    content
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let validate_records_command = CreateCommand::new("validate_records")
+        .description("Check the Records sheet for malformed, duplicate or orphaned rows.")
+        .default_member_permissions(Permissions::ADMINISTRATOR);
+
+    guild_id.create_command(http, validate_records_command).await?;
+
+    Ok(())
+}
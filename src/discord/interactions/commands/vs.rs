@@ -0,0 +1,278 @@
+use std::{cmp::Ordering, collections::BTreeMap, time::Duration};
+
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, EditInteractionResponse, GuildId, Http, UserId,
+};
+
+use crate::{
+    discord::{handler::Handler, templates::track::DEFAULT_COLOUR},
+    sheets::records::{approval_required, record::Record},
+    time_format,
+};
+
+// Embed descriptions are capped at 4096 characters.
+const MAX_DESCRIPTION_LEN: usize = 3800;
+
+#[derive(Debug, thiserror::Error)]
+pub enum VsCmdError {
+    #[error("Command option was missing: {0}")]
+    MissingOption(&'static str),
+
+    #[error("Please pick two different players")]
+    SamePlayer,
+
+    #[error("Something went wrong while fetching the records")]
+    FetchRecords,
+}
+
+/// Both players' best on a track they have both driven.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VsRow {
+    pub track: String,
+    pub first: Duration,
+    pub second: Duration,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct VsReport {
+    pub rows: Vec<VsRow>,
+    pub only_first: Vec<String>,
+    pub only_second: Vec<String>,
+}
+
+/// Wins of the first player, wins of the second player and ties.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VsTally {
+    pub first: usize,
+    pub second: usize,
+    pub ties: usize,
+}
+
+pub async fn handle(ctx: &Context, cmd: &CommandInteraction, handler: &Handler) {
+    let _ = cmd.defer(&ctx.http).await;
+
+    let response = match vs_command(cmd, handler).await {
+        Ok((first, second, report)) => {
+            EditInteractionResponse::new().embed(vs_embed(first, second, &report))
+        }
+        Err(error) => EditInteractionResponse::new().content(error.to_string()),
+    };
+
+    let _ = cmd.edit_response(&ctx.http, response).await;
+}
+
+pub async fn vs_command(
+    cmd: &CommandInteraction,
+    handler: &Handler,
+) -> Result<(UserId, UserId, VsReport), VsCmdError> {
+    let user_option = |name: &'static str| {
+        cmd.data
+            .options
+            .iter()
+            .find(|opt| opt.name == name)
+            .and_then(|opt| opt.value.as_user_id())
+            .ok_or(VsCmdError::MissingOption(name))
+    };
+    let first = user_option("player1")?;
+    let second = user_option("player2")?;
+    if first == second {
+        return Err(VsCmdError::SamePlayer);
+    }
+
+    let approved_only = approval_required();
+    let records: Vec<Record> = handler
+        .gsheet
+        .records()
+        .get_all()
+        .await
+        .map_err(|_| VsCmdError::FetchRecords)?
+        .into_iter()
+        .filter(|r| r.is_visible(approved_only))
+        .collect();
+
+    let report = head_to_head(
+        best_per_track(&records, first.get()),
+        best_per_track(&records, second.get()),
+    );
+
+    Ok((first, second, report))
+}
+
+/// The driver's best time per track, categories kept apart as "Track (category)".
+pub fn best_per_track(records: &[Record<'_>], driver_user_id: u64) -> BTreeMap<String, Duration> {
+    let mut best: BTreeMap<String, Duration> = BTreeMap::new();
+    for record in records.iter().filter(|r| r.driver_user_id == driver_user_id) {
+        let track = match &record.category {
+            Some(category) => format!("{} ({category})", record.track_name),
+            None => record.track_name.clone(),
+        };
+        best.entry(track)
+            .and_modify(|time| {
+                if time_format::cmp_millis(record.race_duration, *time).is_lt() {
+                    *time = record.race_duration;
+                }
+            })
+            .or_insert(record.race_duration);
+    }
+    best
+}
+
+/// Pairs up the tracks both drivers have a time on, and lists the ones only one drove.
+pub fn head_to_head(
+    first: BTreeMap<String, Duration>,
+    mut second: BTreeMap<String, Duration>,
+) -> VsReport {
+    let mut report = VsReport::default();
+    for (track, first_time) in first {
+        match second.remove(&track) {
+            Some(second_time) => report.rows.push(VsRow {
+                track,
+                first: first_time,
+                second: second_time,
+            }),
+            None => report.only_first.push(track),
+        }
+    }
+    report.only_second = second.into_keys().collect();
+    report
+}
+
+/// Times equal to the millisecond are a tie.
+pub fn tally(rows: &[VsRow]) -> VsTally {
+    rows.iter()
+        .fold(VsTally::default(), |mut tally, row| {
+            match time_format::cmp_millis(row.first, row.second) {
+                Ordering::Less => tally.first += 1,
+                Ordering::Greater => tally.second += 1,
+                Ordering::Equal => tally.ties += 1,
+            }
+            tally
+        })
+}
+
+fn vs_embed(first: UserId, second: UserId, report: &VsReport) -> CreateEmbed {
+    let tally = tally(&report.rows);
+
+    let mut description = format!(
+        "<@{first}> **{}** – **{}** <@{second}>",
+        tally.first, tally.second
+    );
+    if tally.ties > 0 {
+        description.push_str(&format!(" ({} tied)", tally.ties));
+    }
+    description.push('\n');
+
+    if report.rows.is_empty() {
+        description.push_str("\nNo tracks in common yet.\n");
+    }
+
+    for row in &report.rows {
+        let line = match time_format::cmp_millis(row.first, row.second) {
+            Ordering::Less => format!(
+                "\n**{}**: **{}** vs {} (<@{first}> by {})",
+                row.track,
+                time_format::format(row.first),
+                time_format::format(row.second),
+                time_format::format_delta(row.second - row.first)
+            ),
+            Ordering::Greater => format!(
+                "\n**{}**: {} vs **{}** (<@{second}> by {})",
+                row.track,
+                time_format::format(row.first),
+                time_format::format(row.second),
+                time_format::format_delta(row.first - row.second)
+            ),
+            Ordering::Equal => format!(
+                "\n**{}**: {} vs {} (tie)",
+                row.track,
+                time_format::format(row.first),
+                time_format::format(row.second)
+            ),
+        };
+        if description.len() + line.len() > MAX_DESCRIPTION_LEN {
+            description.push_str("\n…");
+            break;
+        }
+        description.push_str(&line);
+    }
+
+    let mut embed = CreateEmbed::default()
+        .title("Head to head")
+        .color(Colour::new(DEFAULT_COLOUR))
+        .description(description);
+
+    if !report.only_first.is_empty() {
+        embed = embed.field(
+            "Only driven by player 1",
+            report.only_first.len().to_string(),
+            true,
+        );
+    }
+    if !report.only_second.is_empty() {
+        embed = embed.field(
+            "Only driven by player 2",
+            report.only_second.len().to_string(),
+            true,
+        );
+    }
+
+    embed
+}
+
+pub async fn register(http: &Http, guild_id: GuildId) -> serenity::Result<()> {
+    let first_option =
+        CreateCommandOption::new(CommandOptionType::User, "player1", "The first player")
+            .required(true);
+    let second_option =
+        CreateCommandOption::new(CommandOptionType::User, "player2", "The second player")
+            .required(true);
+
+    let vs_command = CreateCommand::new("vs")
+        .description("Compare two players' best times on the tracks they both drove.")
+        .add_option(first_option)
+        .add_option(second_option);
+
+    guild_id.create_command(http, vs_command).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best(times: &[(&str, u64)]) -> BTreeMap<String, Duration> {
+        times.iter()
+            .map(|(track, millis)| (track.to_string(), Duration::from_millis(*millis)))
+            .collect()
+    }
+
+    #[test]
+    fn shared_tracks_are_paired_and_the_rest_listed() {
+        let report = head_to_head(
+            best(&[("Mario Circuit", 80_000), ("Rainbow Road", 150_000)]),
+            best(&[("Mario Circuit", 81_000), ("Luigi Circuit", 90_000)]),
+        );
+
+        assert_eq!(report.rows, vec![VsRow {
+            track: "Mario Circuit".to_string(),
+            first: Duration::from_millis(80_000),
+            second: Duration::from_millis(81_000),
+        }]);
+        assert_eq!(report.only_first, vec!["Rainbow Road"]);
+        assert_eq!(report.only_second, vec!["Luigi Circuit"]);
+    }
+
+    #[test]
+    fn equal_milliseconds_are_a_tie() {
+        let row = |first: Duration, second: Duration| VsRow { track: String::new(), first, second };
+        let rows = [
+            row(Duration::from_millis(80_000), Duration::from_millis(81_000)),
+            row(Duration::from_millis(90_000), Duration::from_millis(89_000)),
+            row(Duration::from_micros(70_000_100), Duration::from_micros(70_000_900)),
+        ];
+
+        assert_eq!(tally(&rows), VsTally { first: 1, second: 1, ties: 1 });
+    }
+}
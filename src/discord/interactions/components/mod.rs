@@ -1 +1,7 @@
-pub mod record;
\ No newline at end of file
+pub mod record;
+
+/// Components split over several parts, like the track menus, share the name
+/// before the colon in their custom id.
+pub fn component_name(custom_id: &str) -> &str {
+    custom_id.split(':').next().unwrap_or(custom_id)
+}
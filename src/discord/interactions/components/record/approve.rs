@@ -0,0 +1,67 @@
+use serenity::all::{
+    ComponentInteraction, Context, CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+
+use crate::discord::{handler::Handler, permissions::is_moderator, templates::record::record_embed};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApproveError {
+    #[error("Only moderators can approve records")]
+    NotAllowed,
+
+    #[error("Something went wrong while fetching the record")]
+    FetchRecord,
+
+    #[error("This message isn't a tracked record")]
+    NotARecord,
+
+    #[error("Something went wrong while approving the record")]
+    UpdateFailed,
+}
+
+pub async fn handle(ctx: &Context, act: &ComponentInteraction, handler: &Handler) {
+    let response = match approve(ctx, act, handler).await {
+        Ok(message) => CreateInteractionResponse::UpdateMessage(message),
+        Err(error) => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::default()
+                .ephemeral(true)
+                .content(error.to_string()),
+        ),
+    };
+
+    if let Err(why) = act.create_response(&ctx, response).await {
+        eprintln!("could not respond to the approve button: {why}");
+    }
+}
+
+/// Marks the record behind the clicked message as approved and returns the
+/// re-rendered message, which no longer carries the Approve button.
+pub async fn approve(
+    ctx: &Context,
+    act: &ComponentInteraction,
+    handler: &Handler,
+) -> Result<CreateInteractionResponseMessage, ApproveError> {
+    if !is_moderator(act.member.as_ref()) {
+        return Err(ApproveError::NotAllowed);
+    }
+
+    let records = handler.gsheet.records();
+
+    let mut record = records
+        .get_by_bot_message_id(act.message.id.get())
+        .await
+        .map_err(|_| ApproveError::FetchRecord)?
+        .ok_or(ApproveError::NotARecord)?;
+
+    record
+        .set_approved(true, act.user.id.get())
+        .await
+        .map_err(|_| ApproveError::UpdateFailed)?;
+
+    let (embed, components) = record_embed(&ctx.http, record, handler, None, false).await;
+
+    Ok(CreateInteractionResponseMessage::default()
+        .content("")
+        .embed(embed)
+        .components(components))
+}
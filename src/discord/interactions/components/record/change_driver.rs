@@ -3,17 +3,60 @@ use serenity::all::{
     CreateSelectMenu, CreateSelectMenuKind, UserId,
 };
 
-use crate::discord::handler::Handler;
+use crate::discord::{handler::Handler, permissions::is_admin};
+
+use super::record_message_id;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChangeDriverError {
+    #[error("The original record message could not be found")]
+    MissingRecordMessage,
+
+    #[error("Something went wrong while fetching the record")]
+    FetchRecord,
+
+    #[error("This message isn't a tracked record")]
+    NotARecord,
+
+    #[error("This record is locked and can only be edited by an admin")]
+    Locked,
+}
 
 pub async fn handle(ctx: &Context, act: &ComponentInteraction, handler: &Handler) {
-    let record_holder = handler
-        .gsheet
-        .records()
-        .get_by_bot_message_id(act.message.id.get())
+    let message = match change_driver_menu(act, handler).await {
+        Ok(message) => message,
+        Err(error) => CreateInteractionResponseMessage::default()
+            .ephemeral(true)
+            .content(error.to_string()),
+    };
+
+    let response = CreateInteractionResponse::Message(message);
+
+    if let Err(why) = act.create_response(&ctx, response).await {
+        eprintln!("could not respond to the change driver button: {why}");
+    }
+}
+
+/// The driver picker for the record behind the clicked message. Stale or foreign
+/// messages without a record row get `NotARecord` instead.
+pub async fn change_driver_menu(
+    act: &ComponentInteraction,
+    handler: &Handler,
+) -> Result<CreateInteractionResponseMessage, ChangeDriverError> {
+    let bot_message_id = record_message_id(act).ok_or(ChangeDriverError::MissingRecordMessage)?;
+
+    let records = handler.gsheet.records();
+    let record = records
+        .get_by_bot_message_id(bot_message_id.get())
         .await
-        .unwrap()
-        .unwrap()
-        .driver_user_id;
+        .map_err(|_| ChangeDriverError::FetchRecord)?
+        .ok_or(ChangeDriverError::NotARecord)?;
+
+    if record.is_locked_for(is_admin(act.member.as_ref())) {
+        return Err(ChangeDriverError::Locked);
+    }
+
+    let record_holder = record.driver_user_id;
 
     let driver_options = CreateSelectMenuKind::User {
         default_users: Some(vec![UserId::new(record_holder)]),
@@ -22,12 +65,8 @@ pub async fn handle(ctx: &Context, act: &ComponentInteraction, handler: &Handler
     let driver_dropdown =
         CreateSelectMenu::new("record_select_driver", driver_options).placeholder("No driver selected");
 
-    let message = CreateInteractionResponseMessage::default()
+    Ok(CreateInteractionResponseMessage::default()
         .ephemeral(true)
         .content("Please select the person that drove this record")
-        .select_menu(driver_dropdown);
-
-    let response = CreateInteractionResponse::Message(message);
-
-    act.create_response(&ctx, response).await.unwrap();
+        .select_menu(driver_dropdown))
 }
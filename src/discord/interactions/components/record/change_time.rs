@@ -0,0 +1,66 @@
+use serenity::all::{
+    ComponentInteraction, Context, CreateActionRow, CreateInputText, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateModal, InputTextStyle,
+};
+
+use crate::{
+    discord::{handler::Handler, permissions::is_admin},
+    time_format,
+};
+
+/// The id of the time input in the modal, read back by `submit_time`.
+pub const TIME_INPUT_ID: &str = "record_time";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChangeTimeError {
+    #[error("Something went wrong while fetching the record")]
+    FetchRecord,
+
+    #[error("This message isn't a tracked record")]
+    NotARecord,
+
+    #[error("This record is locked and can only be edited by an admin")]
+    Locked,
+}
+
+pub async fn handle(ctx: &Context, act: &ComponentInteraction, handler: &Handler) {
+    let response = match change_time_modal(act, handler).await {
+        Ok(modal) => CreateInteractionResponse::Modal(modal),
+        Err(error) => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::default()
+                .ephemeral(true)
+                .content(error.to_string()),
+        ),
+    };
+
+    if let Err(why) = act.create_response(&ctx, response).await {
+        eprintln!("could not respond to the change time button: {why}");
+    }
+}
+
+/// A modal asking for the new time of the record behind the clicked message,
+/// prefilled with its current time.
+pub async fn change_time_modal(
+    act: &ComponentInteraction,
+    handler: &Handler,
+) -> Result<CreateModal, ChangeTimeError> {
+    let records = handler.gsheet.records();
+    let record = records
+        .get_by_bot_message_id(act.message.id.get())
+        .await
+        .map_err(|_| ChangeTimeError::FetchRecord)?
+        .ok_or(ChangeTimeError::NotARecord)?;
+
+    if record.is_locked_for(is_admin(act.member.as_ref())) {
+        return Err(ChangeTimeError::Locked);
+    }
+
+    let time_input = CreateInputText::new(InputTextStyle::Short, "Time", TIME_INPUT_ID)
+        .placeholder("m:ss.mmm")
+        .value(time_format::format(record.race_duration))
+        .min_length(1)
+        .max_length(16);
+
+    Ok(CreateModal::new("record_submit_time", "Change time")
+        .components(vec![CreateActionRow::InputText(time_input)]))
+}
@@ -0,0 +1,96 @@
+use serenity::all::{
+    ComponentInteraction, Context, CreateActionRow, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateSelectMenu, CreateSelectMenuKind,
+    CreateSelectMenuOption,
+};
+
+use crate::discord::{handler::Handler, permissions::is_admin};
+
+/// Discord allows 25 options per select menu and 5 menus per message.
+const OPTIONS_PER_MENU: usize = 25;
+const MAX_MENUS: usize = 5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChangeTrackError {
+    #[error("Something went wrong while fetching the record")]
+    FetchRecord,
+
+    #[error("This message isn't a tracked record")]
+    NotARecord,
+
+    #[error("This record is locked and can only be edited by an admin")]
+    Locked,
+}
+
+pub async fn handle(ctx: &Context, act: &ComponentInteraction, handler: &Handler) {
+    let message = match change_track_menu(act, handler).await {
+        Ok(message) => message,
+        Err(error) => CreateInteractionResponseMessage::default()
+            .ephemeral(true)
+            .content(error.to_string()),
+    };
+
+    let response = CreateInteractionResponse::Message(message);
+
+    if let Err(why) = act.create_response(&ctx, response).await {
+        eprintln!("could not respond to the change track button: {why}");
+    }
+}
+
+/// The track picker for the record behind the clicked message, with its current
+/// track preselected.
+pub async fn change_track_menu(
+    act: &ComponentInteraction,
+    handler: &Handler,
+) -> Result<CreateInteractionResponseMessage, ChangeTrackError> {
+    let records = handler.gsheet.records();
+    let record = records
+        .get_by_bot_message_id(act.message.id.get())
+        .await
+        .map_err(|_| ChangeTrackError::FetchRecord)?
+        .ok_or(ChangeTrackError::NotARecord)?;
+
+    if record.is_locked_for(is_admin(act.member.as_ref())) {
+        return Err(ChangeTrackError::Locked);
+    }
+
+    Ok(CreateInteractionResponseMessage::default()
+        .ephemeral(true)
+        .content("Please select the track this record was driven on")
+        .components(track_menus(&handler.track_names().await, &record.track_name)))
+}
+
+/// The tracks spread over as many menus as needed, each with its own
+/// `record_select_track:<n>` id since ids have to be unique within a message.
+pub fn track_menus(track_names: &[String], current_track: &str) -> Vec<CreateActionRow> {
+    let chunks: Vec<&[String]> = track_names.chunks(OPTIONS_PER_MENU).take(MAX_MENUS).collect();
+    let split = chunks.len() > 1;
+
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let options = chunk
+                .iter()
+                .map(|name| {
+                    CreateSelectMenuOption::new(name, name).default_selection(name == current_track)
+                })
+                .collect();
+
+            let placeholder = if split {
+                let first = index * OPTIONS_PER_MENU + 1;
+                format!("Tracks {first}-{}", first + chunk.len() - 1)
+            } else {
+                "No track selected".to_string()
+            };
+
+            let menu = CreateSelectMenu::new(
+                format!("record_select_track:{index}"),
+                CreateSelectMenuKind::String { options },
+            )
+            .placeholder(placeholder);
+
+            CreateActionRow::SelectMenu(menu)
+        })
+        .collect()
+}
@@ -1,2 +1,29 @@
+use serenity::all::{ComponentInteraction, MessageFlags, MessageId};
+
+pub mod approve;
 pub mod change_driver;
-pub mod select_driver;
\ No newline at end of file
+pub mod change_time;
+pub mod change_track;
+pub mod select_driver;
+pub mod select_track;
+pub mod submit_time;
+
+/// The record message a component belongs to. Buttons sit on the record message
+/// itself; the pickers they open are ephemeral replies that reference it. A record
+/// message can itself be a reply to the upload, so its reference is only followed
+/// for ephemeral messages. `None` when an ephemeral picker lost its reference.
+pub fn record_message_id(act: &ComponentInteraction) -> Option<MessageId> {
+    let ephemeral = act
+        .message
+        .flags
+        .is_some_and(|flags| flags.contains(MessageFlags::EPHEMERAL));
+
+    if !ephemeral {
+        return Some(act.message.id);
+    }
+
+    act.message
+        .message_reference
+        .as_ref()
+        .and_then(|reference| reference.message_id)
+}
@@ -1,46 +1,101 @@
 use serenity::all::{
     ComponentInteraction, ComponentInteractionDataKind, Context, CreateInteractionResponse,
-    EditMessage,
+    CreateInteractionResponseMessage,
 };
 
-use crate::discord::{handler::Handler, templates::record::record_embed};
+use crate::discord::{
+    handler::Handler,
+    output::{edit_record_message, RecordMessage},
+    permissions::is_admin,
+    templates::record::record_embed,
+};
+
+use super::record_message_id;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SelectDriverError {
+    #[error("The original record message could not be found")]
+    MissingRecordMessage,
+
+    #[error("No driver was selected")]
+    NoDriverSelected,
+
+    #[error("Something went wrong while fetching the record")]
+    FetchRecord,
+
+    #[error("This message isn't a tracked record")]
+    NotARecord,
+
+    #[error("This record is locked and can only be edited by an admin")]
+    Locked,
+
+    #[error("Something went wrong while updating the record driver")]
+    UpdateFailed,
+
+    #[error("Something went wrong while editing the message")]
+    EditFailed,
+}
 
 pub async fn handle(ctx: &Context, act: &ComponentInteraction, handler: &Handler) {
-    let bot_message_id = act
-        .message
-        .clone()
-        .message_reference
-        .unwrap()
-        .message_id
-        .unwrap()
-        .get();
+    let response = match select_driver(ctx, act, handler).await {
+        Ok(()) => CreateInteractionResponse::Acknowledge,
+        Err(error) => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .ephemeral(true)
+                .content(error.to_string()),
+        ),
+    };
+
+    if let Err(why) = act.create_response(&ctx, response).await {
+        eprintln!("could not respond to the driver selection: {why}");
+    }
+}
+
+/// Moves the record to the selected driver and re-renders its message, see
+/// `record_message_id` for how the record message is found.
+pub async fn select_driver(
+    ctx: &Context,
+    act: &ComponentInteraction,
+    handler: &Handler,
+) -> Result<(), SelectDriverError> {
+    let bot_message_id = record_message_id(act).ok_or(SelectDriverError::MissingRecordMessage)?;
+
     let driver_user_id = match &act.data.kind {
-        ComponentInteractionDataKind::UserSelect { values } => &values[0],
-        _ => panic! {"unexpected interaction data kind"},
+        ComponentInteractionDataKind::UserSelect { values } => values.first(),
+        _ => None,
     }
+    .ok_or(SelectDriverError::NoDriverSelected)?
     .get();
 
     let records = handler.gsheet.records();
 
     let mut record = records
-        .get_by_bot_message_id(bot_message_id)
+        .get_by_bot_message_id(bot_message_id.get())
         .await
-        .unwrap() // TODO: handle the unwrap properly
-        .unwrap(); // TODO: handle the unwrap properly
-
-    record.set_driver_user_id(driver_user_id).await.unwrap(); // TODO: handle the unwrap properly
+        .map_err(|_| SelectDriverError::FetchRecord)?
+        .ok_or(SelectDriverError::NotARecord)?;
 
-    let (embed, components) = record_embed(record, handler).await;
+    // The menu may have been opened before the record got locked.
+    if record.is_locked_for(is_admin(act.member.as_ref())) {
+        return Err(SelectDriverError::Locked);
+    }
 
-    let edit = EditMessage::new()
-        .content("")
-        .embed(embed)
-        .components(components);
-    act.channel_id
-        .edit_message(&ctx, bot_message_id, edit)
-        .await
-        .unwrap();
-    act.create_response(&ctx, CreateInteractionResponse::Acknowledge)
+    record
+        .set_driver_user_id(driver_user_id, act.user.id.get())
         .await
-        .unwrap();
+        .map_err(|_| SelectDriverError::UpdateFailed)?;
+
+    let (embed, components) = record_embed(&ctx.http, record, handler, None, false).await;
+
+    edit_record_message(
+        &ctx.http,
+        handler.record_webhook.get(),
+        act.channel_id,
+        bot_message_id,
+        RecordMessage::embed(embed, components),
+    )
+    .await
+    .map_err(|_| SelectDriverError::EditFailed)?;
+
+    Ok(())
 }
@@ -0,0 +1,107 @@
+use serenity::all::{
+    ComponentInteraction, ComponentInteractionDataKind, Context, CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+};
+
+use crate::discord::{
+    handler::Handler,
+    output::{edit_record_message, RecordMessage},
+    permissions::is_admin,
+    templates::record::record_embed,
+};
+
+use super::record_message_id;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SelectTrackError {
+    #[error("The original record message could not be found")]
+    MissingRecordMessage,
+
+    #[error("No track was selected")]
+    NoTrackSelected,
+
+    #[error("Please select a valid track")]
+    InvalidTrack,
+
+    #[error("Something went wrong while fetching the record")]
+    FetchRecord,
+
+    #[error("This message isn't a tracked record")]
+    NotARecord,
+
+    #[error("This record is locked and can only be edited by an admin")]
+    Locked,
+
+    #[error("Something went wrong while updating the record track")]
+    UpdateFailed,
+
+    #[error("Something went wrong while editing the message")]
+    EditFailed,
+}
+
+pub async fn handle(ctx: &Context, act: &ComponentInteraction, handler: &Handler) {
+    let response = match select_track(ctx, act, handler).await {
+        Ok(()) => CreateInteractionResponse::Acknowledge,
+        Err(error) => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .ephemeral(true)
+                .content(error.to_string()),
+        ),
+    };
+
+    if let Err(why) = act.create_response(&ctx, response).await {
+        eprintln!("could not respond to the track selection: {why}");
+    }
+}
+
+/// Moves the record to the selected track and re-renders its message, see
+/// `record_message_id` for how the record message is found.
+pub async fn select_track(
+    ctx: &Context,
+    act: &ComponentInteraction,
+    handler: &Handler,
+) -> Result<(), SelectTrackError> {
+    let bot_message_id = record_message_id(act).ok_or(SelectTrackError::MissingRecordMessage)?;
+
+    let track_name = match &act.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => values.first(),
+        _ => None,
+    }
+    .ok_or(SelectTrackError::NoTrackSelected)?;
+
+    if !handler.track_names().await.contains(track_name) {
+        return Err(SelectTrackError::InvalidTrack);
+    }
+
+    let records = handler.gsheet.records();
+
+    let mut record = records
+        .get_by_bot_message_id(bot_message_id.get())
+        .await
+        .map_err(|_| SelectTrackError::FetchRecord)?
+        .ok_or(SelectTrackError::NotARecord)?;
+
+    // The menu may have been opened before the record got locked.
+    if record.is_locked_for(is_admin(act.member.as_ref())) {
+        return Err(SelectTrackError::Locked);
+    }
+
+    record
+        .set_track_name(track_name.clone(), act.user.id.get())
+        .await
+        .map_err(|_| SelectTrackError::UpdateFailed)?;
+
+    let (embed, components) = record_embed(&ctx.http, record, handler, None, false).await;
+
+    edit_record_message(
+        &ctx.http,
+        handler.record_webhook.get(),
+        act.channel_id,
+        bot_message_id,
+        RecordMessage::embed(embed, components),
+    )
+    .await
+    .map_err(|_| SelectTrackError::EditFailed)?;
+
+    Ok(())
+}
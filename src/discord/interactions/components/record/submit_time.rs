@@ -0,0 +1,127 @@
+use serenity::all::{
+    ActionRowComponent, Context, CreateInteractionResponse, CreateInteractionResponseMessage,
+    ModalInteraction,
+};
+
+use crate::{
+    discord::{
+        handler::Handler,
+        interactions::{
+            components::record::change_time::TIME_INPUT_ID,
+        },
+        output::{edit_record_message, RecordMessage},
+        permissions::is_admin,
+        templates::record::record_embed,
+    },
+    time_format,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SubmitTimeError {
+    #[error("The original record message could not be found")]
+    MissingRecordMessage,
+
+    #[error("No time was entered")]
+    MissingTime,
+
+    #[error("Provided time was not valid: {0}, please use m:ss.mmm")]
+    InvalidTimeFormat(String),
+
+    #[error("Something went wrong while fetching the record")]
+    FetchRecord,
+
+    #[error("This message isn't a tracked record")]
+    NotARecord,
+
+    #[error("This record is locked and can only be edited by an admin")]
+    Locked,
+
+    #[error("This record is older than {0} days and can no longer be edited")]
+    RecordTooOld(i64),
+
+    #[error("Something went wrong while updating the record time")]
+    UpdateFailed,
+
+    #[error("Something went wrong while editing the message")]
+    EditFailed,
+}
+
+pub async fn handle(ctx: &Context, modal: &ModalInteraction, handler: &Handler) {
+    let response = match submit_time(ctx, modal, handler).await {
+        Ok(()) => CreateInteractionResponse::Acknowledge,
+        Err(error) => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .ephemeral(true)
+                .content(error.to_string()),
+        ),
+    };
+
+    if let Err(why) = modal.create_response(&ctx, response).await {
+        eprintln!("could not respond to the change time modal: {why}");
+    }
+}
+
+/// Stores the time entered in the change time modal, with the same checks as
+/// `/update_time`, and re-renders the record message the modal was opened from.
+pub async fn submit_time(
+    ctx: &Context,
+    modal: &ModalInteraction,
+    handler: &Handler,
+) -> Result<(), SubmitTimeError> {
+    let bot_message_id = modal
+        .message
+        .as_ref()
+        .map(|message| message.id)
+        .ok_or(SubmitTimeError::MissingRecordMessage)?;
+
+    let time_text = modal
+        .data
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == TIME_INPUT_ID => {
+                input.value.as_deref()
+            }
+            _ => None,
+        })
+        .ok_or(SubmitTimeError::MissingTime)?;
+
+    let duration = time_format::parse(time_text)
+        .map_err(|e| SubmitTimeError::InvalidTimeFormat(e.to_string()))?;
+
+    let records = handler.gsheet.records();
+
+    let mut record = records
+        .get_by_bot_message_id(bot_message_id.get())
+        .await
+        .map_err(|_| SubmitTimeError::FetchRecord)?
+        .ok_or(SubmitTimeError::NotARecord)?;
+
+    if record.is_locked_for(is_admin(modal.member.as_ref())) {
+        return Err(SubmitTimeError::Locked);
+    }
+
+    if let Some(max_age_days) = record.edit_age_limit_for(is_admin(modal.member.as_ref())) {
+        return Err(SubmitTimeError::RecordTooOld(max_age_days));
+    }
+
+    record
+        .set_race_duration(duration, modal.user.id.get())
+        .await
+        .map_err(|_| SubmitTimeError::UpdateFailed)?;
+
+    let (embed, components) = record_embed(&ctx.http, record, handler, None, false).await;
+
+    edit_record_message(
+        &ctx.http,
+        handler.record_webhook.get(),
+        modal.channel_id,
+        bot_message_id,
+        RecordMessage::embed(embed, components),
+    )
+    .await
+    .map_err(|_| SubmitTimeError::EditFailed)?;
+
+    Ok(())
+}
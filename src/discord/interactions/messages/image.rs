@@ -1,79 +1,349 @@
-use serenity::all::{Context, EditMessage, Message};
+use std::{future::Future, sync::atomic::Ordering, time::{Duration, Instant}};
+
+use chrono_tz::Tz;
+use serenity::all::{ChannelId, Context, GuildId, Message, MessageId, Timestamp};
 
 use crate::{discord::{
     handler::Handler,
-    interactions::messages::validation::{validate_all, ValidationOutcome},
-    templates::record::record_embed,
-}, sheets::records::record::Record};
+    interactions::{commands::maintenance::PAUSED_MESSAGE, messages::validation::{validate_all, ValidationOutcome}},
+    output::{delete_record_message, edit_record_message, RecordMessage, RecordOutput},
+    templates::record::{record_embed, PbContext},
+}, config::{day_timezone, env_flag, env_parse}, ocr::{content_hash, ExtractError}, sheets::{errors::{DataFetchError, DataUploadError}, players::player::Selection, tracks::track_from_filename, records::{approval_required, record::{NewRecord, Record}}}, time_format};
+
+const BUSY_MESSAGE: &str =
+    "I'm busy reading other screenshots right now, please upload yours again in a minute.";
 
-pub enum OcrProcessOutcome<'a> {
-    Success { record: Record<'a> },
+/// How an upload ended. `R` is the stored record, `Record` outside of tests.
+pub enum OcrProcessOutcome<R> {
+    Success { record: R, pb: Option<PbContext>, first_of_day: bool },
     InvalidImage(String),
     PlayerMissing,
     TrackMissing,
     StorageFailure,
+    NotPersonalBest { time: Duration, personal_best: Duration },
+    /// Slower than the `cap` records kept per track, see `MAX_RECORDS_PER_TRACK`.
+    OutsideCap { time: Duration, cap: usize },
+    Paused,
+    /// Folded into the record of an upload moments before, see `merge_recent`.
+    Merged { record: R, improved: bool, previous: RecentUpload },
 }
 
 pub async fn handle_message(ctx: &Context, msg: &Message, handler: &Handler) {
-    let bytes = match validate_all(msg).await {
+    let channel_override = *handler
+        .records_channel
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let bytes = match validate_all(msg, channel_override).await {
         Ok(b) => b,
         Err(ValidationOutcome::Ignore) => return,
         Err(ValidationOutcome::SystemError(e)) => {
             eprintln!("{e}");
             return;
         }
-        Err(ValidationOutcome::UserError(_)) => {
-            // TODO: inform user
+        Err(ValidationOutcome::UserError(reason)) => {
+            if let Err(why) = msg.reply(&ctx.http, reason).await {
+                eprintln!("could not tell the uploader why the upload was refused: {why}");
+            }
             return;
         }
     };
 
-    let mut message = msg
-        .reply(&ctx.http, "Please wait while the image is being processed")
-        .await
-        .unwrap();
-    let result = process_ocr_message(msg, bytes, handler, &message).await;
+    let selection = current_selection(msg, handler).await;
+
+    // Told in the upload channel, whatever the output is, since nothing else gets posted.
+    let Some(ticket) = handler.ocr_limiter.try_admit() else {
+        if let Err(why) = msg.reply(&ctx.http, BUSY_MESSAGE).await {
+            eprintln!("could not post the busy message: {why}");
+        }
+        return;
+    };
 
-    match result {
-        OcrProcessOutcome::Success { record } => {
-            let (embed, components) = record_embed(record, handler).await;
+    let output = RecordOutput::new(
+        handler.record_webhook.get(),
+        output_channel(handler.records_channel_id(), msg.channel_id),
+    );
 
-            let edit = EditMessage::new()
-                .content("")
-                .embed(embed)
-                .components(components);
-            message.edit(&ctx.http, edit).await.unwrap();
+    // The placeholder's id is stored with the record, so it has to exist before the row is written.
+    let message = match output
+        .post(&ctx.http, msg, "Please wait while the image is being processed")
+        .await
+    {
+        Ok(message) => message,
+        Err(why) => {
+            eprintln!("could not post the processing message: {why}");
+            return;
         }
-        OcrProcessOutcome::InvalidImage(reason) => {
-            let edit = EditMessage::new().content(reason);
-            message.edit(&ctx.http, edit).await.unwrap();
+    };
+    let hash = content_hash(&bytes);
+    if let Ok(current) = &selection
+        && let Some(notice) =
+            duplicate_notice(ctx, handler, msg, &current.selection.track_name, hash).await
+    {
+        if let Err(why) = edit_record_message(&ctx.http, handler.record_webhook.get(), message.channel_id, message.id, notice).await {
+            eprintln!("could not update the processing message: {why}");
         }
-        OcrProcessOutcome::StorageFailure => {
-            let edit = EditMessage::new().content("Failed to save record");
-            message.edit(&ctx.http, edit).await.unwrap();
+        if output.is_separate() {
+            post_confirmation(ctx, msg, &message).await;
         }
-        OcrProcessOutcome::PlayerMissing | OcrProcessOutcome::TrackMissing => {
-            let edit = EditMessage::new()
-                .content("Please select a track first using /play before uploading records.");
-            message.edit(&ctx.http, edit).await.unwrap();
+        return;
+    }
+
+    let result = match selection {
+        Ok(CurrentSelection { selection, inferred }) => {
+            let _permit = ticket.acquire().await;
+            let outcome =
+                process_ocr_message(Upload::from(msg), &bytes, selection.clone(), handler, message.id.get()).await;
+            if inferred && !matches!(outcome, OcrProcessOutcome::Paused) {
+                store_inferred_selection(msg, handler, &selection).await;
+            }
+            outcome
         }
+        Err(outcome) => outcome,
+    };
+
+    let stored = matches!(result, OcrProcessOutcome::Success { .. });
+    if let OcrProcessOutcome::Success { record, .. } = &result {
+        remember_upload(handler, record, message.channel_id, hash);
+    }
+
+    let update = match result {
+        // Only a confirmed row gets the record embed and its edit buttons.
+        OcrProcessOutcome::Success { record, pb, first_of_day } => {
+            let (embed, components) =
+                record_embed(&ctx.http, record, handler, pb, first_of_day).await;
+            RecordMessage::embed(embed, components)
+        }
+        OcrProcessOutcome::InvalidImage(reason) => RecordMessage::text(reason),
+        OcrProcessOutcome::StorageFailure => unsaved_message(),
+        OcrProcessOutcome::NotPersonalBest { time, personal_best } => {
+            RecordMessage::text(format!(
+                "{} is not faster than your personal best of {}, so it was not stored.",
+                time_format::format(time),
+                time_format::format(personal_best)
+            ))
+        }
+        OcrProcessOutcome::OutsideCap { time, cap } => RecordMessage::text(format!(
+            "{} is slower than your {cap} best times on this track, so it was not stored.",
+            time_format::format(time)
+        )),
+        OcrProcessOutcome::Paused => RecordMessage::text(PAUSED_MESSAGE),
+        OcrProcessOutcome::Merged { record, improved, previous } => {
+            finish_merge(ctx, handler, &message, record, improved, previous).await;
+            if should_delete_source(improved, env_flag("DELETE_SOURCE_AFTER_RECORD")) {
+                delete_source_upload(ctx, msg).await;
+            }
+            return;
+        }
+        OcrProcessOutcome::PlayerMissing | OcrProcessOutcome::TrackMissing => RecordMessage::text(
+            "Please select a track first using /play before uploading records.",
+        ),
+    };
+
+    if let Err(why) = edit_record_message(&ctx.http, handler.record_webhook.get(), message.channel_id, message.id, update).await {
+        eprintln!("could not update the processing message: {why}");
+    }
+
+    if output.is_separate() {
+        post_confirmation(ctx, msg, &message).await;
+    }
+
+    if should_delete_source(stored, env_flag("DELETE_SOURCE_AFTER_RECORD")) {
+        delete_source_upload(ctx, msg).await;
     }
 }
 
-pub async fn process_ocr_message<'a>(
+/// Uploads from one of the `UPLOAD_CHANNEL_IDS` have their result posted in the
+/// records channel. Uploads in the records channel get a reply.
+pub fn output_channel(records_channel: Option<ChannelId>, upload_channel: ChannelId) -> Option<ChannelId> {
+    records_channel.filter(|channel_id| *channel_id != upload_channel)
+}
+
+/// With `DELETE_SOURCE_AFTER_RECORD=1` only the record embed stays. The screenshot is
+/// only removed once its record is stored, so a failed upload can still be retried.
+pub fn should_delete_source(stored: bool, enabled: bool) -> bool {
+    stored && enabled
+}
+
+/// Deletes the uploaded screenshot. The record keeps its `user_message_id` for audit.
+/// Without Manage Messages the screenshot just stays, which is only logged.
+async fn delete_source_upload(ctx: &Context, msg: &Message) {
+    let Err(why) = msg.delete(&ctx.http).await else {
+        return;
+    };
+    let status = match &why {
+        serenity::Error::Http(http) => http.status_code().map(|status| status.as_u16()),
+        _ => None,
+    };
+    if lacks_manage_messages(status) {
+        eprintln!(
+            "could not delete upload {}: Manage Messages is needed for DELETE_SOURCE_AFTER_RECORD",
+            msg.id
+        );
+    } else {
+        eprintln!("could not delete upload {}: {why}", msg.id);
+    }
+}
+
+/// A 403 on deleting someone else's message means the bot lacks Manage Messages.
+pub fn lacks_manage_messages(status: Option<u16>) -> bool {
+    status == Some(403)
+}
+
+/// The record message a screenshot was last stored under, per driver and track.
+#[derive(Debug, Clone, Copy)]
+pub struct RecentUpload {
+    pub hash: u64,
+    pub channel_id: ChannelId,
+    pub bot_message_id: MessageId,
+    pub stored_at: Instant,
+}
+
+fn remember_upload(handler: &Handler, record: &Record<'_>, channel_id: ChannelId, hash: u64) {
+    let upload = RecentUpload {
+        hash,
+        channel_id,
+        bot_message_id: MessageId::new(record.bot_message_id),
+        stored_at: Instant::now(),
+    };
+    handler
+        .recent_uploads
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert((record.driver_user_id, record.track_name.clone()), upload);
+}
+
+/// Whether an upload repeats the driver's last screenshot for the track. The record
+/// has to still exist and still be theirs on that track, otherwise it's a new upload.
+/// `record` is the stored record's driver and track.
+pub fn is_duplicate_upload(
+    previous: &RecentUpload,
+    record: Option<(u64, &str)>,
+    hash: u64,
+    driver_user_id: u64,
+    track_name: &str,
+) -> bool {
+    previous.hash == hash
+        && record.is_some_and(|(driver, track)| driver == driver_user_id && track == track_name)
+}
+
+/// When the screenshot was already stored for this track, refreshes that record's
+/// message instead of creating a second record, and returns the notice for the
+/// placeholder. `None` means the upload should be processed as usual.
+async fn duplicate_notice(
+    ctx: &Context,
+    handler: &Handler,
     msg: &Message,
-    bytes: Vec<u8>,
-    handler: &'a Handler,
-    bot_msg: &Message,
-) -> OcrProcessOutcome<'a> {
-    let time = match crate::ocr::extract_time(&bytes).await {
-        Ok(t) => t,
+    track_name: &str,
+    hash: u64,
+) -> Option<RecordMessage> {
+    let driver_user_id = msg.author.id.get();
+    let previous = *handler
+        .recent_uploads
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(&(driver_user_id, track_name.to_string()))?;
+
+    if previous.hash != hash {
+        return None;
+    }
+
+    let records = handler.gsheet.records();
+    let record = match records.get_by_bot_message_id(previous.bot_message_id.get()).await {
+        Ok(record) => record,
         Err(why) => {
-            eprintln!("{why}");
-            return OcrProcessOutcome::InvalidImage("Sorry, I couldn't process that image.".into());
+            eprintln!("duplicate upload lookup failed: {why}");
+            return None;
+        }
+    };
+
+    let stored = record.as_ref().map(|r| (r.driver_user_id, r.track_name.as_str()));
+    if !is_duplicate_upload(&previous, stored, hash, driver_user_id, track_name) {
+        return None;
+    }
+
+    let (embed, components) = record_embed(&ctx.http, record?, handler, None, false).await;
+    let update = RecordMessage::embed(embed, components);
+    if let Err(why) =
+        edit_record_message(&ctx.http, handler.record_webhook.get(), previous.channel_id, previous.bot_message_id, update).await
+    {
+        eprintln!("could not refresh the duplicated record message: {why}");
+    }
+
+    let link = previous.bot_message_id.link(previous.channel_id, msg.guild_id);
+    Some(RecordMessage::text(format!(
+        "This screenshot was already uploaded for {track_name}, so no new record was stored: {link}"
+    )))
+}
+
+/// How long the upload channel confirmation stays, `None` keeps it.
+/// Set through `CONFIRMATION_DELETE_SECS`, 0 (the default) disables deleting.
+pub fn confirmation_delete_delay(secs: u64) -> Option<Duration> {
+    match secs {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// Link to the posted result. Webhook and channel messages can come back without a
+/// guild id, the upload's is the same guild.
+pub fn jump_link(
+    result_id: MessageId,
+    result_channel_id: ChannelId,
+    result_guild_id: Option<GuildId>,
+    upload_guild_id: Option<GuildId>,
+) -> String {
+    result_id.link(result_channel_id, result_guild_id.or(upload_guild_id))
+}
+
+/// Points the uploader at the result in the output channel, since they can't see
+/// it from where they posted.
+async fn post_confirmation(ctx: &Context, msg: &Message, result: &Message) {
+    let link = jump_link(result.id, result.channel_id, result.guild_id, msg.guild_id);
+    let content = format!("Your upload was processed: {link}");
+    let confirmation = match msg.reply(&ctx.http, content).await {
+        Ok(confirmation) => confirmation,
+        Err(why) => {
+            eprintln!("could not post the upload confirmation: {why}");
+            return;
         }
     };
 
+    if let Some(delay) = confirmation_delete_delay(env_parse("CONFIRMATION_DELETE_SECS", 0)) {
+        let http = ctx.http.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Err(why) = confirmation.delete(&http).await {
+                eprintln!("could not delete the upload confirmation: {why}");
+            }
+        });
+    }
+}
+
+/// Marks the placeholder as unsaved, without embed or buttons, so nothing suggests
+/// there is a record behind it.
+fn unsaved_message() -> RecordMessage {
+    RecordMessage::text(
+        "⚠️ Failed to save record, nothing was stored. Please upload the screenshot again.",
+    )
+}
+
+/// The selection an upload counts towards, see `current_selection`.
+pub struct CurrentSelection {
+    pub selection: Selection,
+    /// Read from the filename rather than the Players sheet, see `store_inferred_selection`.
+    pub inferred: bool,
+}
+
+/// What a player has selected with `/play`, read once when their upload arrives.
+///
+/// This is captured before OCR starts and handed to `process_ocr_message`, so a `/play`
+/// for the next track while the screenshot is still being processed doesn't relabel it.
+/// Nothing is written here, an upload arriving during maintenance must not touch the
+/// Players sheet.
+pub async fn current_selection<'a>(
+    msg: &Message,
+    handler: &Handler,
+) -> Result<CurrentSelection, OcrProcessOutcome<Record<'a>>> {
     let players = handler
     .gsheet
     .players();
@@ -82,36 +352,743 @@ pub async fn process_ocr_message<'a>(
         .get_by_user_id(msg.author.id.get())
         .await
     {
-        Ok(Some(p)) => p,
-        Ok(None) => return OcrProcessOutcome::PlayerMissing,
-        Err(_) => return OcrProcessOutcome::StorageFailure,
-    };
-
-    let track_name = match player.current_track.clone() {
-        Some(name) => name,
-        None => return OcrProcessOutcome::TrackMissing,
-    };
-
-    let created = handler
-        .gsheet
-        .records()
-        .create(
-            msg.id.get(),
-            bot_msg.id.get(),
-            msg.timestamp,
-            msg.author.id.get(),
-            track_name.clone(),
-            time,
-        )
+        Ok(player) => player,
+        Err(_) => return Err(OcrProcessOutcome::StorageFailure),
+    };
+
+    if let Some(selection) = player.as_ref().and_then(|p| p.selection()) {
+        return Ok(CurrentSelection { selection, inferred: false });
+    }
+
+    let missing = if player.is_some() {
+        OcrProcessOutcome::TrackMissing
+    } else {
+        OcrProcessOutcome::PlayerMissing
+    };
+    let selection = filename_selection(msg, handler).await.ok_or(missing)?;
+    Ok(CurrentSelection { selection, inferred: true })
+}
+
+/// Stores a selection read from the filename as the player's, creating their row
+/// when they have none, so the next uploads don't depend on the filename. Called
+/// only once the upload got past the maintenance check.
+async fn store_inferred_selection(msg: &Message, handler: &Handler, selection: &Selection) {
+    let players = handler.gsheet.players();
+    let stored = match players.get_by_user_id(msg.author.id.get()).await {
+        Ok(Some(mut player)) => player.select(selection).await,
+        Ok(None) => players
+            .create(
+                msg.author.id.get(),
+                msg.author.display_name(),
+                Some(selection.track_name.clone()),
+                None,
+                None,
+            )
+            .await
+            .map(|_| ()),
+        Err(why) => Err(why.into()),
+    };
+    if let Err(why) = stored {
+        eprintln!("could not store the track inferred from the filename: {why}");
+    }
+}
+
+/// With `INFER_TRACK_FROM_FILENAME=1`, a player who hasn't used `/play` gets the track
+/// their screenshot's filename names, see `track_from_filename`.
+async fn filename_selection(msg: &Message, handler: &Handler) -> Option<Selection> {
+    if !env_flag("INFER_TRACK_FROM_FILENAME") {
+        return None;
+    }
+    let attachment = msg.attachments.first()?;
+    let track_names = handler.track_names().await;
+    let track_name = track_from_filename(&attachment.filename, &track_names)?;
+    Some(Selection {
+        track_name: track_name.to_string(),
+        category: None,
+        platform: None,
+    })
+}
+
+/// The parts of an upload message that end up in its record.
+#[derive(Debug, Clone, Copy)]
+pub struct Upload {
+    pub message_id: u64,
+    pub author_id: u64,
+    pub timestamp: Timestamp,
+}
+
+impl From<&Message> for Upload {
+    fn from(msg: &Message) -> Self {
+        Upload {
+            message_id: msg.id.get(),
+            author_id: msg.author.id.get(),
+            timestamp: msg.timestamp,
+        }
+    }
+}
+
+/// Reads the race time from a screenshot. `Ocr` asks the configured providers.
+pub trait TimeReader {
+    fn read_time(&self, bytes: &[u8]) -> impl Future<Output = Result<Duration, ExtractError>> + Send;
+}
+
+pub struct Ocr;
+
+impl TimeReader for Ocr {
+    async fn read_time(&self, bytes: &[u8]) -> Result<Duration, ExtractError> {
+        crate::ocr::extract_time(bytes).await
+    }
+}
+
+/// The Sheets side of storing an upload. `SheetStore` is the spreadsheet behind
+/// the handler.
+pub trait RecordStore {
+    type Record;
+
+    fn writes_paused(&self) -> bool;
+
+    fn store_only_pb(&self) -> bool;
+
+    fn personal_best(
+        &self,
+        driver_user_id: u64,
+        selection: &Selection,
+    ) -> impl Future<Output = Result<Option<Duration>, DataFetchError>> + Send;
+
+    fn latest_report(
+        &self,
+        driver_user_id: u64,
+    ) -> impl Future<Output = Result<Option<Timestamp>, DataFetchError>> + Send;
+
+    /// See `merge_recent`, `None` stores the upload as a record of its own.
+    fn merge_recent(
+        &self,
+        upload: Upload,
+        time: Duration,
+        selection: &Selection,
+    ) -> impl Future<Output = Option<OcrProcessOutcome<Self::Record>>> + Send;
+
+    fn create(
+        &self,
+        new_record: NewRecord,
+    ) -> impl Future<Output = Result<Self::Record, DataUploadError>> + Send;
+}
+
+pub struct SheetStore<'a> {
+    handler: &'a Handler,
+}
+
+impl<'a> SheetStore<'a> {
+    pub fn new(handler: &'a Handler) -> Self {
+        SheetStore { handler }
+    }
+}
+
+impl<'a> RecordStore for SheetStore<'a> {
+    type Record = Record<'a>;
+
+    fn writes_paused(&self) -> bool {
+        self.handler.writes_paused()
+    }
+
+    fn store_only_pb(&self) -> bool {
+        self.handler.store_only_pb.load(Ordering::Relaxed)
+    }
+
+    async fn personal_best(
+        &self,
+        driver_user_id: u64,
+        selection: &Selection,
+    ) -> Result<Option<Duration>, DataFetchError> {
+        let best = self
+            .handler
+            .gsheet
+            .records()
+            .get_personal_best(driver_user_id, &selection.track_name, selection.category.as_deref())
+            .await?;
+        Ok(best.map(|record| record.race_duration))
+    }
+
+    async fn latest_report(&self, driver_user_id: u64) -> Result<Option<Timestamp>, DataFetchError> {
+        let latest = self.handler.gsheet.records().get_latest_by_driver(driver_user_id).await?;
+        Ok(latest.map(|record| record.report_timestamp))
+    }
+
+    async fn merge_recent(
+        &self,
+        upload: Upload,
+        time: Duration,
+        selection: &Selection,
+    ) -> Option<OcrProcessOutcome<Record<'a>>> {
+        merge_recent(upload, time, selection, self.handler).await
+    }
+
+    async fn create(&self, new_record: NewRecord) -> Result<Record<'a>, DataUploadError> {
+        self.handler.gsheet.records().create(new_record).await
+    }
+}
+
+pub async fn process_ocr_message<'a>(
+    upload: Upload,
+    bytes: &[u8],
+    selection: Selection,
+    handler: &'a Handler,
+    bot_message_id: u64,
+) -> OcrProcessOutcome<Record<'a>> {
+    process_upload(upload, bytes, selection, bot_message_id, &Ocr, &SheetStore::new(handler)).await
+}
+
+/// Reads the time from the screenshot and merges it into a recent record or stores
+/// it, whichever applies.
+pub async fn process_upload<S: RecordStore>(
+    upload: Upload,
+    bytes: &[u8],
+    selection: Selection,
+    bot_message_id: u64,
+    ocr: &impl TimeReader,
+    store: &S,
+) -> OcrProcessOutcome<S::Record> {
+    // Checked before OCR, a paused upload shouldn't cost a provider call either.
+    if store.writes_paused() {
+        return OcrProcessOutcome::Paused;
+    }
+
+    let time = match read_time(ocr, bytes).await {
+        Ok(time) => time,
+        Err(outcome) => return outcome,
+    };
+
+    if let Some(outcome) = store.merge_recent(upload, time, &selection).await {
+        return outcome;
+    }
+
+    store_time(upload, time, selection, store, bot_message_id).await
+}
+
+/// What happens to an upload that follows a stored record of the same driver and track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeDecision {
+    /// Outside the grace window, the upload becomes a record of its own.
+    Separate,
+    /// The upload is faster, its time replaces the earlier record's.
+    ReplaceTime,
+    /// The earlier record is at least as fast, the upload is dropped.
+    KeepExisting,
+}
+
+/// Uploads within `window` of the earlier record are merged, keeping the best time.
+/// A zero window disables merging.
+pub fn merge_decision(
+    elapsed: Duration,
+    window: Duration,
+    time: Duration,
+    existing_time: Duration,
+) -> MergeDecision {
+    if window.is_zero() || elapsed > window {
+        MergeDecision::Separate
+    } else if time_format::cmp_millis(time, existing_time).is_lt() {
+        MergeDecision::ReplaceTime
+    } else {
+        MergeDecision::KeepExisting
+    }
+}
+
+/// With `UPLOAD_GRACE_SECS` set, a driver's uploads for a track within that many
+/// seconds of their last stored record there are merged into it, so a burst of
+/// attempts leaves one embed with the best time. The window counts from the first
+/// record, merges don't extend it. Uploads read at the same time as the first one
+/// can still be stored separately. `None` stores the upload as usual.
+async fn merge_recent<'a>(
+    upload: Upload,
+    time: Duration,
+    selection: &Selection,
+    handler: &'a Handler,
+) -> Option<OcrProcessOutcome<Record<'a>>> {
+    let window = Duration::from_secs(env_parse("UPLOAD_GRACE_SECS", 0));
+    if window.is_zero() {
+        return None;
+    }
+
+    let previous = *handler
+        .recent_uploads
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(&(upload.author_id, selection.track_name.clone()))?;
+    if previous.stored_at.elapsed() > window {
+        return None;
+    }
+
+    let records = handler.gsheet.records();
+    let mut record = match records.get_by_bot_message_id(previous.bot_message_id.get()).await {
+        Ok(record) => record?,
+        Err(why) => {
+            eprintln!("grace window lookup failed: {why}");
+            return None;
+        }
+    };
+
+    if record.driver_user_id != upload.author_id
+        || !record.is_on(&selection.track_name, selection.category.as_deref())
+        || record.is_locked_for(false)
+    {
+        return None;
+    }
+
+    match merge_decision(previous.stored_at.elapsed(), window, time, record.race_duration) {
+        MergeDecision::Separate => None,
+        MergeDecision::KeepExisting => {
+            Some(OcrProcessOutcome::Merged { record, improved: false, previous })
+        }
+        MergeDecision::ReplaceTime => {
+            if let Err(why) = record.set_race_duration(time, upload.author_id).await {
+                eprintln!("storage failure: {why}");
+                return Some(OcrProcessOutcome::StorageFailure);
+            }
+            Some(OcrProcessOutcome::Merged { record, improved: true, previous })
+        }
+    }
+}
+
+/// Re-renders the earlier record's message when its time improved and removes the
+/// placeholder of the merged upload.
+async fn finish_merge(
+    ctx: &Context,
+    handler: &Handler,
+    placeholder: &Message,
+    record: Record<'_>,
+    improved: bool,
+    previous: RecentUpload,
+) {
+    if improved {
+        let (embed, components) = record_embed(&ctx.http, record, handler, None, false).await;
+        let update = RecordMessage::embed(embed, components);
+        if let Err(why) =
+            edit_record_message(&ctx.http, handler.record_webhook.get(), previous.channel_id, previous.bot_message_id, update).await
+        {
+            eprintln!("could not update the merged record message: {why}");
+        }
+    }
+
+    if let Err(why) = delete_record_message(&ctx.http, handler.record_webhook.get(), placeholder.channel_id, placeholder.id).await {
+        eprintln!("could not delete the processing message of a merged upload: {why}");
+    }
+}
+
+/// Runs OCR on the screenshot, mapping failures to the message the uploader sees.
+pub async fn read_time<R>(ocr: &impl TimeReader, bytes: &[u8]) -> Result<Duration, OcrProcessOutcome<R>> {
+    match ocr.read_time(bytes).await {
+        Ok(t) => Ok(t),
+        Err(ExtractError::ImageDimensionsTooLarge(width, height)) => {
+            Err(OcrProcessOutcome::InvalidImage(format!(
+                "That image is too large ({width}x{height}), please upload a smaller screenshot."
+            )))
+        }
+        Err(ExtractError::ImplausibleTime(time)) => Err(OcrProcessOutcome::InvalidImage(format!(
+            "I read {} from that screenshot, which can't be right. Please upload a clearer screenshot.",
+            time_format::format(time)
+        ))),
+        Err(ExtractError::UnsupportedImageFormat(format)) => {
+            Err(OcrProcessOutcome::InvalidImage(format!(
+                "{format} images aren't supported, please upload a PNG, JPEG or WebP screenshot."
+            )))
+        }
+        Err(ExtractError::NotResultScreen) => Err(OcrProcessOutcome::InvalidImage(
+            "That doesn't look like a time trial result. Please upload a screenshot that shows the yellow result card.".into(),
+        )),
+        Err(why) => {
+            eprintln!("{why}");
+            Err(OcrProcessOutcome::InvalidImage("Sorry, I couldn't process that image.".into()))
+        }
+    }
+}
+
+/// Stores a time read from an upload, unless PB-only mode drops it. Independent of
+/// OCR and Discord, so any source of a time can go through the same checks.
+pub async fn store_time<S: RecordStore>(
+    upload: Upload,
+    time: Duration,
+    selection: Selection,
+    store: &S,
+    bot_message_id: u64,
+) -> OcrProcessOutcome<S::Record> {
+    // Looked up before creating so the new record isn't compared against itself.
+    let pb = match store.personal_best(upload.author_id, &selection).await {
+        Ok(Some(personal_best)) => Some(PbContext::Previous { personal_best }),
+        Ok(None) => Some(PbContext::FirstRecord),
+        Err(why) => {
+            eprintln!("personal best lookup failed: {}", why);
+            None
+        }
+    };
+
+    if let Some(PbContext::Previous { personal_best }) = pb
+        && time_format::cmp_millis(time, personal_best).is_ge()
+        && store.store_only_pb()
+    {
+        return OcrProcessOutcome::NotPersonalBest { time, personal_best };
+    }
+
+    // Set FIRST_RUN_OF_DAY=1 to celebrate a driver's first record of the day.
+    let first_of_day = env_flag("FIRST_RUN_OF_DAY")
+        && match store.latest_report(upload.author_id).await {
+            Ok(latest) => is_first_of_day(latest, upload.timestamp, day_timezone()),
+            Err(why) => {
+                eprintln!("latest record lookup failed: {why}");
+                false
+            }
+        };
+
+    let created = store
+        .create(NewRecord {
+            user_message_id: upload.message_id,
+            bot_message_id,
+            report_timestamp: upload.timestamp,
+            driver_user_id: upload.author_id,
+            track_name: selection.track_name,
+            race_duration: time,
+            category: selection.category,
+            approved: !approval_required(),
+            platform: selection.platform,
+        })
         .await;
 
     let record = match created {
         Ok(record) => record,
+        Err(DataUploadError::OutsideRecordCap(cap)) => {
+            return OcrProcessOutcome::OutsideCap { time, cap };
+        }
         Err(why) => {
             eprintln!("storage failure: {}", why);
             return OcrProcessOutcome::StorageFailure
         },
     };
 
-    OcrProcessOutcome::Success { record }
+    OcrProcessOutcome::Success { record, pb, first_of_day }
+}
+
+/// Whether `now` falls on a later calendar day in `tz` than the previous record.
+pub fn is_first_of_day(previous: Option<Timestamp>, now: Timestamp, tz: Tz) -> bool {
+    previous.is_none_or(|previous| {
+        previous.with_timezone(&tz).date_naive() < now.with_timezone(&tz).date_naive()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, sync::Mutex};
+
+    use super::*;
+    use crate::discord::interactions::messages::validation::accepts_channel;
+
+    /// Answers with a fixed time, or as if the screenshot wasn't a result screen.
+    struct FakeOcr(Option<Duration>);
+
+    impl TimeReader for FakeOcr {
+        async fn read_time(&self, _bytes: &[u8]) -> Result<Duration, ExtractError> {
+            self.0.ok_or(ExtractError::NotResultScreen)
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeSheet {
+        paused: bool,
+        /// PB-only mode, see `/store_mode`.
+        store_only_pb: bool,
+        personal_best: Option<Duration>,
+        fail_writes: bool,
+        /// Refuses every record as if it fell outside `MAX_RECORDS_PER_TRACK`.
+        cap: Option<usize>,
+        created: Mutex<Vec<NewRecord>>,
+    }
+
+    impl RecordStore for FakeSheet {
+        type Record = NewRecord;
+
+        fn writes_paused(&self) -> bool {
+            self.paused
+        }
+
+        fn store_only_pb(&self) -> bool {
+            self.store_only_pb
+        }
+
+        async fn personal_best(&self, _: u64, _: &Selection) -> Result<Option<Duration>, DataFetchError> {
+            Ok(self.personal_best)
+        }
+
+        async fn latest_report(&self, _: u64) -> Result<Option<Timestamp>, DataFetchError> {
+            Ok(None)
+        }
+
+        async fn merge_recent(&self, _: Upload, _: Duration, _: &Selection) -> Option<OcrProcessOutcome<NewRecord>> {
+            None
+        }
+
+        async fn create(&self, new_record: NewRecord) -> Result<NewRecord, DataUploadError> {
+            if self.fail_writes {
+                return Err(DataUploadError::MissingOrUnexpectedResponse);
+            }
+            if let Some(cap) = self.cap {
+                return Err(DataUploadError::OutsideRecordCap(cap));
+            }
+            self.created.lock().unwrap().push(new_record.clone());
+            Ok(new_record)
+        }
+    }
+
+    fn upload() -> Upload {
+        Upload {
+            message_id: 11,
+            author_id: 22,
+            timestamp: Timestamp::parse("2026-05-01T18:00:00Z").unwrap(),
+        }
+    }
+
+    fn selection() -> Selection {
+        Selection {
+            track_name: "Mario Bros. Circuit".to_string(),
+            category: Some("150cc".to_string()),
+            platform: None,
+        }
+    }
+
+    async fn run(ocr: FakeOcr, sheet: &FakeSheet) -> OcrProcessOutcome<NewRecord> {
+        process_upload(upload(), b"screenshot", selection(), 33, &ocr, sheet).await
+    }
+
+    #[tokio::test]
+    async fn stores_the_time_read_from_the_upload() {
+        let sheet = FakeSheet { personal_best: Some(Duration::from_millis(95_000)), ..Default::default() };
+
+        let outcome = run(FakeOcr(Some(Duration::from_millis(90_123))), &sheet).await;
+
+        let OcrProcessOutcome::Success { record, pb, .. } = outcome else {
+            panic!("expected the upload to be stored");
+        };
+        assert_eq!(record.user_message_id, 11);
+        assert_eq!(record.bot_message_id, 33);
+        assert_eq!(record.driver_user_id, 22);
+        assert_eq!(record.track_name, "Mario Bros. Circuit");
+        assert_eq!(record.category.as_deref(), Some("150cc"));
+        assert_eq!(record.race_duration, Duration::from_millis(90_123));
+        assert!(matches!(
+            pb,
+            Some(PbContext::Previous { personal_best }) if personal_best == Duration::from_millis(95_000)
+        ));
+        assert_eq!(sheet.created.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn ocr_failure_stores_nothing() {
+        let sheet = FakeSheet::default();
+
+        let outcome = run(FakeOcr(None), &sheet).await;
+
+        assert!(matches!(outcome, OcrProcessOutcome::InvalidImage(reason) if reason.contains("time trial result")));
+        assert!(sheet.created.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn sheets_failure_is_a_storage_failure() {
+        let sheet = FakeSheet { fail_writes: true, ..Default::default() };
+
+        let outcome = run(FakeOcr(Some(Duration::from_millis(90_123))), &sheet).await;
+
+        assert!(matches!(outcome, OcrProcessOutcome::StorageFailure));
+    }
+
+    #[tokio::test]
+    async fn upload_outside_the_cap_is_reported() {
+        let sheet = FakeSheet { cap: Some(1), ..Default::default() };
+
+        let outcome = run(FakeOcr(Some(Duration::from_millis(90_123))), &sheet).await;
+
+        assert!(matches!(outcome, OcrProcessOutcome::OutsideCap { cap: 1, .. }));
+    }
+
+    #[tokio::test]
+    async fn pb_only_mode_skips_a_slower_time() {
+        let sheet = FakeSheet {
+            store_only_pb: true,
+            personal_best: Some(Duration::from_millis(90_000)),
+            ..Default::default()
+        };
+
+        let outcome = run(FakeOcr(Some(Duration::from_millis(90_123))), &sheet).await;
+
+        assert!(matches!(
+            outcome,
+            OcrProcessOutcome::NotPersonalBest { time, personal_best }
+                if time == Duration::from_millis(90_123) && personal_best == Duration::from_millis(90_000)
+        ));
+        assert!(sheet.created.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn pb_only_mode_stores_a_faster_time() {
+        let sheet = FakeSheet {
+            store_only_pb: true,
+            personal_best: Some(Duration::from_millis(90_000)),
+            ..Default::default()
+        };
+
+        let outcome = run(FakeOcr(Some(Duration::from_millis(89_999))), &sheet).await;
+
+        assert!(matches!(outcome, OcrProcessOutcome::Success { .. }));
+        assert_eq!(sheet.created.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn slower_time_is_stored_outside_pb_only_mode() {
+        let sheet = FakeSheet { personal_best: Some(Duration::from_millis(90_000)), ..Default::default() };
+
+        let outcome = run(FakeOcr(Some(Duration::from_millis(90_123))), &sheet).await;
+
+        assert!(matches!(outcome, OcrProcessOutcome::Success { .. }));
+        assert_eq!(sheet.created.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn paused_uploads_are_not_read_or_stored() {
+        let sheet = FakeSheet { paused: true, ..Default::default() };
+
+        let outcome = run(FakeOcr(Some(Duration::from_millis(90_123))), &sheet).await;
+
+        assert!(matches!(outcome, OcrProcessOutcome::Paused));
+        assert!(sheet.created.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn jump_link_points_at_the_result() {
+        let link = jump_link(MessageId::new(3), ChannelId::new(2), Some(GuildId::new(1)), None);
+        assert_eq!(link, "https://discord.com/channels/1/2/3");
+    }
+
+    #[test]
+    fn jump_link_uses_the_uploads_guild_when_the_result_has_none() {
+        let link = jump_link(MessageId::new(3), ChannelId::new(2), None, Some(GuildId::new(1)));
+        assert_eq!(link, "https://discord.com/channels/1/2/3");
+    }
+
+    #[test]
+    fn confirmation_is_kept_by_default() {
+        assert_eq!(confirmation_delete_delay(0), None);
+        assert_eq!(confirmation_delete_delay(15), Some(Duration::from_secs(15)));
+    }
+
+    #[test]
+    fn source_is_only_deleted_once_stored() {
+        assert!(should_delete_source(true, true));
+        assert!(!should_delete_source(false, true));
+        assert!(!should_delete_source(true, false));
+        assert!(!should_delete_source(false, false));
+    }
+
+    #[test]
+    fn forbidden_delete_points_at_manage_messages() {
+        assert!(lacks_manage_messages(Some(403)));
+        assert!(!lacks_manage_messages(Some(404)));
+        assert!(!lacks_manage_messages(None));
+    }
+
+    fn at(rfc3339: &str) -> Timestamp {
+        Timestamp::parse(rfc3339).unwrap()
+    }
+
+    #[test]
+    fn first_record_is_first_of_day() {
+        assert!(is_first_of_day(None, at("2026-05-01T18:00:00Z"), chrono_tz::Europe::Amsterdam));
+    }
+
+    #[test]
+    fn day_turns_over_at_local_midnight() {
+        let tz = chrono_tz::Europe::Amsterdam;
+        // 23:30 and 00:10 local, both on the same UTC day.
+        assert!(is_first_of_day(Some(at("2026-05-01T21:30:00Z")), at("2026-05-01T22:10:00Z"), tz));
+        // 00:10 and 01:50 local, across UTC midnight.
+        assert!(!is_first_of_day(Some(at("2026-05-01T22:10:00Z")), at("2026-05-01T23:50:00Z"), tz));
+    }
+
+    #[test]
+    fn dst_changes_keep_the_local_day() {
+        let tz = chrono_tz::Europe::Amsterdam;
+        // Spring forward: 23:30 CET, then 03:30 CEST the next day.
+        assert!(is_first_of_day(Some(at("2026-03-28T22:30:00Z")), at("2026-03-29T01:30:00Z"), tz));
+        // 00:30 CET and 23:30 CEST are the same 23 hour day.
+        assert!(!is_first_of_day(Some(at("2026-03-28T23:30:00Z")), at("2026-03-29T21:30:00Z"), tz));
+        // Fall back: 00:30 CEST and 23:30 CET are the same 25 hour day.
+        assert!(!is_first_of_day(Some(at("2026-10-24T22:30:00Z")), at("2026-10-25T22:30:00Z"), tz));
+        assert!(is_first_of_day(Some(at("2026-10-24T22:30:00Z")), at("2026-10-25T23:30:00Z"), tz));
+    }
+
+    #[test]
+    fn faster_upload_in_the_window_replaces_the_time() {
+        let decision = merge_decision(
+            Duration::from_secs(30),
+            Duration::from_secs(60),
+            Duration::from_millis(80_000),
+            Duration::from_millis(81_000),
+        );
+        assert_eq!(decision, MergeDecision::ReplaceTime);
+    }
+
+    #[test]
+    fn slower_or_equal_upload_keeps_the_record() {
+        let window = Duration::from_secs(60);
+        let existing = Duration::from_millis(80_000);
+        assert_eq!(merge_decision(Duration::ZERO, window, Duration::from_millis(80_500), existing), MergeDecision::KeepExisting);
+        assert_eq!(merge_decision(Duration::ZERO, window, Duration::from_micros(80_000_400), existing), MergeDecision::KeepExisting);
+    }
+
+    #[test]
+    fn merging_needs_an_open_window() {
+        let (fast, slow) = (Duration::from_millis(80_000), Duration::from_millis(81_000));
+        assert_eq!(merge_decision(Duration::from_secs(61), Duration::from_secs(60), fast, slow), MergeDecision::Separate);
+        assert_eq!(merge_decision(Duration::ZERO, Duration::ZERO, fast, slow), MergeDecision::Separate);
+    }
+
+    fn recent_upload(hash: u64) -> RecentUpload {
+        RecentUpload {
+            hash,
+            channel_id: ChannelId::new(2),
+            bot_message_id: MessageId::new(3),
+            stored_at: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn same_screenshot_for_the_same_record_is_a_duplicate() {
+        let previous = recent_upload(42);
+        assert!(is_duplicate_upload(&previous, Some((7, "Mario Circuit")), 42, 7, "Mario Circuit"));
+    }
+
+    #[test]
+    fn changed_or_missing_records_make_a_new_upload() {
+        let previous = recent_upload(42);
+        assert!(!is_duplicate_upload(&previous, Some((7, "Mario Circuit")), 43, 7, "Mario Circuit"));
+        assert!(!is_duplicate_upload(&previous, None, 42, 7, "Mario Circuit"));
+        assert!(!is_duplicate_upload(&previous, Some((8, "Mario Circuit")), 42, 7, "Mario Circuit"));
+        assert!(!is_duplicate_upload(&previous, Some((7, "Luigi Circuit")), 42, 7, "Mario Circuit"));
+    }
+
+    #[test]
+    fn upload_channel_results_are_posted_in_the_records_channel() {
+        let (records, upload) = (ChannelId::new(1), ChannelId::new(2));
+        assert!(accepts_channel(upload, records, &HashSet::from([2])));
+
+        let output = RecordOutput::new(None, output_channel(Some(records), upload));
+        assert!(matches!(output, RecordOutput::Channel(channel_id) if channel_id == records));
+        assert!(output.is_separate());
+    }
+
+    #[test]
+    fn records_channel_uploads_get_a_reply() {
+        let records = ChannelId::new(1);
+        assert!(accepts_channel(records, records, &HashSet::new()));
+        assert!(!accepts_channel(ChannelId::new(3), records, &HashSet::from([2])));
+
+        let output = RecordOutput::new(None, output_channel(Some(records), records));
+        assert!(matches!(output, RecordOutput::Reply));
+        assert!(!output.is_separate());
+    }
 }
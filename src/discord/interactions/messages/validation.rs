@@ -1,7 +1,7 @@
-use std::{env, str::FromStr};
+use std::{collections::HashSet, env, str::FromStr};
 
 use mime::Mime;
-use serenity::all::{Attachment, ChannelId, Message};
+use serenity::all::{Attachment, ChannelId, Message, UserId, WebhookId};
 
 pub enum ValidationOutcome {
     Ignore,
@@ -9,8 +9,11 @@ pub enum ValidationOutcome {
     SystemError(&'static str),
 }
 
-pub async fn validate_all(msg: &Message) -> Result<Vec<u8>, ValidationOutcome> {
-    validate_channel(msg)?;
+pub async fn validate_all(
+    msg: &Message,
+    channel_override: Option<ChannelId>,
+) -> Result<Vec<u8>, ValidationOutcome> {
+    validate_channel(msg, channel_override)?;
     validate_from_user(msg)?;
     let att = get_single_attachment(msg)?;
     validate_filename_mime_type(&att)?;
@@ -19,33 +22,82 @@ pub async fn validate_all(msg: &Message) -> Result<Vec<u8>, ValidationOutcome> {
     Ok(data)
 }
 
-fn validate_channel(msg: &Message) -> Result<(), ValidationOutcome> {
-    let channel_id = env::var("CHANNEL_ID")
-        .map_err(|_| ValidationOutcome::SystemError("Failed to get CHANNEL_ID env var"))?;
-    let channel_id = ChannelId::from_str(&channel_id)
-        .map_err(|_| ValidationOutcome::SystemError("Invalid CHANNEL_ID format"))?;
-    if msg.channel_id == channel_id {
+fn validate_channel(
+    msg: &Message,
+    channel_override: Option<ChannelId>,
+) -> Result<(), ValidationOutcome> {
+    let channel_id = records_channel_id(channel_override)?;
+    if accepts_channel(msg.channel_id, channel_id, &upload_channel_ids()) {
         Ok(())
     } else {
         Err(ValidationOutcome::Ignore) 
     }
 }
 
+/// Channels from the comma separated `UPLOAD_CHANNEL_IDS` that uploads are read from
+/// besides the records channel. Their results are posted in the records channel, with
+/// a confirmation in the upload channel.
+pub fn upload_channel_ids() -> HashSet<u64> {
+    parse_ids(&env::var("UPLOAD_CHANNEL_IDS").unwrap_or_default())
+}
+
+pub fn accepts_channel(channel_id: ChannelId, records_channel: ChannelId, upload_channels: &HashSet<u64>) -> bool {
+    channel_id == records_channel || upload_channels.contains(&channel_id.get())
+}
+
+/// The channel set through `/set_channel` wins, the `CHANNEL_ID` env var is the default.
+pub fn records_channel_id(
+    channel_override: Option<ChannelId>,
+) -> Result<ChannelId, ValidationOutcome> {
+    pick_records_channel(channel_override, env::var("CHANNEL_ID").ok().as_deref())
+}
+
+fn pick_records_channel(
+    channel_override: Option<ChannelId>,
+    channel_env: Option<&str>,
+) -> Result<ChannelId, ValidationOutcome> {
+    if let Some(channel_id) = channel_override {
+        return Ok(channel_id);
+    }
+    let channel_id =
+        channel_env.ok_or(ValidationOutcome::SystemError("Failed to get CHANNEL_ID env var"))?;
+    ChannelId::from_str(channel_id)
+        .map_err(|_| ValidationOutcome::SystemError("Invalid CHANNEL_ID format"))
+}
+
 fn validate_from_user(msg: &Message) -> Result<(), ValidationOutcome> {
-    if !msg.author.bot {
+    if !msg.author.bot || is_trusted_bot(msg.author.id, msg.webhook_id, &trusted_bot_ids()) {
         Ok(())
     } else {
         Err(ValidationOutcome::Ignore)
     }
 }
 
+/// Bot and webhook ids from the comma separated `TRUSTED_BOT_IDS`, e.g. a capture-card
+/// integration. Their uploads are processed like a user's; every other bot is ignored.
+pub fn trusted_bot_ids() -> HashSet<u64> {
+    parse_ids(&env::var("TRUSTED_BOT_IDS").unwrap_or_default())
+}
+
+fn parse_ids(ids: &str) -> HashSet<u64> {
+    ids.split(',')
+        .filter_map(|id| id.trim().parse().ok())
+        .collect()
+}
+
+/// Webhook messages carry the webhook's id both as author and as `webhook_id`.
+pub fn is_trusted_bot(author: UserId, webhook: Option<WebhookId>, trusted: &HashSet<u64>) -> bool {
+    trusted.contains(&author.get())
+        || webhook.is_some_and(|id| trusted.contains(&id.get()))
+}
+
 fn get_single_attachment(msg: &Message) -> Result<Attachment, ValidationOutcome> {
     if msg.attachments.len() != 1 {
         return Err(ValidationOutcome::Ignore);
     }
 
     msg.attachments
-        .get(0)
+        .first()
         .cloned()
         .ok_or(ValidationOutcome::SystemError("Could not get attachment, even though it should exist"))
 }
@@ -61,22 +113,103 @@ fn validate_filename_mime_type(att: &Attachment) -> Result<(), ValidationOutcome
     if mime.type_() == mime::IMAGE {
         Ok(())
     } else {
-        Err(ValidationOutcome::UserError("File is not an image"))
+        Err(ValidationOutcome::UserError("Only image uploads can be read as records."))
     }
 }
 
+const HTML_DOWNLOAD_MESSAGE: &str =
+    "Discord returned an error downloading the image, please re-upload it.";
+
+/// Discord's CDN occasionally answers with an HTML error page instead of the file,
+/// which usually succeeds on a second try.
 async fn download_attachment(att: Attachment) -> Result<Vec<u8>, ValidationOutcome> {
+    let data = download_once(&att).await?;
+    if !looks_like_html(&data) {
+        return Ok(data);
+    }
+
+    eprintln!("attachment {} downloaded as an HTML page, retrying", att.id);
+    let data = download_once(&att).await?;
+    validate_not_html(&data)?;
+    Ok(data)
+}
+
+async fn download_once(att: &Attachment) -> Result<Vec<u8>, ValidationOutcome> {
     att.download()
         .await
-        .map_err(|_| ValidationOutcome::UserError("Download failed"))
+        .map_err(|_| ValidationOutcome::UserError("The image could not be downloaded, please re-upload it."))
+}
+
+fn validate_not_html(data: &[u8]) -> Result<(), ValidationOutcome> {
+    if looks_like_html(data) {
+        Err(ValidationOutcome::UserError(HTML_DOWNLOAD_MESSAGE))
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether the bytes start like an HTML or XML document rather than an image.
+pub fn looks_like_html(data: &[u8]) -> bool {
+    let head = &data[..data.len().min(512)];
+    let head = String::from_utf8_lossy(head).trim_start().to_ascii_lowercase();
+    ["<!doctype html", "<html", "<?xml", "<head", "<body"]
+        .iter()
+        .any(|prefix| head.starts_with(prefix))
 }
 
 fn validate_content_mime_type(data: &[u8]) -> Result<(), ValidationOutcome> {
     let info =
-        infer::get(data).ok_or(ValidationOutcome::UserError("Cannot infer file type"))?;
+        infer::get(data).ok_or(ValidationOutcome::UserError("That file doesn't look like an image."))?;
     if info.matcher_type() == infer::MatcherType::Image {
         Ok(())
     } else {
-        Err(ValidationOutcome::UserError("Content is not image"))
+        Err(ValidationOutcome::UserError("That file doesn't look like an image."))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_skip_blanks_and_garbage() {
+        assert_eq!(parse_ids(" 1, 2,,abc"), HashSet::from([1, 2]));
+        assert!(parse_ids("").is_empty());
+    }
+
+    #[test]
+    fn listed_author_or_webhook_is_trusted() {
+        let trusted = HashSet::from([7]);
+        assert!(is_trusted_bot(UserId::new(7), None, &trusted));
+        assert!(is_trusted_bot(UserId::new(8), Some(WebhookId::new(7)), &trusted));
+        assert!(!is_trusted_bot(UserId::new(8), Some(WebhookId::new(9)), &trusted));
+        assert!(!is_trusted_bot(UserId::new(8), None, &HashSet::new()));
+    }
+
+    #[test]
+    fn html_error_pages_are_recognised() {
+        assert!(looks_like_html(b"<!DOCTYPE html><html><body>502</body></html>"));
+        assert!(looks_like_html(b"\r\n  <HTML>"));
+        assert!(looks_like_html(b"<?xml version=\"1.0\"?><Error>AccessDenied</Error>"));
+    }
+
+    #[test]
+    fn images_are_not_html() {
+        assert!(!looks_like_html(b"\x89PNG\r\n\x1a\n<html>"));
+        assert!(!looks_like_html(&[0xFF, 0xD8, 0xFF, 0xE0]));
+        assert!(!looks_like_html(b""));
+    }
+
+    #[test]
+    fn channel_override_wins_over_the_env() {
+        let channel_override = Some(ChannelId::new(222));
+        assert_eq!(pick_records_channel(channel_override, Some("111")).ok(), channel_override);
+        assert_eq!(pick_records_channel(channel_override, Some("not an id")).ok(), channel_override);
+    }
+
+    #[test]
+    fn env_channel_is_the_default() {
+        assert_eq!(pick_records_channel(None, Some("111")).ok(), Some(ChannelId::new(111)));
+        assert!(matches!(pick_records_channel(None, Some("general")), Err(ValidationOutcome::SystemError(_))));
+        assert!(matches!(pick_records_channel(None, None), Err(ValidationOutcome::SystemError(_))));
+    }
+}
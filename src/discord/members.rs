@@ -0,0 +1,88 @@
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+use serenity::all::{GuildId, Http, UserId};
+
+use crate::{config::env_flag, discord::handler::Handler, sheets::records::record::Record};
+
+const MEMBERSHIP_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Membership {
+    Present,
+    Departed,
+    /// The lookup failed for another reason than the member being gone.
+    Unknown,
+}
+
+/// Whether the user is still in the guild. Answers are cached for ten minutes,
+/// failed lookups are not cached and count as `Unknown`.
+pub async fn membership(http: &Http, handler: &Handler, user_id: u64) -> Membership {
+    if let Some((checked_at, present)) = handler
+        .member_cache
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(&user_id)
+        && checked_at.elapsed() < MEMBERSHIP_TTL
+    {
+        return if *present { Membership::Present } else { Membership::Departed };
+    }
+
+    let Some(guild) = env::var("GUILD_ID").ok().and_then(|id| id.parse::<u64>().ok()) else {
+        return Membership::Unknown;
+    };
+
+    let present = match GuildId::new(guild).member(http, UserId::new(user_id)).await {
+        Ok(_) => true,
+        Err(serenity::Error::Http(why))
+            if why.status_code().map(|status| status.as_u16()) == Some(404) =>
+        {
+            false
+        }
+        Err(why) => {
+            eprintln!("member lookup for {user_id} failed: {why}");
+            return Membership::Unknown;
+        }
+    };
+
+    handler
+        .member_cache
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(user_id, (Instant::now(), present));
+
+    if present { Membership::Present } else { Membership::Departed }
+}
+
+/// How to show the record's driver in an embed. With `MARK_DEPARTED_DRIVERS=1`, drivers
+/// who left the guild get their Players name and a "(left)" mark instead of a mention
+/// Discord can no longer resolve.
+pub async fn driver_mention(http: &Http, handler: &Handler, record: &Record<'_>) -> String {
+    let mention = format!("<@{}>", record.driver_user_id);
+    if !env_flag("MARK_DEPARTED_DRIVERS") {
+        return mention;
+    }
+
+    match membership(http, handler, record.driver_user_id).await {
+        Membership::Departed => mark_departed(&record.driver_display_name().await),
+        Membership::Present | Membership::Unknown => mention,
+    }
+}
+
+/// Like `driver_mention`, but for plain-text output where mentions don't render.
+pub async fn driver_name(http: &Http, handler: &Handler, record: &Record<'_>) -> String {
+    let name = record.driver_display_name().await;
+    if env_flag("MARK_DEPARTED_DRIVERS")
+        && membership(http, handler, record.driver_user_id).await == Membership::Departed
+    {
+        mark_departed(&name)
+    } else {
+        name
+    }
+}
+
+fn mark_departed(name: &str) -> String {
+    format!("{name} (left)")
+}
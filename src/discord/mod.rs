@@ -1,3 +1,7 @@
+pub mod cooldown;
 pub mod handler;
 pub mod interactions;
-pub mod templates;
\ No newline at end of file
+pub mod members;
+pub mod output;
+pub mod permissions;
+pub mod templates;
@@ -0,0 +1,300 @@
+use std::env;
+
+use serenity::all::{
+    ApplicationId, ChannelId, CreateActionRow, CreateEmbed, CreateMessage, EditMessage, EditWebhookMessage,
+    ExecuteWebhook, Http, Message, MessageId, Webhook,
+};
+
+/// Where record results are posted.
+pub enum RecordOutput {
+    /// A reply to the upload, the default.
+    Reply,
+    /// The records channel (`/set_channel` or `CHANNEL_ID`), for uploads posted in one
+    /// of the `UPLOAD_CHANNEL_IDS`.
+    Channel(ChannelId),
+    /// `RECORD_WEBHOOK_URL`, so the name and avatar can differ from the bot's.
+    Webhook(Box<Webhook>),
+}
+
+/// Which of the `RecordOutput`s applies, see `select_output`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum OutputTarget {
+    Reply,
+    Channel(ChannelId),
+    Webhook,
+}
+
+/// A usable webhook wins over an output channel, without either the result is a reply.
+pub fn select_output(has_webhook: bool, output_channel: Option<ChannelId>) -> OutputTarget {
+    match (has_webhook, output_channel) {
+        (true, _) => OutputTarget::Webhook,
+        (false, Some(channel_id)) => OutputTarget::Channel(channel_id),
+        (false, None) => OutputTarget::Reply,
+    }
+}
+
+/// Only webhooks created by the bot's own application can post buttons, and record
+/// messages need theirs to be edited.
+pub fn webhook_usable(webhook_application_id: Option<ApplicationId>, application_id: ApplicationId) -> bool {
+    webhook_application_id == Some(application_id)
+}
+
+fn webhook_url() -> Option<String> {
+    env::var("RECORD_WEBHOOK_URL")
+        .ok()
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+}
+
+/// Looks up `RECORD_WEBHOOK_URL` once at startup, see `Handler::record_webhook`. A
+/// webhook that can't be resolved or belongs to another application is left unused,
+/// records then go to the output channel or are replies.
+pub async fn resolve_record_webhook(http: &Http, application_id: ApplicationId) -> Option<Webhook> {
+    let url = webhook_url()?;
+    let webhook = match Webhook::from_url(http, &url).await {
+        Ok(webhook) => webhook,
+        Err(why) => {
+            eprintln!("WARNING: could not resolve RECORD_WEBHOOK_URL, not using it: {why}");
+            return None;
+        }
+    };
+    if !webhook_usable(webhook.application_id, application_id) {
+        eprintln!(
+            "WARNING: RECORD_WEBHOOK_URL was not created by this bot, its messages can't carry the record buttons, not using it"
+        );
+        return None;
+    }
+    Some(webhook)
+}
+
+impl RecordOutput {
+    /// `webhook` is the one resolved at startup, `None` when there is none to use.
+    /// `output_channel` is the records channel when the upload came from another one.
+    pub fn new(webhook: Option<&Webhook>, output_channel: Option<ChannelId>) -> Self {
+        match select_output(webhook.is_some(), output_channel) {
+            OutputTarget::Webhook => webhook
+                .map(|webhook| RecordOutput::Webhook(Box::new(webhook.clone())))
+                .unwrap_or(RecordOutput::Reply),
+            OutputTarget::Channel(channel_id) => RecordOutput::Channel(channel_id),
+            OutputTarget::Reply => RecordOutput::Reply,
+        }
+    }
+
+    /// Whether the uploader can't see the result from the upload channel.
+    pub fn is_separate(&self) -> bool {
+        !matches!(self, RecordOutput::Reply)
+    }
+
+    pub async fn post(&self, http: &Http, upload: &Message, content: &str) -> serenity::Result<Message> {
+        match self {
+            RecordOutput::Reply => upload.reply(http, content).await,
+            RecordOutput::Channel(channel_id) => {
+                let content = format!("<@{}> {content}", upload.author.id);
+                channel_id.send_message(http, CreateMessage::new().content(content)).await
+            }
+            RecordOutput::Webhook(webhook) => {
+                let content = format!("<@{}> {content}", upload.author.id);
+                webhook
+                    .execute(http, true, ExecuteWebhook::new().content(content))
+                    .await?
+                    .ok_or(serenity::Error::Other("webhook did not return the posted message"))
+            }
+        }
+    }
+}
+
+/// The full content of a record message; every edit replaces all of it.
+#[derive(Clone)]
+pub struct RecordMessage {
+    pub content: String,
+    pub embeds: Vec<CreateEmbed>,
+    pub components: Vec<CreateActionRow>,
+}
+
+impl RecordMessage {
+    pub fn text(content: impl Into<String>) -> Self {
+        RecordMessage {
+            content: content.into(),
+            embeds: vec![],
+            components: vec![],
+        }
+    }
+
+    pub fn embed(embed: CreateEmbed, components: Vec<CreateActionRow>) -> Self {
+        RecordMessage {
+            content: String::new(),
+            embeds: vec![embed],
+            components,
+        }
+    }
+}
+
+/// Edits a record message, whoever posted it. Messages in the webhook's channel are
+/// edited through the webhook first; the bot can't edit those itself, and records
+/// from before the webhook was configured fall back to a normal edit.
+pub async fn edit_record_message(
+    http: &Http,
+    webhook: Option<&Webhook>,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    message: RecordMessage,
+) -> serenity::Result<()> {
+    if let Some(webhook) = webhook
+        && webhook.channel_id == Some(channel_id)
+    {
+        let edit = EditWebhookMessage::new()
+            .content(message.content.clone())
+            .embeds(message.embeds.clone())
+            .components(message.components.clone());
+        if webhook.edit_message(http, message_id, edit).await.is_ok() {
+            return Ok(());
+        }
+    }
+
+    let edit = EditMessage::new()
+        .content(message.content)
+        .embeds(message.embeds)
+        .components(message.components);
+    channel_id.edit_message(http, message_id, edit).await?;
+    Ok(())
+}
+
+/// Channels a record message can be in, most likely first: the configured output,
+/// then the records channel, where replies and records posted before the output was
+/// configured are.
+pub fn record_message_channels(output: Option<ChannelId>, records_channel: Option<ChannelId>) -> Vec<ChannelId> {
+    let mut channels: Vec<ChannelId> = output.into_iter().collect();
+    if let Some(channel_id) = records_channel
+        && !channels.contains(&channel_id)
+    {
+        channels.push(channel_id);
+    }
+    channels
+}
+
+/// Posts a new record message where records go: through the webhook when there is
+/// one, otherwise in `records_channel`.
+pub async fn post_record_message(
+    http: &Http,
+    webhook: Option<&Webhook>,
+    records_channel: ChannelId,
+    message: RecordMessage,
+) -> serenity::Result<Message> {
+    match webhook {
+        Some(webhook) => {
+            let execute = ExecuteWebhook::new()
+                .content(message.content)
+                .embeds(message.embeds)
+                .components(message.components);
+            webhook
+                .execute(http, true, execute)
+                .await?
+                .ok_or(serenity::Error::Other("webhook did not return the posted message"))
+        }
+        None => {
+            let create = CreateMessage::new()
+                .content(message.content)
+                .embeds(message.embeds)
+                .components(message.components);
+            records_channel.send_message(http, create).await
+        }
+    }
+}
+
+/// Edits a record message in the first of `channels` that has it, see
+/// `record_message_channels`.
+pub async fn edit_record_message_in(
+    http: &Http,
+    webhook: Option<&Webhook>,
+    channels: &[ChannelId],
+    message_id: MessageId,
+    message: RecordMessage,
+) -> serenity::Result<()> {
+    let mut result = Err(serenity::Error::Other("no channel to look for the record message in"));
+    for &channel_id in channels {
+        result = edit_record_message(http, webhook, channel_id, message_id, message.clone()).await;
+        match &result {
+            Ok(()) => break,
+            Err(why) => eprintln!("could not edit record message {message_id} in {channel_id}: {why}"),
+        }
+    }
+    result
+}
+
+/// Deletes a record message from the first of `channels` that has it, like
+/// `edit_record_message_in`.
+pub async fn delete_record_message_in(
+    http: &Http,
+    webhook: Option<&Webhook>,
+    channels: &[ChannelId],
+    message_id: MessageId,
+) -> serenity::Result<()> {
+    let mut result = Err(serenity::Error::Other("no channel to look for the record message in"));
+    for &channel_id in channels {
+        result = delete_record_message(http, webhook, channel_id, message_id).await;
+        match &result {
+            Ok(()) => break,
+            Err(why) => eprintln!("could not delete record message {message_id} in {channel_id}: {why}"),
+        }
+    }
+    result
+}
+
+/// Deletes a record message, whoever posted it, the same way `edit_record_message`
+/// edits one.
+pub async fn delete_record_message(
+    http: &Http,
+    webhook: Option<&Webhook>,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> serenity::Result<()> {
+    if let Some(webhook) = webhook
+        && webhook.channel_id == Some(channel_id)
+        && webhook.delete_message(http, None, message_id).await.is_ok()
+    {
+        return Ok(());
+    }
+
+    channel_id.delete_message(http, message_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_wins_over_the_output_channel() {
+        let channel_id = ChannelId::new(10);
+        assert_eq!(select_output(true, Some(channel_id)), OutputTarget::Webhook);
+        assert_eq!(select_output(true, None), OutputTarget::Webhook);
+    }
+
+    #[test]
+    fn output_channel_without_a_webhook() {
+        let channel_id = ChannelId::new(10);
+        assert_eq!(select_output(false, Some(channel_id)), OutputTarget::Channel(channel_id));
+    }
+
+    #[test]
+    fn reply_when_nothing_is_configured() {
+        assert_eq!(select_output(false, None), OutputTarget::Reply);
+    }
+
+    #[test]
+    fn record_messages_are_looked_for_in_the_output_first() {
+        let output = ChannelId::new(10);
+        let records = ChannelId::new(20);
+        assert_eq!(record_message_channels(Some(output), Some(records)), vec![output, records]);
+        assert_eq!(record_message_channels(None, Some(records)), vec![records]);
+        assert_eq!(record_message_channels(Some(records), Some(records)), vec![records]);
+        assert!(record_message_channels(None, None).is_empty());
+    }
+
+    #[test]
+    fn only_the_bots_own_webhooks_are_used() {
+        let application_id = ApplicationId::new(1);
+        assert!(webhook_usable(Some(application_id), application_id));
+        assert!(!webhook_usable(Some(ApplicationId::new(2)), application_id));
+        assert!(!webhook_usable(None, application_id));
+    }
+}
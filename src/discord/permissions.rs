@@ -0,0 +1,75 @@
+use serenity::all::{ChannelId, Context, GuildId, Member, Permissions, UserId};
+
+/// Whether the interacting member has the Administrator permission. Discord only
+/// includes resolved permissions on interaction members, so this is `false` elsewhere.
+pub fn is_admin(member: Option<&Member>) -> bool {
+    member
+        .and_then(|m| m.permissions)
+        .is_some_and(|p| p.administrator())
+}
+
+/// Administrators and members who can manage messages, e.g. to approve records.
+pub fn is_moderator(member: Option<&Member>) -> bool {
+    member
+        .and_then(|m| m.permissions)
+        .is_some_and(|p| p.administrator() || p.manage_messages())
+}
+
+/// What the bot needs in the records channel to read uploads and answer them.
+pub const RECORDS_CHANNEL_PERMISSIONS: Permissions = Permissions::VIEW_CHANNEL
+    .union(Permissions::SEND_MESSAGES)
+    .union(Permissions::EMBED_LINKS)
+    .union(Permissions::READ_MESSAGE_HISTORY);
+
+/// The permissions from `required` that `granted` lacks, empty when everything is there.
+/// Administrators have every permission.
+pub fn missing_permissions(granted: Permissions, required: Permissions) -> Permissions {
+    if granted.administrator() {
+        return Permissions::empty();
+    }
+    required - granted
+}
+
+/// Checks at startup that the bot can post in the records channel. Replies to uploads
+/// would otherwise fail without anyone noticing, so a gap is logged loudly.
+pub async fn check_records_channel(ctx: &Context, guild_id: GuildId, bot_id: UserId, channel_id: ChannelId) {
+    let granted = async {
+        let channel = channel_id.to_channel(&ctx.http).await?.guild();
+        let guild = guild_id.to_partial_guild(&ctx.http).await?;
+        let member = guild_id.member(&ctx.http, bot_id).await?;
+        Ok::<_, serenity::Error>(channel.map(|channel| guild.user_permissions_in(&channel, &member)))
+    };
+
+    match granted.await {
+        Ok(Some(granted)) => {
+            let missing = missing_permissions(granted, RECORDS_CHANNEL_PERMISSIONS);
+            if !missing.is_empty() {
+                eprintln!(
+                    "WARNING: the bot is missing {missing} in records channel {channel_id}, uploads will not get a reply"
+                );
+            }
+        }
+        Ok(None) => eprintln!("WARNING: records channel {channel_id} is not a guild channel"),
+        Err(why) => eprintln!("WARNING: could not check permissions in records channel {channel_id}: {why}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_only_what_is_missing() {
+        let granted = Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY;
+        assert_eq!(
+            missing_permissions(granted, RECORDS_CHANNEL_PERMISSIONS),
+            Permissions::SEND_MESSAGES | Permissions::EMBED_LINKS
+        );
+        assert!(missing_permissions(RECORDS_CHANNEL_PERMISSIONS, RECORDS_CHANNEL_PERMISSIONS).is_empty());
+    }
+
+    #[test]
+    fn administrators_miss_nothing() {
+        assert!(missing_permissions(Permissions::ADMINISTRATOR, RECORDS_CHANNEL_PERMISSIONS).is_empty());
+    }
+}
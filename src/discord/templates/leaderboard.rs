@@ -0,0 +1,234 @@
+use std::{env, time::Duration};
+
+use serenity::all::{
+    Colour, CommandInteraction, Context, CreateEmbed, CreateInteractionResponseFollowup,
+    EditInteractionResponse, Http,
+};
+
+use crate::{
+    discord::{
+        handler::Handler,
+        templates::track::DEFAULT_COLOUR,
+        members::{driver_mention, driver_name},
+    },
+    sheets::records::record::Record,
+    time_format,
+};
+
+// Discord rejects message content over 2000 characters.
+const MAX_MESSAGE_LEN: usize = 2000;
+const MAX_NAME_WIDTH: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeaderboardFormat {
+    Embed,
+    Table,
+}
+
+impl LeaderboardFormat {
+    /// Reads `LEADERBOARD_FORMAT` (`embed` or `table`), defaulting to `embed`.
+    pub fn from_env() -> Self {
+        match env::var("LEADERBOARD_FORMAT")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "table" => LeaderboardFormat::Table,
+            _ => LeaderboardFormat::Embed,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub time: Duration,
+    pub player: String,
+}
+
+/// Numbers the (already ranked) records. Tables get display names since mentions
+/// don't render inside code blocks, embeds get mentions.
+pub async fn leaderboard_entries(
+    http: &Http,
+    handler: &Handler,
+    records: &[Record<'_>],
+    format: LeaderboardFormat,
+) -> Vec<LeaderboardEntry> {
+    let mut entries = Vec::with_capacity(records.len());
+    for (index, record) in records.iter().enumerate() {
+        let player = match format {
+            LeaderboardFormat::Embed => driver_mention(http, handler, record).await,
+            LeaderboardFormat::Table => driver_name(http, handler, record).await,
+        };
+        entries.push(LeaderboardEntry {
+            rank: index + 1,
+            time: record.race_duration,
+            player,
+        });
+    }
+    entries
+}
+
+pub fn leaderboard_embed(title: &str, entries: &[LeaderboardEntry]) -> CreateEmbed {
+    let description = if entries.is_empty() {
+        "No records yet.".to_string()
+    } else {
+        entries
+            .iter()
+            .map(|e| format!("**{}.** {} — {}", e.rank, time_format::format_display(e.time), e.player))
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+
+    CreateEmbed::default()
+        .title(title)
+        .color(Colour::new(DEFAULT_COLOUR))
+        .description(description)
+}
+
+/// Answers a deferred command with the ranked records in the configured format.
+/// Tables that don't fit in one message continue in follow-ups.
+pub async fn send_leaderboard(
+    ctx: &Context,
+    cmd: &CommandInteraction,
+    handler: &Handler,
+    title: &str,
+    records: &[Record<'_>],
+) {
+    let format = LeaderboardFormat::from_env();
+    let entries = leaderboard_entries(&ctx.http, handler, records, format).await;
+
+    match format {
+        LeaderboardFormat::Embed => {
+            let edit = EditInteractionResponse::new().embed(leaderboard_embed(title, &entries));
+            let _ = cmd.edit_response(&ctx.http, edit).await;
+        }
+        LeaderboardFormat::Table => {
+            let mut messages = leaderboard_table(title, &entries).into_iter();
+            if let Some(first) = messages.next() {
+                let _ = cmd
+                    .edit_response(&ctx.http, EditInteractionResponse::new().content(first))
+                    .await;
+            }
+            for message in messages {
+                let _ = cmd
+                    .create_followup(
+                        &ctx.http,
+                        CreateInteractionResponseFollowup::new().content(message),
+                    )
+                    .await;
+            }
+        }
+    }
+}
+
+/// Renders the entries as an aligned monospace table, split over as many code-block
+/// messages as needed to stay under Discord's message length limit.
+pub fn leaderboard_table(title: &str, entries: &[LeaderboardEntry]) -> Vec<String> {
+    let rank_width = entries
+        .iter()
+        .map(|e| e.rank.to_string().len())
+        .max()
+        .unwrap_or(1)
+        .max(1);
+    let time_width = entries
+        .iter()
+        .map(|e| time_format::format(e.time).len())
+        .max()
+        .unwrap_or(4)
+        .max(4);
+
+    let header = format!("{:>rank_width$}  {:<time_width$}  Player", "#", "Time");
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|e| {
+            format!(
+                "{:>rank_width$}  {:<time_width$}  {}",
+                e.rank,
+                time_format::format(e.time),
+                truncate_name(&e.player, MAX_NAME_WIDTH)
+            )
+        })
+        .collect();
+
+    let open = format!("**{title}**\n```\n{header}\n");
+    let close = "```";
+
+    let mut messages = vec![];
+    let mut current = open.clone();
+    for line in lines {
+        if current.len() + line.len() + 1 + close.len() > MAX_MESSAGE_LEN {
+            current.push_str(close);
+            messages.push(current);
+            current = format!("```\n{header}\n");
+        }
+        current.push_str(&line);
+        current.push('\n');
+    }
+    current.push_str(close);
+    messages.push(current);
+
+    messages
+}
+
+/// Cuts names longer than `max_chars` characters, marking the cut with `…`.
+pub fn truncate_name(name: &str, max_chars: usize) -> String {
+    if name.chars().count() <= max_chars {
+        return name.to_string();
+    }
+    let mut truncated: String = name.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(rank: usize, millis: u64, player: &str) -> LeaderboardEntry {
+        LeaderboardEntry { rank, time: Duration::from_millis(millis), player: player.to_string() }
+    }
+
+    #[test]
+    fn columns_are_aligned() {
+        let entries = [entry(9, 83_456, "Mario"), entry(10, 603_001, "Luigi")];
+        let messages = leaderboard_table("Mario Bros. Circuit", &entries);
+        assert_eq!(
+            messages,
+            vec![
+                "**Mario Bros. Circuit**\n```\n #  Time       Player\n 9  1:23.456   Mario\n10  10:03.001  Luigi\n```"
+            ]
+        );
+    }
+
+    #[test]
+    fn long_names_are_truncated() {
+        assert_eq!(truncate_name("Mario", MAX_NAME_WIDTH), "Mario");
+        assert_eq!(truncate_name(&"é".repeat(20), MAX_NAME_WIDTH), "é".repeat(20));
+        assert_eq!(truncate_name(&"é".repeat(21), MAX_NAME_WIDTH), format!("{}…", "é".repeat(19)));
+
+        let messages = leaderboard_table("Track", &[entry(1, 83_456, &"x".repeat(30))]);
+        assert!(messages[0].contains(&format!("{}…\n", "x".repeat(19))));
+    }
+
+    #[test]
+    fn long_tables_are_split_under_the_message_limit() {
+        let entries: Vec<_> = (1..=200).map(|rank| entry(rank, 60_000 + rank as u64, &"x".repeat(25))).collect();
+        let messages = leaderboard_table("Track", &entries);
+
+        assert!(messages.len() > 1);
+        for message in &messages {
+            assert!(message.chars().count() <= MAX_MESSAGE_LEN);
+            assert!(message.ends_with("```"));
+            assert!(message.contains("```\n  #  Time      Player\n"));
+        }
+        let rows = messages.iter().flat_map(|m| m.lines()).filter(|l| l.contains('…')).count();
+        assert_eq!(rows, entries.len());
+    }
+
+    #[test]
+    fn empty_table_keeps_its_header() {
+        assert_eq!(leaderboard_table("Track", &[]), vec!["**Track**\n```\n#  Time  Player\n```"]);
+    }
+}
@@ -1 +1,3 @@
-pub mod record;
\ No newline at end of file
+pub mod leaderboard;
+pub mod record;
+pub mod track;
\ No newline at end of file
@@ -1,54 +1,103 @@
-use std::time::Duration;
+use std::{cmp::Ordering, time::Duration};
 
-use serenity::all::{Colour, CreateActionRow, CreateButton, CreateEmbed};
+use serenity::all::{ButtonStyle, Colour, CreateActionRow, CreateButton, CreateEmbed, Http};
 
-use crate::{discord::handler::Handler, sheets::records::record::Record};
+use crate::{
+    config::env_flag_or,
+    discord::{handler::Handler, members::driver_mention, templates::track::track_style},
+    sheets::records::record::Record,
+    time_format,
+};
+
+/// How a freshly uploaded time compares to the driver's earlier records on the same track.
+#[derive(Debug, Clone, Copy)]
+pub enum PbContext {
+    FirstRecord,
+    Previous { personal_best: Duration },
+}
 
 pub async fn record_embed(
+    http: &Http,
     record: Record<'_>,
     handler: &Handler,
+    pb: Option<PbContext>,
+    first_of_day: bool,
 ) -> (CreateEmbed, Vec<CreateActionRow>) {
-    let mention = format!("<@{}>", record.driver_user_id);
-
-    let icon_url = handler
-        .gsheet
-        .tracks()
-        .get_all()
-        .await
-        .unwrap_or_default()
-        .into_iter()
-        .find(|t| t.name == record.track_name)
-        .map(|t| t.icon_url)
-        .unwrap_or_else(|| {
-            "https://mario.wiki.gallery/images/thumb/4/47/MKWorldFreeroamWarioWaluigi.png/1600px-MKWorldFreeroamWarioWaluigi.png".into()
-        });
-
-    let embed = CreateEmbed::default()
-        .title("NEW RECORD ADDED")
-        .color(Colour::new(0x00b0f4))
-        .field("Track", record.track_name, true)
-        .field("Time", duration_to_string(record.race_duration), true)
+    let mention = driver_mention(http, handler, &record).await;
+
+    let style = track_style(handler, &record.track_name).await;
+
+    let (title, colour) = if record.approved {
+        ("NEW RECORD ADDED", style.colour)
+    } else {
+        ("RECORD PENDING APPROVAL", Colour::new(0xf4a100))
+    };
+
+    let mut embed = CreateEmbed::default()
+        .title(title)
+        .color(colour)
+        .field("Track", &record.track_name, true)
+        .field("Time", time_format::format_display(record.race_duration), true)
         .field("Player", mention, true)
-        .image(icon_url);
+        .image(style.icon_url);
 
-    // let change_track_button = change_track_button();
-    // let change_time_button = change_time_button();
+    if let Some(category) = &record.category {
+        embed = embed.field("Category", category, true);
+    }
+    if let Some(platform) = &record.platform {
+        embed = embed.field("Platform", platform, true);
+    }
+
+    let mut notes = Vec::new();
+    // Set SHOW_PB_DELTA=0 to hide the comparison with the previous personal best.
+    if let Some(pb) = pb
+        && env_flag_or("SHOW_PB_DELTA", true)
+    {
+        notes.push(pb_note(pb, record.race_duration));
+    }
+    if first_of_day {
+        notes.push("First run of the day! 🌅".to_string());
+    }
+    if !notes.is_empty() {
+        embed = embed.description(notes.join("\n"));
+    }
+
+    let change_track_button = change_track_button();
+    let change_time_button = change_time_button();
     let change_driver_button = change_driver_button();
 
-    let components = vec![
-        // CreateActionRow::Buttons(vec![change_track_button]),
-        // CreateActionRow::Buttons(vec![change_time_button]),
+    let mut components = vec![
+        CreateActionRow::Buttons(vec![change_track_button]),
+        CreateActionRow::Buttons(vec![change_time_button]),
         CreateActionRow::Buttons(vec![change_driver_button]),
     ];
+    if !record.approved {
+        components.push(CreateActionRow::Buttons(vec![approve_button()]));
+    }
 
     (embed, components)
 }
 
-pub fn duration_to_string(duration: Duration) -> String {
-    let minutes = duration.as_secs() / 60;
-    let seconds = duration.as_secs() - minutes * 60;
-    let millis = duration.subsec_millis();
-    format!("{minutes}:{seconds:0>2}.{millis:0>3}")
+pub fn pb_note(pb: PbContext, race_duration: Duration) -> String {
+    let personal_best = match pb {
+        PbContext::FirstRecord => return "First recorded time on this track!".to_string(),
+        PbContext::Previous { personal_best } => personal_best,
+    };
+    let delta = time_format::format_delta(time_format::abs_diff_millis(race_duration, personal_best));
+
+    match time_format::cmp_millis(race_duration, personal_best) {
+        Ordering::Less => format!(
+            "Improved by {} (previous best {})",
+            delta,
+            time_format::format(personal_best)
+        ),
+        Ordering::Equal => format!("Matched personal best {}", time_format::format(personal_best)),
+        Ordering::Greater => format!(
+            "{} slower than personal best {}",
+            delta,
+            time_format::format(personal_best)
+        ),
+    }
 }
 
 pub fn change_track_button() -> CreateButton {
@@ -61,4 +110,43 @@ pub fn change_time_button() -> CreateButton {
 
 pub fn change_driver_button() -> CreateButton {
     CreateButton::new("record_change_driver").label("Change driver")
-}
\ No newline at end of file
+}
+
+pub fn approve_button() -> CreateButton {
+    CreateButton::new("record_approve")
+        .label("Approve")
+        .emoji('✅')
+        .style(ButtonStyle::Success)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn previous(millis: u64) -> PbContext {
+        PbContext::Previous { personal_best: Duration::from_millis(millis) }
+    }
+
+    #[test]
+    fn faster_time_is_an_improvement() {
+        let note = pb_note(previous(83_456), Duration::from_millis(82_210));
+        assert_eq!(note, "Improved by 1.246 (previous best 1:23.456)");
+    }
+
+    #[test]
+    fn slower_time_is_a_regression() {
+        let note = pb_note(previous(83_456), Duration::from_millis(84_000));
+        assert_eq!(note, "0.544 slower than personal best 1:23.456");
+    }
+
+    #[test]
+    fn equal_time_matches_the_best() {
+        let note = pb_note(previous(83_456), Duration::from_micros(83_456_700));
+        assert_eq!(note, "Matched personal best 1:23.456");
+    }
+
+    #[test]
+    fn first_record_has_no_previous_best() {
+        let note = pb_note(PbContext::FirstRecord, Duration::from_millis(83_456));
+        assert_eq!(note, "First recorded time on this track!");
+    }
+}
@@ -0,0 +1,84 @@
+use reqwest::Url;
+use serenity::all::{Colour, CreateEmbed};
+
+use crate::discord::handler::Handler;
+
+pub const FALLBACK_ICON_URL: &str = "https://mario.wiki.gallery/images/thumb/4/47/MKWorldFreeroamWarioWaluigi.png/1600px-MKWorldFreeroamWarioWaluigi.png";
+
+pub const DEFAULT_COLOUR: u32 = 0x00b0f4;
+
+pub fn track_embed(track_name: &str, icon_url: Option<&str>, color: Option<u32>) -> CreateEmbed {
+    CreateEmbed::default()
+        .title(track_name)
+        .color(track_colour(color))
+        .image(resolve_icon_url(icon_url))
+}
+
+/// How a track's embeds look.
+pub struct TrackStyle {
+    pub icon_url: String,
+    pub colour: Colour,
+}
+
+/// Looks up the icon and colour of a track, falling back to the defaults when the
+/// track is unknown, the sheet can't be read, or the stored values are unusable.
+pub async fn track_style(handler: &Handler, track_name: &str) -> TrackStyle {
+    let tracks = handler.gsheet.tracks();
+    let track = tracks.get_by_name(track_name).await.ok().flatten();
+
+    TrackStyle {
+        icon_url: resolve_icon_url(track.as_ref().map(|t| t.icon_url.as_str())),
+        colour: track_colour(track.and_then(|t| t.color)),
+    }
+}
+
+pub fn track_colour(color: Option<u32>) -> Colour {
+    Colour::new(color.unwrap_or(DEFAULT_COLOUR))
+}
+
+pub fn resolve_icon_url(icon_url: Option<&str>) -> String {
+    match icon_url {
+        Some(url) if is_valid_icon_url(url) => url.trim().to_string(),
+        _ => FALLBACK_ICON_URL.to_string(),
+    }
+}
+
+/// Discord only renders embed images from absolute http(s) URLs.
+pub fn is_valid_icon_url(url: &str) -> bool {
+    Url::parse(url.trim())
+        .map(|u| matches!(u.scheme(), "http" | "https") && u.host_str().is_some())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheets::tracks::parse_hex_color;
+
+    #[test]
+    fn unusable_icons_fall_back() {
+        for icon_url in [None, Some(""), Some("not a url"), Some("/icons/mbc.png"), Some("ftp://example.com/mbc.png")] {
+            assert_eq!(resolve_icon_url(icon_url), FALLBACK_ICON_URL, "{icon_url:?}");
+        }
+    }
+
+    #[test]
+    fn valid_icons_are_kept() {
+        assert_eq!(resolve_icon_url(Some(" https://example.com/mbc.png ")), "https://example.com/mbc.png");
+        assert!(is_valid_icon_url("http://example.com/mbc.png"));
+    }
+
+    #[test]
+    fn embed_uses_the_fallback_icon_and_default_colour() {
+        let embed = serde_json::to_value(track_embed("Mario Bros. Circuit", Some("mbc.png"), None)).unwrap();
+        assert_eq!(embed["title"], "Mario Bros. Circuit");
+        assert_eq!(embed["image"]["url"], FALLBACK_ICON_URL);
+        assert_eq!(embed["color"], DEFAULT_COLOUR);
+    }
+
+    #[test]
+    fn unreadable_colours_use_the_default() {
+        assert_eq!(track_colour(parse_hex_color("orange")), Colour::new(DEFAULT_COLOUR));
+        assert_eq!(track_colour(parse_hex_color("#FF8800")), Colour::new(0xFF8800));
+    }
+}
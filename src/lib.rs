@@ -1,3 +1,6 @@
 pub mod sheets;
 pub mod discord;
-pub mod ocr;
\ No newline at end of file
+pub mod ocr;
+pub mod config;
+pub mod seasons;
+pub mod time_format;
\ No newline at end of file
@@ -0,0 +1,109 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::env_parse;
+
+/// Caps how many screenshots go through OCR at once, with a bounded number waiting
+/// behind them. Uploads beyond that are turned away instead of piling up.
+pub struct OcrLimiter {
+    running: Arc<Semaphore>,
+    admitted: Arc<AtomicUsize>,
+    capacity: usize,
+}
+
+/// A place in the queue, released when dropped.
+pub struct OcrTicket {
+    running: Arc<Semaphore>,
+    admitted: Arc<AtomicUsize>,
+}
+
+impl OcrLimiter {
+    pub fn new(concurrency: usize, queue_limit: usize) -> Self {
+        let concurrency = concurrency.max(1);
+        OcrLimiter {
+            running: Arc::new(Semaphore::new(concurrency)),
+            admitted: Arc::new(AtomicUsize::new(0)),
+            capacity: concurrency + queue_limit,
+        }
+    }
+
+    /// `OCR_CONCURRENCY` (default 2) running at once, `OCR_QUEUE_LIMIT` (default 8) waiting.
+    pub fn from_env() -> Self {
+        Self::new(env_parse("OCR_CONCURRENCY", 2), env_parse("OCR_QUEUE_LIMIT", 8))
+    }
+
+    /// A ticket when there is room, `None` when the queue is full.
+    pub fn try_admit(&self) -> Option<OcrTicket> {
+        self.admitted
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |admitted| {
+                admits(admitted, self.capacity).then_some(admitted + 1)
+            })
+            .ok()?;
+
+        Some(OcrTicket {
+            running: self.running.clone(),
+            admitted: self.admitted.clone(),
+        })
+    }
+}
+
+impl OcrTicket {
+    /// Waits for a free OCR slot. The permit has to be held while processing.
+    pub async fn acquire(&self) -> OwnedSemaphorePermit {
+        self.running
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the OCR semaphore is never closed")
+    }
+}
+
+impl Drop for OcrTicket {
+    fn drop(&mut self) {
+        self.admitted.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Whether another upload fits next to the `admitted` ones running or waiting.
+pub fn admits(admitted: usize, capacity: usize) -> bool {
+    admitted < capacity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_queue_turns_uploads_away() {
+        let limiter = OcrLimiter::new(1, 1);
+        let running = limiter.try_admit();
+        let waiting = limiter.try_admit();
+
+        assert!(running.is_some() && waiting.is_some());
+        assert!(limiter.try_admit().is_none());
+    }
+
+    #[test]
+    fn dropped_tickets_free_their_place() {
+        let limiter = OcrLimiter::new(1, 0);
+        let ticket = limiter.try_admit();
+        assert!(limiter.try_admit().is_none());
+
+        drop(ticket);
+        assert!(limiter.try_admit().is_some());
+    }
+
+    #[tokio::test]
+    async fn only_the_concurrency_runs_at_once() {
+        let limiter = OcrLimiter::new(1, 1);
+        let first = limiter.try_admit().unwrap();
+        let second = limiter.try_admit().unwrap();
+
+        let _permit = first.acquire().await;
+        assert_eq!(second.running.available_permits(), 0);
+    }
+}
@@ -4,15 +4,28 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::{env, time::Duration};
+use futures::future::select_ok;
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+use std::{env, io::Cursor, num::NonZeroUsize, sync::Mutex, time::Duration};
 use thiserror::Error;
 
+use crate::{
+    config::{env_flag, env_parse},
+    time_format::{self, TimeFormatError},
+};
+use stats::{Outcome, ProviderStats};
+
 use image::codecs::jpeg::JpegEncoder;
 use image::codecs::png::{CompressionType as PngCompression, FilterType as PngFilter, PngEncoder};
 use image::{
-    DynamicImage, ExtendedColorType, GenericImageView, imageops::FilterType as ResizeFilter,
+    DynamicImage, ExtendedColorType, GenericImageView, ImageReader,
+    imageops::FilterType as ResizeFilter,
 };
 
+pub mod limiter;
+pub mod stats;
+
 pub type Result<T> = std::result::Result<T, ExtractError>;
 
 #[derive(Error, Debug)]
@@ -20,29 +33,22 @@ pub enum ExtractError {
     #[error("http: {0}")]
     Http(#[from] reqwest::Error),
 
-    #[error("provider {0} non-OK status: {1}")]
-    ProviderStatus(&'static str, StatusCode),
+    /// Carries the provider's error message from the response body, when it sent one.
+    #[error("provider {0} non-OK status: {1} {2}")]
+    ProviderStatus(&'static str, StatusCode, String),
 
     #[error("provider {0} decode: {1}")]
     ProviderDecode(&'static str, String),
 
+    /// Carries the provider's `Retry-After` delay when it sent one.
     #[error("rate limited by provider {0}")]
-    RateLimited(&'static str),
+    RateLimited(&'static str, Option<Duration>),
 
     #[error("no yellow time found")]
     YellowMissing,
 
-    #[error("invalid time format: {0}")]
-    InvalidFormat(String),
-
-    #[error("minutes parse: {0}")]
-    MinutesParse(String),
-
-    #[error("seconds parse: {0}")]
-    SecondsParse(String),
-
-    #[error("milliseconds parse: {0}")]
-    MillisParse(String),
+    #[error(transparent)]
+    TimeFormat(#[from] TimeFormatError),
 
     #[error("no providers configured or available")]
     NoProviders,
@@ -56,6 +62,19 @@ pub enum ExtractError {
 
     #[error("image size still too large after downscaling")]
     ImageTooLarge,
+
+    #[error("image of {0}x{1} pixels exceeds the configured megapixel limit")]
+    ImageDimensionsTooLarge(u32, u32),
+
+    #[error("image does not look like a result screen")]
+    NotResultScreen,
+
+    /// The bytes are an image, but in a format that can't be decoded, e.g. AVIF.
+    #[error("unsupported image format: {0}")]
+    UnsupportedImageFormat(String),
+
+    #[error("implausible time read: {}", time_format::format(*.0))]
+    ImplausibleTime(Duration),
 }
 
 #[derive(Serialize)]
@@ -70,6 +89,22 @@ struct OAChatRequest<'a> {
     top_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     stop: Option<Vec<&'a str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+}
+
+#[derive(Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+/// The answer asked for with `OCR_RESPONSE_FORMAT=json`.
+#[derive(Deserialize)]
+struct TimeAnswer {
+    #[serde(default)]
+    time: Option<String>,
+    found: bool,
 }
 
 #[derive(Serialize)]
@@ -100,6 +135,16 @@ struct OAChatResponse {
     choices: Vec<OAChoice>,
 }
 
+impl OAChatResponse {
+    /// The first choice's answer, the same text every provider hands to `parse_answer`.
+    fn text(&self) -> String {
+        self.choices
+            .first()
+            .map(|c| c.message.content.trim().to_string())
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Deserialize)]
 struct OAChoice {
     message: OAMessageResp,
@@ -110,93 +155,587 @@ struct OAMessageResp {
     content: String,
 }
 
+#[derive(Serialize)]
+struct AnthropicRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    system: &'a str,
+    messages: Vec<AnthropicMessage<'a>>,
+    temperature: f32,
+}
+
+#[derive(Serialize)]
+struct AnthropicMessage<'a> {
+    role: &'a str,
+    content: Vec<AnthropicContent<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicContent<'a> {
+    Image { source: AnthropicImageSource<'a> },
+    Text { text: &'a str },
+}
+
+#[derive(Serialize)]
+struct AnthropicImageSource<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    media_type: &'a str,
+    data: &'a str,
+}
+
+#[derive(Deserialize)]
+struct AnthropicResponse {
+    content: Vec<AnthropicResponseBlock>,
+}
+
+impl AnthropicResponse {
+    fn text(&self) -> String {
+        self.content
+            .first()
+            .map(|block| block.text.trim().to_string())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize)]
+struct AnthropicResponseBlock {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Serialize)]
+struct GeminiRequest<'a> {
+    system_instruction: GeminiContent<'a>,
+    contents: Vec<GeminiContent<'a>>,
+    #[serde(rename = "generationConfig")]
+    generation_config: GeminiGenerationConfig,
+}
+
+#[derive(Serialize)]
+struct GeminiContent<'a> {
+    parts: Vec<GeminiPart<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum GeminiPart<'a> {
+    Text { text: &'a str },
+    InlineData { inline_data: GeminiInlineData<'a> },
+}
+
+#[derive(Serialize)]
+struct GeminiInlineData<'a> {
+    mime_type: &'a str,
+    data: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiGenerationConfig {
+    max_output_tokens: u32,
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<&'static str>,
+}
+
+#[derive(Deserialize)]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<GeminiCandidate>,
+}
+
+impl GeminiResponse {
+    fn text(&self) -> String {
+        self.candidates
+            .first()
+            .and_then(|c| c.content.parts.first())
+            .map(|part| part.text.trim().to_string())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize)]
+struct GeminiCandidate {
+    content: GeminiResponseContent,
+}
+
+#[derive(Deserialize)]
+struct GeminiResponseContent {
+    #[serde(default)]
+    parts: Vec<GeminiResponsePart>,
+}
+
+#[derive(Deserialize)]
+struct GeminiResponsePart {
+    #[serde(default)]
+    text: String,
+}
+
 static TIME_STRICT_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^(\d):([0-5]\d)\.(\d{3})$").unwrap());
 
+static TIME_SECONDS_ONLY_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\d{1,2})\.(\d{3})$").unwrap());
+
+static TIME_FRACTION_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(\d:[0-5]\d|\d{1,2})\.(\d+)\b").unwrap());
+
 static TIME_FINDER_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(?m)\b(\d):([0-5]\d)\.(\d{3})\b").unwrap());
 
+/// "1m 23.456s", "1 min 23 sec 456 ms", "1 minute 23.456 seconds", ...
+static VERBAL_TIME_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?:(\d)\s*(?:minutes?|mins?|m)\s*)?(\d{1,2})(?:\.(\d{1,3}))?\s*(?:seconds?|secs?|s)\b(?:\s*(?:and\s*)?(\d{1,3})\s*(?:milliseconds?|ms)\b)?",
+    )
+    .unwrap()
+});
+
+const DEFAULT_MODEL: &str = "llama-4-vision";
+
+const EXTRACT_SYSTEM_PROMPT: &str =
+    "You are a precise OCR assistant. Extract the yellow timer in m:ss.mmm.";
+
+const DESCRIBE_SYSTEM_PROMPT: &str = "You are a helpful vision assistant.";
+
+const DESCRIBE_USER_PROMPT: &str = "Describe this screenshot in a few sentences. \
+Mention what kind of screen it is, which times or numbers are visible and their colours.";
+
+/// The extraction instructions from the file at `OCR_PROMPT_PATH`, read once. Falls
+/// back to the embedded prompt for the configured response format when unset or unreadable.
+static PROMPT_OVERRIDE: Lazy<Option<String>> = Lazy::new(|| {
+    let path = env::var("OCR_PROMPT_PATH").ok()?;
+    match std::fs::read_to_string(&path) {
+        Ok(prompt) => Some(prompt),
+        Err(why) => {
+            eprintln!("could not read OCR_PROMPT_PATH {path}, using the default prompt: {why}");
+            None
+        }
+    }
+});
+
+/// The extraction system message from `OCR_SYSTEM_PROMPT`.
+static SYSTEM_PROMPT_OVERRIDE: Lazy<Option<String>> =
+    Lazy::new(|| env::var("OCR_SYSTEM_PROMPT").ok().filter(|p| !p.trim().is_empty()));
+
+fn extract_system_prompt() -> &'static str {
+    SYSTEM_PROMPT_OVERRIDE
+        .as_deref()
+        .unwrap_or(EXTRACT_SYSTEM_PROMPT)
+}
+
+fn extract_user_prompt(default: &'static str) -> &'static str {
+    PROMPT_OVERRIDE.as_deref().unwrap_or(default)
+}
+
+/// Instructions and output limits for a single vision request.
+struct Prompt<'a> {
+    system: &'a str,
+    user: &'a str,
+    max_tokens: u32,
+    single_line: bool,
+    json: bool,
+}
+
+/// A time read from a screenshot, with the answer it was parsed from.
+#[derive(Debug, Clone)]
+pub struct ExtractOutcome {
+    pub duration: Duration,
+    pub raw_text: String,
+    pub provider: &'static str,
+}
+
 pub async fn extract_time(image_bytes: &[u8]) -> Result<Duration> {
-    extract_time_with_model("llama-4-vision", image_bytes).await
+    extract_time_detailed(image_bytes)
+        .await
+        .map(|outcome| outcome.duration)
 }
 
-/// Main entry with provider failover (OpenRouter -> Groq by default),
+/// Like `extract_time`, but keeps the model's raw answer and which provider gave it.
+/// Answers that can't be read as a time are logged with the provider, since the
+/// error alone doesn't show what the model said.
+pub async fn extract_time_detailed(image_bytes: &[u8]) -> Result<ExtractOutcome> {
+    extract_time_with_model(DEFAULT_MODEL, image_bytes).await
+}
+
+/// Main entry with provider failover (OpenRouter -> Groq by default, Ollama on request),
 /// now with image downscaling & JPEG recompression to respect provider limits.
-pub async fn extract_time_with_model(model: &str, image_bytes: &[u8]) -> Result<Duration> {
+pub async fn extract_time_with_model(model: &str, image_bytes: &[u8]) -> Result<ExtractOutcome> {
+    // Only successes are cached, a failed read is worth another try.
+    let cache_key = (model.to_string(), content_digest(image_bytes));
+    if let Some(outcome) = cached_outcome(&cache_key) {
+        return Ok(outcome);
+    }
+
+    // Opt-in cheap check so menus and random photos don't cost a provider call.
+    if env_flag("OCR_PRECHECK") && !looks_like_result_screen(image_bytes)? {
+        return Err(ExtractError::NotResultScreen);
+    }
+
+    let json = json_response_format();
+    let prompt = if json {
+        Prompt {
+            system: extract_system_prompt(),
+            user: extract_user_prompt(include_str!("prompt_json.txt")),
+            max_tokens: 48,
+            single_line: false,
+            json: true,
+        }
+    } else {
+        Prompt {
+            system: extract_system_prompt(),
+            user: extract_user_prompt(include_str!("prompt.txt")),
+            max_tokens: 16,
+            single_line: true,
+            json: false,
+        }
+    };
+
+    let check = move |text: &str| parse_answer(text, json).map(|_| ());
+    let (raw_text, provider) = run_providers(model, image_bytes, &prompt, &check).await?;
+    let parsed = parse_answer(&raw_text, json);
+
+    match parsed {
+        Ok(duration) => {
+            let outcome = ExtractOutcome { duration, raw_text, provider };
+            cache_outcome(cache_key, &outcome);
+            Ok(outcome)
+        }
+        Err(why) => {
+            eprintln!("{provider} answered {raw_text:?}, which could not be read as a time: {why}");
+            Err(why)
+        }
+    }
+}
+
+/// Debug helper: ask the configured providers for a free-form description of the image,
+/// without any time parsing.
+pub async fn describe_image(image_bytes: &[u8]) -> Result<String> {
+    let prompt = Prompt {
+        system: DESCRIBE_SYSTEM_PROMPT,
+        user: DESCRIBE_USER_PROMPT,
+        max_tokens: 512,
+        single_line: false,
+        json: false,
+    };
+
+    run_providers(DEFAULT_MODEL, image_bytes, &prompt, &|_| Ok(()))
+        .await
+        .map(|(text, _)| text)
+}
+
+/// Send the image to each configured provider in order and return the first raw answer,
+/// along with the name of the provider that gave it.
+/// Rate limits and server errors are retried on the same provider up to `OCR_MAX_RETRIES`
+/// times first. Retryable failures then fall through to the next provider, anything
+/// else stops the loop.
+async fn run_providers(
+    model: &str,
+    image_bytes: &[u8],
+    prompt: &Prompt<'_>,
+    check: &AnswerCheck,
+) -> Result<(String, &'static str)> {
     let providers = read_provider_order();
     if providers.is_empty() {
         return Err(ExtractError::NoProviders);
     }
 
-    // Downscale + recompress and wrap as data URL.
-    let image_data_url = prepare_image_data_url(image_bytes)?;
+    // Downscale + recompress once, every provider gets the same image.
+    let image = prepare_image_base64(image_bytes)?;
 
-    let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+    // Slow self-hosted models need longer, a stuck hosted one should fail over sooner.
+    let client = Client::builder()
+        .timeout(Duration::from_secs(env_parse("OCR_HTTP_TIMEOUT_SECS", 30)))
+        .connect_timeout(Duration::from_secs(env_parse("OCR_CONNECT_TIMEOUT_SECS", 10)))
+        .build()?;
 
-    let user_text = include_str!("prompt.txt");
+    if read_provider_strategy() == ProviderStrategy::Race {
+        return race_providers(&providers, &client, model, &image, prompt, check).await;
+    }
 
     let mut last_err: Option<ExtractError> = None;
     for p in providers {
-        match p {
-            Provider::OpenRouter => {
-                match call_openrouter(&client, model, &image_data_url, user_text).await {
-                    Ok(text) => return post_process_to_duration(&text),
-                    Err(e) => {
-                        let retryable = matches!(
-                            e,
-                            ExtractError::RateLimited(_)
-                                | ExtractError::Http(_)
-                                | ExtractError::ProviderStatus(_, StatusCode::TOO_MANY_REQUESTS)
-                                | ExtractError::ProviderStatus(_, StatusCode::BAD_GATEWAY)
-                                | ExtractError::ProviderStatus(_, StatusCode::SERVICE_UNAVAILABLE)
-                                | ExtractError::ProviderStatus(_, StatusCode::GATEWAY_TIMEOUT)
-                                | ExtractError::ProviderStatus(
-                                    _,
-                                    StatusCode::INTERNAL_SERVER_ERROR
-                                )
-                        );
-                        last_err = Some(e);
-                        if retryable {
-                            continue;
-                        } else {
-                            break;
-                        }
-                    }
+        let result = call_with_retries(p, &client, model, &image, prompt).await;
+
+        match result {
+            Ok(text) => return Ok((text, p.name())),
+            Err(e) => {
+                let retryable = is_retryable(&e);
+                last_err = Some(e);
+                if !retryable {
+                    break;
                 }
             }
-            Provider::Groq => match call_groq(&client, model, &image_data_url, user_text).await {
-                Ok(text) => return post_process_to_duration(&text),
-                Err(e) => {
-                    let retryable = matches!(
-                        e,
-                        ExtractError::RateLimited(_)
-                            | ExtractError::Http(_)
-                            | ExtractError::ProviderStatus(_, StatusCode::TOO_MANY_REQUESTS)
-                            | ExtractError::ProviderStatus(_, StatusCode::BAD_GATEWAY)
-                            | ExtractError::ProviderStatus(_, StatusCode::SERVICE_UNAVAILABLE)
-                            | ExtractError::ProviderStatus(_, StatusCode::GATEWAY_TIMEOUT)
-                            | ExtractError::ProviderStatus(_, StatusCode::INTERNAL_SERVER_ERROR)
-                    );
-                    last_err = Some(e);
-                    if retryable {
-                        continue;
-                    } else {
-                        break;
-                    }
-                }
-            },
         }
     }
 
     Err(last_err.unwrap_or(ExtractError::NoProviders))
 }
 
+/// Whether a raw answer is usable, only consulted by `PROVIDER_STRATEGY=race` where a
+/// fast but unreadable answer mustn't beat a slower good one.
+type AnswerCheck = dyn Fn(&str) -> Result<()> + Sync;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum ProviderStrategy {
+    Failover,
+    Race,
+}
+
+/// `PROVIDER_STRATEGY=race` asks every provider at once, anything else fails over in order.
+fn read_provider_strategy() -> ProviderStrategy {
+    match env::var("PROVIDER_STRATEGY")
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "race" => ProviderStrategy::Race,
+        _ => ProviderStrategy::Failover,
+    }
+}
+
+/// Sends the request to every provider concurrently and keeps the first usable answer.
+/// The other requests are dropped, which aborts them. When all fail, the error of
+/// the last one to fail is returned.
+async fn race_providers(
+    providers: &[Provider],
+    client: &Client,
+    model: &str,
+    image: &EncodedImage,
+    prompt: &Prompt<'_>,
+    check: &AnswerCheck,
+) -> Result<(String, &'static str)> {
+    let requests = providers.iter().map(|&p| {
+        Box::pin(async move {
+            let text = call_with_retries(p, client, model, image, prompt).await?;
+            check(&text)?;
+            Ok::<_, ExtractError>((text, p.name()))
+        })
+    });
+
+    select_ok(requests).await.map(|(answer, _)| answer)
+}
+
+/// Calls the provider, retrying rate limits and server errors up to `OCR_MAX_RETRIES` times.
+async fn call_with_retries(
+    p: Provider,
+    client: &Client,
+    model: &str,
+    image: &EncodedImage,
+    prompt: &Prompt<'_>,
+) -> Result<String> {
+    let max_retries = env_parse("OCR_MAX_RETRIES", 2u32);
+    let base_delay = Duration::from_millis(env_parse("OCR_RETRY_BASE_MS", 500u64));
+    let max_wait = max_retry_wait();
+
+    let mut attempt = 0;
+    loop {
+        let result = call_provider(p, client, model, image, prompt).await;
+        let delay = match &result {
+            Err(e) if attempt < max_retries && should_retry_provider(e) => {
+                retry_delay(e, base_delay, attempt, max_wait)
+            }
+            _ => None,
+        };
+        match (&result, delay) {
+            (Err(e), Some(delay)) => {
+                eprintln!(
+                    "{e}, retrying in {}ms (retry {}/{max_retries})",
+                    delay.as_millis(),
+                    attempt + 1
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            _ => {
+                record_outcome(p, &result);
+                return result;
+            }
+        }
+    }
+}
+
+/// Longest `Retry-After` worth waiting for, `OCR_MAX_RETRY_WAIT_SECS` and by default
+/// the request timeout. The wait holds an `OcrLimiter` slot, so a provider asking for
+/// longer is given up on instead.
+fn max_retry_wait() -> Duration {
+    let timeout = env_parse("OCR_HTTP_TIMEOUT_SECS", 30u64);
+    Duration::from_secs(env_parse("OCR_MAX_RETRY_WAIT_SECS", timeout))
+}
+
+/// Call counts per provider since startup, see `/provider_stats`.
+pub static PROVIDER_STATS: Lazy<ProviderStats> =
+    Lazy::new(|| ProviderStats::new(&Provider::ALL.map(Provider::name)));
+
+/// Counts how the provider call ended and logs the provider's counts when it failed.
+fn record_outcome(p: Provider, result: &Result<String>) {
+    let outcome = match result {
+        Ok(_) => Outcome::Success,
+        Err(e) if is_retryable(e) => Outcome::RetryableFailure,
+        Err(_) => Outcome::FatalFailure,
+    };
+    PROVIDER_STATS.record(p.name(), outcome);
+
+    if outcome != Outcome::Success
+        && let Some(counts) = PROVIDER_STATS.get(p.name())
+    {
+        eprintln!("provider {}: {counts}", p.name());
+    }
+}
+
+async fn call_provider(
+    provider: Provider,
+    client: &Client,
+    model: &str,
+    image: &EncodedImage,
+    prompt: &Prompt<'_>,
+) -> Result<String> {
+    match provider {
+        Provider::OpenRouter => call_openrouter(client, model, &image.data_url(), prompt).await,
+        Provider::Groq => call_groq(client, model, &image.data_url(), prompt).await,
+        Provider::Ollama => call_ollama(client, &image.data_url(), prompt).await,
+        Provider::Anthropic => call_anthropic(client, image, prompt).await,
+        Provider::Gemini => call_gemini(client, image, prompt).await,
+    }
+}
+
+/// Rate limits and server errors are usually brief, so the same provider gets
+/// another go before the loop moves on to the next one.
+fn should_retry_provider(e: &ExtractError) -> bool {
+    match e {
+        ExtractError::RateLimited(..) => true,
+        ExtractError::ProviderStatus(_, status, _) => status.is_server_error(),
+        _ => false,
+    }
+}
+
+/// `base * 2^attempt` plus up to `base` of jitter, or the provider's `Retry-After`.
+/// `None` when the `Retry-After` is longer than `max_wait`, the call is not retried.
+fn retry_delay(e: &ExtractError, base: Duration, attempt: u32, max_wait: Duration) -> Option<Duration> {
+    if let ExtractError::RateLimited(_, Some(retry_after)) = e {
+        if *retry_after > max_wait {
+            eprintln!(
+                "{e}, not waiting the {}s it asked for (at most {}s)",
+                retry_after.as_secs(),
+                max_wait.as_secs()
+            );
+            return None;
+        }
+        return Some(*retry_after);
+    }
+
+    let backoff = base.saturating_mul(2u32.saturating_pow(attempt));
+    Some(backoff + jitter(base))
+}
+
+/// No RNG dependency is needed for this, the clock's sub-second part spreads
+/// concurrent retries well enough.
+fn jitter(max: Duration) -> Duration {
+    let max_nanos = max.as_nanos() as u64;
+    if max_nanos == 0 {
+        return Duration::ZERO;
+    }
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or_default();
+    Duration::from_nanos(nanos % max_nanos)
+}
+
+/// Longest provider error message kept in `ProviderStatus`.
+const MAX_ERROR_MESSAGE_LEN: usize = 300;
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+/// OpenAI compatible APIs, Anthropic and Gemini all nest the message like this.
+#[derive(Deserialize)]
+struct ErrorDetail {
+    message: String,
+}
+
+/// Turns a non-success response into `ProviderStatus`, keeping the explanation from
+/// the body (an invalid model, missing credits, ...) and logging it.
+async fn status_error(provider: &'static str, resp: reqwest::Response) -> ExtractError {
+    let status = resp.status();
+    let body = resp.text().await.unwrap_or_default();
+    let message = error_message(&body);
+    eprintln!("{provider} answered {status}: {message}");
+    ExtractError::ProviderStatus(provider, status, message)
+}
+
+/// The `error.message` of a JSON error body, or the raw body, truncated.
+fn error_message(body: &str) -> String {
+    let message = match serde_json::from_str::<ErrorBody>(body) {
+        Ok(parsed) => parsed.error.message,
+        Err(_) => body.trim().to_string(),
+    };
+    match message.char_indices().nth(MAX_ERROR_MESSAGE_LEN) {
+        Some((cut, _)) => format!("{}…", &message[..cut]),
+        None => message,
+    }
+}
+
+/// Reads a `Retry-After` header given in seconds. The HTTP date form is rare for
+/// these APIs and falls back to the regular backoff.
+fn retry_after(resp: &reqwest::Response) -> Option<Duration> {
+    resp.headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+fn is_retryable(e: &ExtractError) -> bool {
+    matches!(
+        e,
+        ExtractError::RateLimited(..)
+            | ExtractError::Http(_)
+            | ExtractError::ProviderStatus(_, StatusCode::TOO_MANY_REQUESTS, _)
+            | ExtractError::ProviderStatus(_, StatusCode::BAD_GATEWAY, _)
+            | ExtractError::ProviderStatus(_, StatusCode::SERVICE_UNAVAILABLE, _)
+            | ExtractError::ProviderStatus(_, StatusCode::GATEWAY_TIMEOUT, _)
+            | ExtractError::ProviderStatus(_, StatusCode::INTERNAL_SERVER_ERROR, _)
+    )
+}
+
 /* ---------- Provider plumbing ---------- */
 
 #[derive(Copy, Clone)]
 enum Provider {
     OpenRouter,
     Groq,
+    Ollama,
+    Anthropic,
+    Gemini,
+}
+
+impl Provider {
+    const ALL: [Provider; 5] = [
+        Provider::OpenRouter,
+        Provider::Groq,
+        Provider::Ollama,
+        Provider::Anthropic,
+        Provider::Gemini,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Provider::OpenRouter => "openrouter",
+            Provider::Groq => "groq",
+            Provider::Ollama => "ollama",
+            Provider::Anthropic => "anthropic",
+            Provider::Gemini => "gemini",
+        }
+    }
 }
 
 fn read_provider_order() -> Vec<Provider> {
@@ -208,35 +747,43 @@ fn read_provider_order() -> Vec<Provider> {
         .filter_map(|s| match s.as_str() {
             "openrouter" => Some(Provider::OpenRouter),
             "groq" => Some(Provider::Groq),
+            "ollama" => Some(Provider::Ollama),
+            "anthropic" => Some(Provider::Anthropic),
+            "gemini" => Some(Provider::Gemini),
             _ => None,
         })
         .collect()
 }
 
-fn build_payload<'a>(model: &'a str, data_url: &'a str, user_text: &'a str) -> OAChatRequest<'a> {
+fn build_payload<'a>(
+    model: &'a str,
+    data_url: &'a str,
+    prompt: &'a Prompt<'a>,
+) -> OAChatRequest<'a> {
     OAChatRequest {
         model,
         messages: vec![
             OAMessage {
                 role: "system",
                 content: vec![OAContent::Text {
-                    text: "You are a precise OCR assistant. Extract the yellow timer in m:ss.mmm.",
+                    text: prompt.system,
                 }],
             },
             OAMessage {
                 role: "user",
                 content: vec![
-                    OAContent::Text { text: user_text },
+                    OAContent::Text { text: prompt.user },
                     OAContent::ImageUrl {
                         image_url: ImageUrl { url: data_url },
                     },
                 ],
             },
         ],
-        max_tokens: Some(16),
+        max_tokens: Some(prompt.max_tokens),
         temperature: Some(0.0),
         top_p: Some(0.1),
-        stop: Some(vec!["\n"]),
+        stop: prompt.single_line.then(|| vec!["\n"]),
+        response_format: prompt.json.then_some(ResponseFormat { kind: "json_object" }),
     }
 }
 
@@ -246,7 +793,7 @@ async fn call_openrouter(
     client: &Client,
     model_arg_fallback: &str,
     image_data_url: &str,
-    user_text: &str,
+    prompt: &Prompt<'_>,
 ) -> Result<String> {
     let base = env::var("OPENROUTER_BASE_URL")
         .unwrap_or_else(|_| "https://openrouter.ai/api/v1".to_string());
@@ -256,7 +803,7 @@ async fn call_openrouter(
     let model = env::var("OPENROUTER_MODEL").unwrap_or_else(|_| model_arg_fallback.to_string());
 
     let url = format!("{}/chat/completions", base);
-    let payload = build_payload(&model, image_data_url, user_text);
+    let payload = build_payload(&model, image_data_url, prompt);
 
     let mut req = client.post(&url).bearer_auth(api_key).json(&payload);
 
@@ -270,10 +817,10 @@ async fn call_openrouter(
     let resp = req.send().await?;
 
     if resp.status() == StatusCode::TOO_MANY_REQUESTS {
-        return Err(ExtractError::RateLimited("openrouter"));
+        return Err(ExtractError::RateLimited("openrouter", retry_after(&resp)));
     }
     if !resp.status().is_success() {
-        return Err(ExtractError::ProviderStatus("openrouter", resp.status()));
+        return Err(status_error("openrouter", resp).await);
     }
 
     let parsed: OAChatResponse = resp
@@ -281,11 +828,7 @@ async fn call_openrouter(
         .await
         .map_err(|e| ExtractError::ProviderDecode("openrouter", e.to_string()))?;
 
-    let text = parsed
-        .choices
-        .get(0)
-        .map(|c| c.message.content.trim().to_string())
-        .unwrap_or_default();
+    let text = parsed.text();
 
     Ok(text)
 }
@@ -296,7 +839,7 @@ async fn call_groq(
     client: &Client,
     model_arg_fallback: &str,
     image_data_url: &str,
-    user_text: &str,
+    prompt: &Prompt<'_>,
 ) -> Result<String> {
     let base =
         env::var("GROQ_BASE_URL").unwrap_or_else(|_| "https://api.groq.com/openai/v1".to_string());
@@ -305,7 +848,7 @@ async fn call_groq(
     let model = env::var("GROQ_MODEL").unwrap_or_else(|_| model_arg_fallback.to_string());
 
     let url = format!("{}/chat/completions", base);
-    let payload = build_payload(&model, image_data_url, user_text);
+    let payload = build_payload(&model, image_data_url, prompt);
 
     let resp = client
         .post(&url)
@@ -315,10 +858,10 @@ async fn call_groq(
         .await?;
 
     if resp.status() == StatusCode::TOO_MANY_REQUESTS {
-        return Err(ExtractError::RateLimited("groq"));
+        return Err(ExtractError::RateLimited("groq", retry_after(&resp)));
     }
     if !resp.status().is_success() {
-        return Err(ExtractError::ProviderStatus("groq", resp.status()));
+        return Err(status_error("groq", resp).await);
     }
 
     let parsed: OAChatResponse = resp
@@ -326,22 +869,266 @@ async fn call_groq(
         .await
         .map_err(|e| ExtractError::ProviderDecode("groq", e.to_string()))?;
 
-    let text = parsed
-        .choices
-        .get(0)
-        .map(|c| c.message.content.trim().to_string())
-        .unwrap_or_default();
+    let text = parsed.text();
+
+    Ok(text)
+}
+
+/* ----- Ollama ----- */
+
+/// Self-hosted models through Ollama's OpenAI compatible endpoint. No API key is
+/// needed, and the model defaults to `gemma3:4b` rather than the hosted model name.
+async fn call_ollama(client: &Client, image_data_url: &str, prompt: &Prompt<'_>) -> Result<String> {
+    let base =
+        env::var("OLLAMA_BASE_URL").unwrap_or_else(|_| "http://localhost:11434/v1".to_string());
+    let model = env::var("OLLAMA_MODEL").unwrap_or_else(|_| "gemma3:4b".to_string());
+
+    let url = format!("{}/chat/completions", base);
+    let payload = build_payload(&model, image_data_url, prompt);
+
+    let resp = client.post(&url).json(&payload).send().await?;
+
+    if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+        return Err(ExtractError::RateLimited("ollama", retry_after(&resp)));
+    }
+    if !resp.status().is_success() {
+        return Err(status_error("ollama", resp).await);
+    }
+
+    let parsed: OAChatResponse = resp
+        .json()
+        .await
+        .map_err(|e| ExtractError::ProviderDecode("ollama", e.to_string()))?;
+
+    let text = parsed.text();
 
     Ok(text)
 }
 
+/* ----- Anthropic ----- */
+
+/// Anthropic's messages API takes the image as a base64 `source` block rather than
+/// a data URL, so the payload is built separately from `build_payload`.
+async fn call_anthropic(client: &Client, image: &EncodedImage, prompt: &Prompt<'_>) -> Result<String> {
+    let base =
+        env::var("ANTHROPIC_BASE_URL").unwrap_or_else(|_| "https://api.anthropic.com".to_string());
+    let api_key = env::var("ANTHROPIC_API_KEY").map_err(|_| {
+        ExtractError::ProviderDecode("anthropic", "missing ANTHROPIC_API_KEY".into())
+    })?;
+    let model = env::var("ANTHROPIC_MODEL").unwrap_or_else(|_| "claude-haiku-4-5".to_string());
+
+    let url = format!("{}/v1/messages", base);
+    let payload = AnthropicRequest {
+        model: &model,
+        max_tokens: prompt.max_tokens,
+        system: prompt.system,
+        messages: vec![AnthropicMessage {
+            role: "user",
+            content: vec![
+                AnthropicContent::Image {
+                    source: AnthropicImageSource {
+                        kind: "base64",
+                        media_type: image.mime_type,
+                        data: &image.base64,
+                    },
+                },
+                AnthropicContent::Text { text: prompt.user },
+            ],
+        }],
+        temperature: 0.0,
+    };
+
+    let resp = client
+        .post(&url)
+        .header("x-api-key", api_key)
+        .header("anthropic-version", "2023-06-01")
+        .json(&payload)
+        .send()
+        .await?;
+
+    if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+        return Err(ExtractError::RateLimited("anthropic", retry_after(&resp)));
+    }
+    if !resp.status().is_success() {
+        return Err(status_error("anthropic", resp).await);
+    }
+
+    let parsed: AnthropicResponse = resp
+        .json()
+        .await
+        .map_err(|e| ExtractError::ProviderDecode("anthropic", e.to_string()))?;
+
+    let text = parsed.text();
+
+    // Single line answers are cut by a stop sequence elsewhere, Anthropic doesn't
+    // accept a bare newline as one.
+    let text = if prompt.single_line {
+        text.lines().next().unwrap_or_default().to_string()
+    } else {
+        text
+    };
+
+    Ok(text)
+}
+
+/* ----- Gemini ----- */
+
+/// Google's `generateContent` API, the image goes in as an `inline_data` part.
+async fn call_gemini(client: &Client, image: &EncodedImage, prompt: &Prompt<'_>) -> Result<String> {
+    let base = env::var("GEMINI_BASE_URL")
+        .unwrap_or_else(|_| "https://generativelanguage.googleapis.com/v1beta".to_string());
+    let api_key = env::var("GEMINI_API_KEY")
+        .map_err(|_| ExtractError::ProviderDecode("gemini", "missing GEMINI_API_KEY".into()))?;
+    let model = env::var("GEMINI_MODEL").unwrap_or_else(|_| "gemini-2.0-flash".to_string());
+
+    let url = format!("{}/models/{}:generateContent", base, model);
+    let payload = GeminiRequest {
+        system_instruction: GeminiContent {
+            parts: vec![GeminiPart::Text { text: prompt.system }],
+        },
+        contents: vec![GeminiContent {
+            parts: vec![
+                GeminiPart::InlineData {
+                    inline_data: GeminiInlineData {
+                        mime_type: image.mime_type,
+                        data: &image.base64,
+                    },
+                },
+                GeminiPart::Text { text: prompt.user },
+            ],
+        }],
+        generation_config: GeminiGenerationConfig {
+            max_output_tokens: prompt.max_tokens,
+            temperature: 0.0,
+            response_mime_type: prompt.json.then_some("application/json"),
+        },
+    };
+
+    let resp = client
+        .post(&url)
+        .header("x-goog-api-key", api_key)
+        .json(&payload)
+        .send()
+        .await?;
+
+    if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+        return Err(ExtractError::RateLimited("gemini", retry_after(&resp)));
+    }
+    if !resp.status().is_success() {
+        return Err(status_error("gemini", resp).await);
+    }
+
+    let parsed: GeminiResponse = resp
+        .json()
+        .await
+        .map_err(|e| ExtractError::ProviderDecode("gemini", e.to_string()))?;
+
+    let text = parsed.text();
+
+    let text = if prompt.single_line {
+        text.lines().next().unwrap_or_default().to_string()
+    } else {
+        text
+    };
+
+    Ok(text)
+}
+
+/* ---------- Content hash ---------- */
+
+/// Identifies an uploaded screenshot by its bytes, so a re-upload of the same file
+/// can be recognised without running OCR again.
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    let digest = content_digest(bytes);
+    u64::from_be_bytes(digest[..8].try_into().expect("a SHA-256 digest has 32 bytes"))
+}
+
+fn content_digest(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytes).into()
+}
+
+/* ---------- Result cache ---------- */
+
+type CacheKey = (String, [u8; 32]);
+
+/// Results per model and image, so the same screenshot isn't encoded and sent again.
+/// `OCR_CACHE_SIZE` entries (default 256) are kept, 0 disables the cache.
+static OCR_CACHE: Lazy<Option<Mutex<LruCache<CacheKey, ExtractOutcome>>>> = Lazy::new(|| {
+    NonZeroUsize::new(env_parse("OCR_CACHE_SIZE", 256usize))
+        .map(|capacity| Mutex::new(LruCache::new(capacity)))
+});
+
+fn cached_outcome(key: &CacheKey) -> Option<ExtractOutcome> {
+    let cache = OCR_CACHE.as_ref()?;
+    let mut cache = cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache.get(key).cloned()
+}
+
+fn cache_outcome(key: CacheKey, outcome: &ExtractOutcome) {
+    if let Some(cache) = OCR_CACHE.as_ref() {
+        let mut cache = cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        cache.put(key, outcome.clone());
+    }
+}
+
+/* ---------- Result screen precheck ---------- */
+
+/// Heuristic: a time trial result screen shows a yellow result card, menus and
+/// photos of other screens rarely contain a meaningful share of that yellow.
+/// The minimum share is configurable through `OCR_PRECHECK_MIN_YELLOW` (0.0–1.0).
+pub fn looks_like_result_screen(bytes: &[u8]) -> Result<bool> {
+    let img = decode_image(bytes)?;
+    let min_share = env_parse("OCR_PRECHECK_MIN_YELLOW", 0.005f32);
+    Ok(yellow_share(&img) >= min_share)
+}
+
+fn yellow_share(img: &DynamicImage) -> f32 {
+    // Sampling a thumbnail is plenty for a coarse colour share.
+    let thumb = img.thumbnail(160, 160).to_rgb8();
+    let total = thumb.pixels().len();
+    if total == 0 {
+        return 0.0;
+    }
+    let yellow = thumb
+        .pixels()
+        .filter(|p| {
+            let [r, g, b] = p.0;
+            r >= 180 && g >= 150 && b <= 110 && r.saturating_sub(b) >= 90
+        })
+        .count();
+    yellow as f32 / total as f32
+}
+
 /* ---------- Image downscale + data URL ---------- */
 
-/// Convert arbitrary input bytes into a downscaled data URL (PNG or JPEG),
+/// A downscaled image as base64, ready to send to a provider.
+struct EncodedImage {
+    mime_type: &'static str,
+    base64: String,
+}
+
+impl EncodedImage {
+    fn new(mime_type: &'static str, bytes: Vec<u8>) -> Self {
+        EncodedImage {
+            mime_type,
+            base64: base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+
+    /// The form OpenAI compatible APIs take, `data:image/png;base64,...`.
+    fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.base64)
+    }
+}
+
+/// Convert arbitrary input bytes into a downscaled base64 image (PNG or JPEG),
 /// choosing the smallest that still looks good and stays under ~3.9 MB base64.
-fn prepare_image_data_url(bytes: &[u8]) -> Result<String> {
-    let mut img =
-        image::load_from_memory(bytes).map_err(|e| ExtractError::ImageDecode(e.to_string()))?;
+/// Providers that take a data URL get it from `EncodedImage::data_url`.
+fn prepare_image_base64(bytes: &[u8]) -> Result<EncodedImage> {
+    let mut img = decode_image(bytes)?;
+
+    // Drop letterbox/pillarbox bars so the resolution budget goes to the game frame.
+    img = crop_black_borders(img);
 
     // Prefer PNG if the source has alpha (transparency).
     let mut prefer_png = has_alpha(&img);
@@ -363,37 +1150,30 @@ fn prepare_image_data_url(bytes: &[u8]) -> Result<String> {
         let mut candidates: Vec<(String, usize)> = Vec::new();
 
         // Encode PNG (good for transparency / UI text)
-        if prefer_png {
-            if let Ok(png) = encode_png(&current) {
-                let b64_len = estimate_base64_len(png.len());
-                if b64_len <= SAFE_BASE64_MAX {
-                    let b64 = base64::engine::general_purpose::STANDARD.encode(png);
-                    return Ok(format!("data:image/png;base64,{}", b64));
-                }
-                candidates.push(("png".into(), b64_len));
+        if prefer_png && let Ok(png) = encode_png(&current) {
+            let b64_len = estimate_base64_len(png.len());
+            if b64_len <= SAFE_BASE64_MAX {
+                return Ok(EncodedImage::new("image/png", png));
             }
+            candidates.push(("png".into(), b64_len));
         }
 
         // Encode JPEG at current quality (good for photos; often smaller)
         if let Ok(jpg) = encode_jpeg(&current, jpeg_quality) {
             let b64_len = estimate_base64_len(jpg.len());
             if b64_len <= SAFE_BASE64_MAX {
-                let b64 = base64::engine::general_purpose::STANDARD.encode(jpg);
-                return Ok(format!("data:image/jpeg;base64,{}", b64));
+                return Ok(EncodedImage::new("image/jpeg", jpg));
             }
             candidates.push(("jpeg".into(), b64_len));
         }
 
         // If PNG wasn’t preferred initially, try it as an alternative and see if it’s smaller.
-        if !prefer_png {
-            if let Ok(png) = encode_png(&current) {
-                let b64_len = estimate_base64_len(png.len());
-                if b64_len <= SAFE_BASE64_MAX {
-                    let b64 = base64::engine::general_purpose::STANDARD.encode(png);
-                    return Ok(format!("data:image/png;base64,{}", b64));
-                }
-                candidates.push(("png".into(), b64_len));
+        if !prefer_png && let Ok(png) = encode_png(&current) {
+            let b64_len = estimate_base64_len(png.len());
+            if b64_len <= SAFE_BASE64_MAX {
+                return Ok(EncodedImage::new("image/png", png));
             }
+            candidates.push(("png".into(), b64_len));
         }
 
         // Neither fit: adjust strategy
@@ -424,6 +1204,79 @@ fn prepare_image_data_url(bytes: &[u8]) -> Result<String> {
     Err(ExtractError::ImageTooLarge)
 }
 
+/// Decode the image, but only after its header confirms the pixel count stays within
+/// `OCR_MAX_MEGAPIXELS` (default 40), so a decompression bomb is rejected up front.
+fn decode_image(bytes: &[u8]) -> Result<DynamicImage> {
+    let (width, height) = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| decode_error(bytes, e))?
+        .into_dimensions()
+        .map_err(|e| decode_error(bytes, e))?;
+
+    let max_megapixels = env_parse("OCR_MAX_MEGAPIXELS", 40.0f64);
+    if exceeds_megapixels(width, height, max_megapixels) {
+        return Err(ExtractError::ImageDimensionsTooLarge(width, height));
+    }
+
+    image::load_from_memory(bytes).map_err(|e| decode_error(bytes, e))
+}
+
+/// PNG, JPEG and WebP are decoded. Other images, such as AVIF or HEIC from phones,
+/// are reported by name so the uploader knows to convert them.
+fn decode_error(bytes: &[u8], error: impl ToString) -> ExtractError {
+    match infer::get(bytes) {
+        Some(kind)
+            if kind.matcher_type() == infer::MatcherType::Image
+                && !matches!(kind.extension(), "png" | "jpg" | "webp") =>
+        {
+            ExtractError::UnsupportedImageFormat(kind.extension().to_ascii_uppercase())
+        }
+        _ => ExtractError::ImageDecode(error.to_string()),
+    }
+}
+
+fn exceeds_megapixels(width: u32, height: u32, max_megapixels: f64) -> bool {
+    (width as f64) * (height as f64) > max_megapixels * 1_000_000.0
+}
+
+/// Crop uniform black bars (rows/columns that are entirely near-black) from the
+/// edges of the image. Bars thinner than ~2% of the side are left alone so a
+/// dark UI edge isn't mistaken for a border; fully dark images are returned as-is.
+fn crop_black_borders(img: DynamicImage) -> DynamicImage {
+    const DARK_LUMA_MAX: u8 = 16;
+    const MIN_BAR_FRACTION: f32 = 0.02;
+
+    let luma = img.to_luma8();
+    let (w, h) = luma.dimensions();
+    if w == 0 || h == 0 {
+        return img;
+    }
+
+    let row_dark = |y: u32| (0..w).all(|x| luma.get_pixel(x, y)[0] <= DARK_LUMA_MAX);
+    let top = (0..h).take_while(|&y| row_dark(y)).count() as u32;
+    if top == h {
+        return img;
+    }
+    let bottom = h - (0..h).rev().take_while(|&y| row_dark(y)).count() as u32;
+
+    let col_dark = |x: u32| (top..bottom).all(|y| luma.get_pixel(x, y)[0] <= DARK_LUMA_MAX);
+    let left = (0..w).take_while(|&x| col_dark(x)).count() as u32;
+    let right = w - (0..w).rev().take_while(|&x| col_dark(x)).count() as u32;
+
+    let min_rows = ((h as f32) * MIN_BAR_FRACTION).ceil() as u32;
+    let min_cols = ((w as f32) * MIN_BAR_FRACTION).ceil() as u32;
+    let top = if top >= min_rows { top } else { 0 };
+    let bottom = if h - bottom >= min_rows { bottom } else { h };
+    let left = if left >= min_cols { left } else { 0 };
+    let right = if w - right >= min_cols { right } else { w };
+
+    if top == 0 && bottom == h && left == 0 && right == w {
+        return img;
+    }
+
+    img.crop_imm(left, top, right - left, bottom - top)
+}
+
 fn resize_long_side(img: DynamicImage, max_side: u32) -> DynamicImage {
     let (w, h) = img.dimensions();
     let long = w.max(h);
@@ -475,7 +1328,7 @@ fn has_alpha(img: &DynamicImage) -> bool {
 #[inline]
 fn estimate_base64_len(raw_bytes: usize) -> usize {
     // base64 expands by ~4/3; round up to nearest 4
-    ((raw_bytes + 2) / 3) * 4
+    raw_bytes.div_ceil(3) * 4
 }
 
 /* ---------- Post-processing (unchanged parsing) ---------- */
@@ -487,36 +1340,374 @@ fn post_process_to_duration(text: &str) -> Result<Duration> {
         return Err(ExtractError::YellowMissing);
     }
 
+    let text = normalize_verbal_time(text);
+    let text = normalize_time_text(&text);
+    let text = text.as_str();
+
     if TIME_STRICT_RE.is_match(text) {
-        return parse_duration(text);
+        return Ok(time_format::parse(text)?);
     }
 
-    if let Some(m) = TIME_FINDER_RE.find(text) {
-        return parse_duration(m.as_str());
+    let times = TIME_FINDER_RE
+        .find_iter(text)
+        .map(|m| time_format::parse(m.as_str()))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    select_time(&times, read_multi_time_rule()).ok_or(ExtractError::YellowMissing)
+}
+
+/// Set `OCR_RESPONSE_FORMAT=json` to ask capable models for a strict JSON answer.
+fn json_response_format() -> bool {
+    env::var("OCR_RESPONSE_FORMAT").is_ok_and(|format| format.trim().eq_ignore_ascii_case("json"))
+}
+
+/// Reads a `{"time":"m:ss.mmm","found":true}` answer. `None` when the text isn't
+/// such an object, so the caller can fall back to the free-text parsing.
+fn parse_json_answer(text: &str) -> Option<Result<Duration>> {
+    let answer: TimeAnswer = serde_json::from_str(text.trim()).ok()?;
+
+    if !answer.found {
+        return Some(Err(ExtractError::YellowMissing));
     }
 
-    Err(ExtractError::YellowMissing)
+    let time = normalize_time_text(answer.time?.trim());
+    if !TIME_STRICT_RE.is_match(&time) {
+        return None;
+    }
+    Some(time_format::parse(&time).map_err(ExtractError::from))
 }
 
-pub fn parse_duration(s: &str) -> Result<Duration> {
-    let caps = TIME_STRICT_RE
-        .captures(s)
-        .ok_or_else(|| ExtractError::InvalidFormat(s.to_string()))?;
+/// Most times `extract_all_times` returns, a result screen has far fewer laps.
+const MAX_EXTRACTED_TIMES: usize = 8;
+
+/// Every `m:ss.mmm` time in a model answer, in order, e.g. the lap splits next to
+/// the final time. Repeats of the same time in a row are kept once.
+pub fn extract_all_times(text: &str) -> Vec<Duration> {
+    let text = normalize_time_text(text);
+
+    let mut times: Vec<Duration> = TIME_FINDER_RE
+        .find_iter(&text)
+        .filter_map(|m| time_format::parse(m.as_str()).ok())
+        .collect();
+    times.dedup();
+    times.truncate(MAX_EXTRACTED_TIMES);
+    times
+}
 
-    let minutes = caps[1]
-        .parse::<u64>()
-        .map_err(|e| ExtractError::MinutesParse(e.to_string()))?;
+/// Reads the time from a raw answer, as JSON first when that format was requested.
+fn parse_answer(text: &str, json: bool) -> Result<Duration> {
+    match json.then(|| parse_json_answer(text)).flatten() {
+        Some(result) => result,
+        None => post_process_to_duration(text),
+    }
+    .and_then(check_plausible)
+}
 
-    let seconds = caps[2]
-        .parse::<u64>()
-        .map_err(|e| ExtractError::SecondsParse(e.to_string()))?;
-    if seconds > 59 {
-        return Err(ExtractError::InvalidFormat(caps[2].to_string()));
+/// Rejects times no track can produce, e.g. a misread overlay. The bounds are
+/// `OCR_MIN_SECONDS` (default 5) and `OCR_MAX_SECONDS` (default 600).
+fn check_plausible(time: Duration) -> Result<Duration> {
+    let bound = |name: &str, default: u64| {
+        Duration::try_from_secs_f64(env_parse(name, default as f64))
+            .unwrap_or(Duration::from_secs(default))
+    };
+    let min = bound("OCR_MIN_SECONDS", 5);
+    let max = bound("OCR_MAX_SECONDS", 600);
+    if time < min || time > max {
+        return Err(ExtractError::ImplausibleTime(time));
+    }
+    Ok(time)
+}
+
+/// Which time to keep when the model answers with more than one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum MultiTimeRule {
+    First,
+    Last,
+    Min,
+}
+
+fn read_multi_time_rule() -> MultiTimeRule {
+    match env::var("OCR_MULTI_TIME_RULE")
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "last" => MultiTimeRule::Last,
+        "min" => MultiTimeRule::Min,
+        _ => MultiTimeRule::First,
     }
+}
 
-    let millis = caps[3]
-        .parse::<u64>()
-        .map_err(|e| ExtractError::MillisParse(e.to_string()))?;
+fn select_time(times: &[Duration], rule: MultiTimeRule) -> Option<Duration> {
+    match rule {
+        MultiTimeRule::First => times.first().copied(),
+        MultiTimeRule::Last => times.last().copied(),
+        MultiTimeRule::Min => times.iter().min().copied(),
+    }
+}
+
+/// Rewrites times spelled out with units, like "1m 23.456s" or "1 min 23 sec 456 ms",
+/// into `m:ss.mmm`. A fraction on the seconds is read as a decimal, a separate
+/// millisecond count as a number, so "45 ms" is 045. Anything else is kept as is,
+/// the strict parsing after this still decides what counts as a time.
+fn normalize_verbal_time(text: &str) -> String {
+    VERBAL_TIME_RE
+        .replace_all(text, |caps: &regex::Captures| {
+            // A bare "5 seconds" is more likely prose than a race time.
+            let bare_seconds = caps.get(1).is_none() && caps.get(3).is_none() && caps.get(4).is_none();
+            let minutes = caps.get(1).map_or("0", |m| m.as_str());
+            let seconds: u64 = caps[2].parse().unwrap_or(60);
+            if bare_seconds || seconds >= 60 {
+                return caps[0].to_string();
+            }
+            let millis = match (caps.get(3), caps.get(4)) {
+                (Some(fraction), _) => format!("{:0<3}", fraction.as_str()),
+                (None, Some(ms)) => format!("{:0>3}", ms.as_str()),
+                (None, None) => "000".to_string(),
+            };
+            format!("{minutes}:{seconds:02}.{millis}")
+        })
+        .into_owned()
+}
+
+/// Rewrite the time into the canonical `m:ss.mmm` form. Fractions are read as
+/// decimals, so one or two digits are padded (`1:23.45` is 450 ms) and extra digits
+/// are dropped. Sub-minute times shown without a minutes segment (`23.456`) get `0:`.
+/// Anything else is returned unchanged.
+fn normalize_time_text(text: &str) -> String {
+    let text = TIME_FRACTION_RE.replace_all(text, |caps: &regex::Captures| {
+        format!("{}.{:0<3.3}", &caps[1], &caps[2])
+    });
+    let text = text.as_ref();
+
+    if let Some(caps) = TIME_SECONDS_ONLY_RE.captures(text)
+        && let Ok(seconds) = caps[1].parse::<u64>()
+        && seconds < 60
+    {
+        return format!("0:{:0>2}.{}", seconds, &caps[2]);
+    }
+
+    text.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_WAIT: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn retry_after_within_the_limit_is_waited_for() {
+        let e = ExtractError::RateLimited("groq", Some(Duration::from_secs(5)));
+        assert_eq!(retry_delay(&e, Duration::from_millis(500), 0, MAX_WAIT), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn retry_after_beyond_the_limit_gives_up() {
+        let e = ExtractError::RateLimited("groq", Some(Duration::from_secs(3600)));
+        assert_eq!(retry_delay(&e, Duration::from_millis(500), 0, MAX_WAIT), None);
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter() {
+        let e = ExtractError::RateLimited("groq", None);
+        let base = Duration::from_millis(500);
+        let delay = retry_delay(&e, base, 2, MAX_WAIT).unwrap();
+        assert!(delay >= Duration::from_millis(2000) && delay < Duration::from_millis(2500));
+    }
+
+    /// A light 200x100 picture inside black bars, `bars` pixels on every side.
+    fn letterboxed(bars: u32) -> DynamicImage {
+        let (w, h) = (200 + 2 * bars, 100 + 2 * bars);
+        let img = image::RgbImage::from_fn(w, h, |x, y| {
+            let inside = (bars..w - bars).contains(&x) && (bars..h - bars).contains(&y);
+            if inside { image::Rgb([200, 180, 40]) } else { image::Rgb([5, 5, 5]) }
+        });
+        DynamicImage::ImageRgb8(img)
+    }
+
+    #[test]
+    fn black_bars_are_cropped() {
+        let cropped = crop_black_borders(letterboxed(20));
+        assert_eq!(cropped.dimensions(), (200, 100));
+    }
+
+    #[test]
+    fn thin_dark_edges_are_kept() {
+        let img = letterboxed(1);
+        assert_eq!(crop_black_borders(img).dimensions(), (202, 102));
+    }
 
-    Ok(Duration::from_secs(minutes * 60 + seconds) + Duration::from_millis(millis))
+    #[test]
+    fn fully_dark_image_is_left_alone() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::new(50, 40));
+        assert_eq!(crop_black_borders(img).dimensions(), (50, 40));
+    }
+
+    #[test]
+    fn content_hash_is_the_start_of_the_sha256() {
+        assert_eq!(content_hash(b"abc"), 0xba78_16bf_8f01_cfea);
+    }
+
+    #[test]
+    fn changed_bytes_change_the_hash() {
+        assert_eq!(content_hash(b"screenshot"), content_hash(b"screenshot"));
+        assert_ne!(content_hash(b"screenshot"), content_hash(b"screenshoT"));
+    }
+
+    #[test]
+    fn json_answer_gives_its_time() {
+        let time = parse_json_answer(r#"{"time":"1:23.45","found":true}"#).unwrap().unwrap();
+        assert_eq!(time, Duration::from_millis(83_450));
+    }
+
+    #[test]
+    fn json_answer_without_a_time_is_missing() {
+        let answer = parse_json_answer(r#"{"time":null,"found":false}"#).unwrap();
+        assert!(matches!(answer, Err(ExtractError::YellowMissing)));
+    }
+
+    #[test]
+    fn other_answers_fall_back_to_the_text_parsing() {
+        assert!(parse_json_answer("1:23.456").is_none());
+        assert!(parse_json_answer(r#"{"time":"about a minute","found":true}"#).is_none());
+    }
+
+    #[test]
+    fn json_error_bodies_give_their_message() {
+        let body = r#"{"error":{"message":"model not found","type":"invalid_request_error"}}"#;
+        assert_eq!(error_message(body), "model not found");
+        assert_eq!(error_message("  Bad Gateway\n"), "Bad Gateway");
+    }
+
+    #[test]
+    fn long_error_messages_are_truncated() {
+        let message = error_message(&"é".repeat(MAX_ERROR_MESSAGE_LEN + 10));
+        assert_eq!(message, format!("{}…", "é".repeat(MAX_ERROR_MESSAGE_LEN)));
+    }
+
+    #[test]
+    fn spelled_out_times_become_clock_times() {
+        assert_eq!(normalize_verbal_time("1m 23.456s"), "1:23.456");
+        assert_eq!(normalize_verbal_time("1 min 23 sec 45 ms"), "1:23.045");
+        assert_eq!(normalize_verbal_time("The time is 1 minute 5.2 seconds."), "The time is 1:05.200.");
+    }
+
+    #[test]
+    fn prose_seconds_are_left_alone() {
+        assert_eq!(normalize_verbal_time("wait 5 seconds"), "wait 5 seconds");
+        assert_eq!(normalize_verbal_time("1 min 75 sec"), "1 min 75 sec");
+        assert_eq!(normalize_verbal_time("1:23.456"), "1:23.456");
+    }
+
+    #[test]
+    fn sub_minute_times_get_a_minutes_segment() {
+        assert_eq!(normalize_time_text("23.456"), "0:23.456");
+        assert_eq!(normalize_time_text("59.999"), "0:59.999");
+        assert_eq!(normalize_time_text("5.123"), "0:05.123");
+    }
+
+    #[test]
+    fn a_minute_or_more_without_minutes_is_left_alone() {
+        assert_eq!(normalize_time_text("60.000"), "60.000");
+        assert!(post_process_to_duration("60.000").is_err());
+    }
+
+    #[test]
+    fn short_fractions_are_padded_as_decimals() {
+        assert_eq!(normalize_time_text("1:23.4"), "1:23.400");
+        assert_eq!(normalize_time_text("1:23.45"), "1:23.450");
+        assert_eq!(normalize_time_text("1:23.456"), "1:23.456");
+    }
+
+    #[test]
+    fn extra_fraction_digits_are_dropped() {
+        assert_eq!(normalize_time_text("1:23.4567"), "1:23.456");
+        assert_eq!(normalize_time_text("Time: 1:23.4567!"), "Time: 1:23.456!");
+    }
+
+    fn laps() -> Vec<Duration> {
+        [28_512, 27_904, 83_210, 26_794].map(Duration::from_millis).to_vec()
+    }
+
+    #[test]
+    fn multi_time_rules_pick_their_time() {
+        assert_eq!(select_time(&laps(), MultiTimeRule::First), Some(Duration::from_millis(28_512)));
+        assert_eq!(select_time(&laps(), MultiTimeRule::Last), Some(Duration::from_millis(26_794)));
+        assert_eq!(select_time(&laps(), MultiTimeRule::Min), Some(Duration::from_millis(26_794)));
+        assert_eq!(select_time(&laps()[..3], MultiTimeRule::Min), Some(Duration::from_millis(27_904)));
+    }
+
+    #[test]
+    fn no_times_select_nothing() {
+        for rule in [MultiTimeRule::First, MultiTimeRule::Last, MultiTimeRule::Min] {
+            assert_eq!(select_time(&[], rule), None);
+        }
+    }
+
+    #[test]
+    fn repeated_times_in_a_row_are_extracted_once() {
+        let times = extract_all_times("Lap 1 0:28.512\nLap 1 0:28.512\nLap 2 0:27.904\nLap 3 0:28.512\nTotal 1:24.928");
+        let expected = [28_512, 27_904, 28_512, 84_928].map(Duration::from_millis);
+        assert_eq!(times, expected);
+    }
+
+    #[test]
+    fn extracted_times_are_capped() {
+        let text = (10..20).map(|s| format!("0:{s}.000")).collect::<Vec<_>>().join(" ");
+        let times = extract_all_times(&text);
+        assert_eq!(times.len(), MAX_EXTRACTED_TIMES);
+        assert_eq!(times.first(), Some(&Duration::from_secs(10)));
+        assert_eq!(times.last(), Some(&Duration::from_secs(17)));
+    }
+
+    /// A PNG header claiming 10000x10000 pixels, followed by an empty data chunk.
+    fn oversized_png_header() -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&10_000u32.to_be_bytes());
+        png.extend_from_slice(&10_000u32.to_be_bytes());
+        png.extend_from_slice(&[8, 2, 0, 0, 0]);
+        png.extend_from_slice(&0x352c_f570u32.to_be_bytes());
+        png.extend_from_slice(b"\x00\x00\x00\x00IDAT\x35\xaf\x06\x1e");
+        png
+    }
+
+    #[test]
+    fn oversized_images_are_rejected_from_the_header() {
+        let result = decode_image(&oversized_png_header());
+        assert!(matches!(result, Err(ExtractError::ImageDimensionsTooLarge(10_000, 10_000))));
+    }
+
+    #[test]
+    fn megapixel_limit_is_inclusive() {
+        assert!(!exceeds_megapixels(1000, 1000, 1.0));
+        assert!(exceeds_megapixels(1000, 1001, 1.0));
+        assert!(!exceeds_megapixels(u32::MAX, u32::MAX, f64::INFINITY));
+    }
+
+    /// What `parse_answer` makes of "1:23.4" in each provider's response body.
+    fn parsed_answers() -> Vec<(&'static str, Result<Duration>)> {
+        let chat = r#"{"choices":[{"message":{"role":"assistant","content":" 1:23.4\n"}}]}"#;
+        let anthropic = r#"{"content":[{"type":"text","text":"1:23.4"}]}"#;
+        let gemini = r#"{"candidates":[{"content":{"parts":[{"text":"1:23.4\n"}]}}]}"#;
+
+        let chat: OAChatResponse = serde_json::from_str(chat).unwrap();
+        let anthropic: AnthropicResponse = serde_json::from_str(anthropic).unwrap();
+        let gemini: GeminiResponse = serde_json::from_str(gemini).unwrap();
+
+        // Ollama, Groq and OpenRouter all answer in the OpenAI chat format.
+        vec![
+            ("ollama", parse_answer(&chat.text(), false)),
+            ("anthropic", parse_answer(&anthropic.text(), false)),
+            ("gemini", parse_answer(&gemini.text(), false)),
+        ]
+    }
+
+    #[test]
+    fn ollama_answers_parse_like_the_other_providers() {
+        for (provider, parsed) in parsed_answers() {
+            assert_eq!(parsed.ok(), Some(Duration::from_millis(83_400)), "{provider}");
+        }
+    }
 }
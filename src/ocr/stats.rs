@@ -0,0 +1,114 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// How one provider call ended, after its retries.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    /// A failure the next provider is tried after, like a rate limit or server error.
+    RetryableFailure,
+    /// A failure that stops the failover, like a bad request or an unreadable answer.
+    FatalFailure,
+}
+
+/// Per provider call counts since startup, so it's visible which provider is
+/// actually serving the screenshots without scraping metrics.
+pub struct ProviderStats {
+    counters: Vec<(&'static str, Counters)>,
+}
+
+#[derive(Default)]
+struct Counters {
+    success: AtomicU64,
+    retryable: AtomicU64,
+    fatal: AtomicU64,
+}
+
+/// A copy of one provider's counters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProviderCounts {
+    pub success: u64,
+    pub retryable: u64,
+    pub fatal: u64,
+}
+
+impl ProviderStats {
+    pub fn new(providers: &[&'static str]) -> Self {
+        ProviderStats {
+            counters: providers.iter().map(|&name| (name, Counters::default())).collect(),
+        }
+    }
+
+    /// Counts the outcome for the provider, unknown providers are ignored.
+    pub fn record(&self, provider: &str, outcome: Outcome) {
+        let Some((_, counters)) = self.counters.iter().find(|(name, _)| *name == provider) else {
+            return;
+        };
+
+        let counter = match outcome {
+            Outcome::Success => &counters.success,
+            Outcome::RetryableFailure => &counters.retryable,
+            Outcome::FatalFailure => &counters.fatal,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, provider: &str) -> Option<ProviderCounts> {
+        self.snapshot()
+            .into_iter()
+            .find(|(name, _)| *name == provider)
+            .map(|(_, counts)| counts)
+    }
+
+    pub fn snapshot(&self) -> Vec<(&'static str, ProviderCounts)> {
+        self.counters
+            .iter()
+            .map(|(name, counters)| {
+                let counts = ProviderCounts {
+                    success: counters.success.load(Ordering::Relaxed),
+                    retryable: counters.retryable.load(Ordering::Relaxed),
+                    fatal: counters.fatal.load(Ordering::Relaxed),
+                };
+                (*name, counts)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for ProviderCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ok, {} retryable failures, {} fatal failures",
+            self.success, self.retryable, self.fatal
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outcomes_are_counted_per_provider() {
+        let stats = ProviderStats::new(&["groq", "gemini"]);
+        stats.record("groq", Outcome::Success);
+        stats.record("groq", Outcome::Success);
+        stats.record("groq", Outcome::RetryableFailure);
+        stats.record("gemini", Outcome::FatalFailure);
+
+        assert_eq!(stats.get("groq"), Some(ProviderCounts { success: 2, retryable: 1, fatal: 0 }));
+        assert_eq!(stats.get("gemini"), Some(ProviderCounts { success: 0, retryable: 0, fatal: 1 }));
+    }
+
+    #[test]
+    fn unknown_providers_are_ignored() {
+        let stats = ProviderStats::new(&["groq"]);
+        stats.record("ollama", Outcome::Success);
+
+        assert_eq!(stats.get("ollama"), None);
+        assert_eq!(stats.snapshot(), vec![("groq", ProviderCounts::default())]);
+    }
+}
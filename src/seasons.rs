@@ -0,0 +1,127 @@
+use std::env;
+
+use chrono::{Days, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serenity::all::Timestamp;
+
+use crate::config::day_timezone;
+
+/// A named date window, both ends inclusive, in `config::day_timezone`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Season {
+    pub name: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl Season {
+    /// The window as `[start, end)` instants: from midnight on the first day up to
+    /// midnight after the last day.
+    pub fn bounds(&self) -> Option<(Timestamp, Timestamp)> {
+        self.bounds_in(day_timezone())
+    }
+
+    /// Like `bounds`, with the days counted in `tz`.
+    pub fn bounds_in(&self, tz: Tz) -> Option<(Timestamp, Timestamp)> {
+        let start = local_midnight(self.start, tz)?;
+        let end = local_midnight(self.end.checked_add_days(Days::new(1))?, tz)?;
+        Some((start, end))
+    }
+}
+
+fn local_midnight(date: NaiveDate, tz: Tz) -> Option<Timestamp> {
+    let local = tz
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .earliest()?;
+    Some(Timestamp::from(local.with_timezone(&Utc)))
+}
+
+/// Reads seasons from `SEASONS`, formatted as `name:YYYY-MM-DD:YYYY-MM-DD`, comma separated.
+pub fn read_seasons() -> Vec<Season> {
+    parse_seasons(&env::var("SEASONS").unwrap_or_default())
+}
+
+/// Entries that don't parse, or end before they start, are skipped with a warning.
+pub fn parse_seasons(raw: &str) -> Vec<Season> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let season = parse_season(entry);
+            if season.is_none() {
+                eprintln!("ignoring invalid season entry: {entry}");
+            }
+            season
+        })
+        .collect()
+}
+
+fn parse_season(entry: &str) -> Option<Season> {
+    let mut parts = entry.splitn(3, ':');
+    let name = parts.next()?.trim();
+    let start = NaiveDate::parse_from_str(parts.next()?.trim(), "%Y-%m-%d").ok()?;
+    let end = NaiveDate::parse_from_str(parts.next()?.trim(), "%Y-%m-%d").ok()?;
+    if name.is_empty() || end < start {
+        return None;
+    }
+    Some(Season {
+        name: name.to_string(),
+        start,
+        end,
+    })
+}
+
+pub fn find_season<'a>(seasons: &'a [Season], name: &str) -> Option<&'a Season> {
+    seasons
+        .iter()
+        .find(|s| s.name.eq_ignore_ascii_case(name.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(raw: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(raw, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn seasons_are_parsed() {
+        let seasons = parse_seasons(" Spring:2026-03-01:2026-05-31 , Summer: 2026-06-01 : 2026-08-31,");
+        assert_eq!(
+            seasons,
+            vec![
+                Season { name: "Spring".to_string(), start: date("2026-03-01"), end: date("2026-05-31") },
+                Season { name: "Summer".to_string(), start: date("2026-06-01"), end: date("2026-08-31") },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_entries_are_skipped() {
+        let seasons = parse_seasons("Spring:2026-03-01,:2026-03-01:2026-05-31,Summer:2026-06-01:August,Day:2026-07-04:2026-07-04");
+        assert_eq!(seasons.len(), 1);
+        assert_eq!(seasons[0].name, "Day");
+    }
+
+    #[test]
+    fn seasons_ending_before_they_start_are_skipped() {
+        assert!(parse_seasons("Backwards:2026-05-31:2026-03-01").is_empty());
+    }
+
+    #[test]
+    fn seasons_are_found_by_name() {
+        let seasons = parse_seasons("Spring:2026-03-01:2026-05-31");
+        assert_eq!(find_season(&seasons, " spring ").map(|s| s.start), Some(date("2026-03-01")));
+        assert!(find_season(&seasons, "Summer").is_none());
+    }
+
+    #[test]
+    fn bounds_follow_the_local_midnights_across_dst() {
+        let season = Season { name: "March".to_string(), start: date("2026-03-01"), end: date("2026-03-31") };
+        let (start, end) = season.bounds_in(chrono_tz::Europe::Amsterdam).unwrap();
+        // Midnight CET at the start, midnight CEST after the last day.
+        assert_eq!(start, Timestamp::parse("2026-02-28T23:00:00Z").unwrap());
+        assert_eq!(end, Timestamp::parse("2026-03-31T22:00:00Z").unwrap());
+    }
+}
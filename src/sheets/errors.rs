@@ -66,6 +66,12 @@ pub enum DataUploadError {
     #[error("Upload would create a duplicate key")]
     UniqueConstraint,
 
+    #[error("The row changed since it was read")]
+    RowChanged,
+
+    #[error("The record is slower than the {0} kept per track and was not stored")]
+    OutsideRecordCap(usize),
+
     #[error(transparent)]
     SerializeValue(#[from] SerializeValueError),
 
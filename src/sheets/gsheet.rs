@@ -1,34 +1,65 @@
 use google_sheets4::{
     Sheets,
-    api::ValueRange,
+    api::{
+        BatchUpdateSpreadsheetRequest, BatchUpdateValuesRequest, DeleteDimensionRequest, DimensionRange, Request,
+        ValueRange,
+    },
     hyper_rustls::{self, HttpsConnector},
     hyper_util::{self, client::legacy::connect::HttpConnector},
     yup_oauth2::{ServiceAccountAuthenticator, ServiceAccountKey},
 };
+use serde::Deserialize;
 use serde_json::Value;
 use std::fmt;
 use std::{
+    collections::{hash_map::Entry, HashMap},
     env,
     fs::File,
     io::Read,
     sync::Arc,
+    time::Instant,
 };
 use tokio::sync::Mutex;
 
+use crate::config::env_flag;
+
+use serenity::all::Timestamp;
+
+use super::errors::DataUploadError;
+use super::retry::{is_rate_limited, is_transient, with_retries};
+use super::schema::{check_headers, expected_headers};
+use super::utils::{column_index, column_letter, timestamp_to_value};
 
 use super::players::Players;
 use super::tracks::Tracks;
 use super::records::Records;
+use super::settings::Settings;
+
+/// A sheet row's cell values with its row number.
+pub type RawRow = (usize, Vec<Value>);
 
 pub struct GSheet {
     pub sheets: Arc<Mutex<Sheets<HttpsConnector<HttpConnector>>>>,
     pub document_id: String,
+    /// Secondary document that receives a best-effort copy of every write,
+    /// set when `GOOGLE_SHEET_ID_BACKUP` is present and `SHEETS_MIRROR_WRITES=1`.
+    pub backup_document_id: Option<String>,
+    /// Held across the existence check and append in `Players::create`, so two
+    /// concurrent creates for the same user can't both pass the check.
+    pub player_creation_lock: Mutex<()>,
+    /// Players' display names by user id, with the time they were read.
+    pub display_name_cache: Mutex<Option<(Instant, HashMap<u64, String>)>>,
+    /// Raw Records rows with the time they were read, dropped on every write.
+    pub records_cache: Mutex<Option<(Instant, Vec<RawRow>)>>,
+    /// Raw Tracks rows with the time they were read, see `Tracks::get_all_raw`.
+    pub tracks_cache: Mutex<Option<(Instant, Vec<RawRow>)>>,
 }
 
 impl fmt::Debug for GSheet {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("GSheet")
             .field("document_id", &self.document_id)
+            .field("backup_document_id", &self.backup_document_id)
             .field("sheets", &"<omitted>")
             .finish()
     }
@@ -44,15 +75,81 @@ pub enum GSheetError {
 
     #[error(transparent)]
     Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    SheetName(#[from] SheetNameError),
+
+    #[error("Could not read the header row of the {sheet} sheet: {source}")]
+    SchemaRead {
+        sheet: &'static str,
+        source: google_sheets4::Error,
+    },
+
+    #[error("The {sheet} sheet has \"{found}\" in column {column}, expected \"{expected}\"")]
+    SchemaMismatch {
+        sheet: &'static str,
+        column: String,
+        expected: String,
+        found: String,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SheetNameError {
+    #[error("Could not get a Drive token to resolve GOOGLE_SHEET_NAME: {0}")]
+    Token(#[from] google_sheets4::yup_oauth2::Error),
+
+    #[error("The service account did not get a Drive token to resolve GOOGLE_SHEET_NAME")]
+    MissingToken,
+
+    #[error("Could not look up GOOGLE_SHEET_NAME in Drive: {0}")]
+    Drive(#[from] reqwest::Error),
+
+    #[error("No spreadsheet named \"{0}\" is shared with the service account")]
+    NotFound(String),
+
+    #[error("{count} spreadsheets are named \"{name}\", set GOOGLE_SHEET_ID instead")]
+    Ambiguous { name: String, count: usize },
+}
+
+/// Read-only access to file names, enough to find a spreadsheet by its title.
+const DRIVE_METADATA_SCOPE: &str = "https://www.googleapis.com/auth/drive.metadata.readonly";
+
+#[derive(Debug, Deserialize)]
+pub struct DriveFile {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DriveFileList {
+    #[serde(default)]
+    files: Vec<DriveFile>,
 }
 
 impl GSheet {
     pub async fn try_new() -> Result<Self, GSheetError> {
-        let document_id = env::var("GOOGLE_SHEET_ID")?;
+        let backup_document_id = env::var("GOOGLE_SHEET_ID_BACKUP")
+            .ok()
+            .filter(|_| env_flag("SHEETS_MIRROR_WRITES"));
         let service_account_path = env::var("SERVICE_ACCOUNT_JSON")?;
         let service_account = read_service_account_json(&service_account_path)?;
         let builder = ServiceAccountAuthenticator::builder(service_account);
         let auth = builder.build().await?;
+
+        // The id stays the primary setting, the name is only looked up without it.
+        let document_id = match (env::var("GOOGLE_SHEET_ID"), env::var("GOOGLE_SHEET_NAME")) {
+            (Ok(id), _) => id,
+            (Err(_), Ok(name)) => {
+                let token = auth
+                    .token(&[DRIVE_METADATA_SCOPE])
+                    .await
+                    .map_err(SheetNameError::from)?;
+                let token = token.token().ok_or(SheetNameError::MissingToken)?;
+                resolve_sheet_name(token, &name).await?
+            }
+            (Err(why), Err(_)) => return Err(why.into()),
+        };
         let client =
             hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
                 .build(
@@ -67,35 +164,488 @@ impl GSheet {
 
         sheets.spreadsheets();
 
-        Ok(GSheet {
+        let gsheet = GSheet {
             sheets: Arc::new(Mutex::new(sheets)),
             document_id,
+            backup_document_id,
+            player_creation_lock: Mutex::new(()),
+            display_name_cache: Mutex::new(None),
+            records_cache: Mutex::new(None),
+            tracks_cache: Mutex::new(None),
+        };
+
+        if env_flag("SHEETS_SKIP_SCHEMA_CHECK") {
+            eprintln!("SHEETS_SKIP_SCHEMA_CHECK is set, not checking the sheet headers");
+        } else {
+            let strict = env_flag("SHEETS_STRICT_SCHEMA");
+            for problem in gsheet.schema_problems().await {
+                if strict {
+                    return Err(problem);
+                }
+                eprintln!(
+                    "WARNING: {problem}. /schema lists the expected headers, set SHEETS_STRICT_SCHEMA=1 to refuse to start on this"
+                );
+            }
+        }
+
+        Ok(gsheet)
+    }
+
+    /// Reads row 1 of Records, Players and Tracks and checks the headers against
+    /// the columns the bot reads and writes, see `schema::expected_headers`. Returns
+    /// the first problem of every sheet; startup only warns about them unless
+    /// `SHEETS_STRICT_SCHEMA=1`, so sheets with their own header wording keep working.
+    pub async fn schema_problems(&self) -> Vec<GSheetError> {
+        let sheets = self.sheets.lock().await;
+
+        let mut problems = vec![];
+        for (sheet, expected) in expected_headers() {
+            let range = format!("{sheet}!1:1");
+            let row = match sheets
+                .spreadsheets()
+                .values_get(&self.document_id, &range)
+                .doit()
+                .await
+            {
+                Ok((_, values)) => values
+                    .values
+                    .unwrap_or_default()
+                    .into_iter()
+                    .next()
+                    .unwrap_or_default(),
+                Err(source) => {
+                    problems.push(GSheetError::SchemaRead { sheet, source });
+                    continue;
+                }
+            };
+
+            if let Some(mismatch) = check_headers(&expected, &row) {
+                problems.push(GSheetError::SchemaMismatch {
+                    sheet,
+                    column: mismatch.column,
+                    expected: mismatch.expected,
+                    found: mismatch.found,
+                });
+            }
+        }
+
+        problems
+    }
+
+    /// Writes one cell, or adjacent cells of one row, see `write_cells`.
+    pub async fn write_cell(&self, write: CellWrite) -> Result<(), google_sheets4::Error> {
+        self.write_cells(vec![write]).await
+    }
+
+    /// Writes every `CellWrite` in one request, which counts once against the Sheets
+    /// write quota. The backup copy gets one request too, its rows are found by their
+    /// id instead of their row number, see `backup_rownum`.
+    pub async fn write_cells(&self, writes: Vec<CellWrite>) -> Result<(), google_sheets4::Error> {
+        if writes.is_empty() {
+            return Ok(());
+        }
+
+        let data = writes
+            .iter()
+            .map(|write| write.value_range(write.rownum))
+            .collect();
+        self.batch_update_in(&self.document_id, data).await?;
+
+        if let Some(backup_id) = &self.backup_document_id {
+            self.mirror_writes(backup_id, &writes).await;
+        }
+        self.invalidate_records_cache().await;
+
+        Ok(())
+    }
+
+    /// Best-effort copy of `write_cells` to the backup document. Writes to rows the
+    /// backup doesn't have are skipped and logged.
+    async fn mirror_writes(&self, backup_id: &str, writes: &[CellWrite]) {
+        let mut id_columns: HashMap<(&str, &str), Vec<Vec<Value>>> = HashMap::new();
+        let mut data = vec![];
+        for write in writes {
+            let key = (write.sheet, write.id_column);
+            if let Entry::Vacant(entry) = id_columns.entry(key) {
+                match self.read_column_in(backup_id, write.sheet, write.id_column).await {
+                    Ok(column) => {
+                        entry.insert(column);
+                    }
+                    Err(why) => {
+                        eprintln!("backup write to {} failed, could not read its ids: {why}", write.sheet);
+                        return;
+                    }
+                }
+            }
+            match find_id_row(&id_columns[&key], &write.id) {
+                Some(backup_row) => data.push(write.value_range(backup_row)),
+                None => eprintln!(
+                    "backup has no {} row with id {}, not mirroring the write of {}",
+                    write.sheet,
+                    write.id,
+                    write.range(write.rownum)
+                ),
+            }
+        }
+
+        if !data.is_empty()
+            && let Err(why) = self.batch_update_in(backup_id, data).await
+        {
+            eprintln!("backup write failed: {why}");
+        }
+    }
+
+    /// A `values_batch_update`, retried on transient errors as it can safely be
+    /// repeated. The sheets lock is taken per attempt, so other calls go ahead during
+    /// the backoff.
+    async fn batch_update_in(
+        &self,
+        document_id: &str,
+        data: Vec<ValueRange>,
+    ) -> Result<(), google_sheets4::Error> {
+        let request = BatchUpdateValuesRequest {
+            data: Some(data),
+            value_input_option: Some("RAW".to_owned()),
+            ..Default::default()
+        };
+        let request = &request;
+
+        with_retries("batch write", is_transient, || async move {
+            let sheets = self.sheets.lock().await;
+            sheets
+                .spreadsheets()
+                .values_batch_update(request.clone(), document_id)
+                .doit()
+                .await
         })
+        .await?;
+        Ok(())
     }
 
-    pub async fn write_cell(&self, cell: String, value: Value) -> Result<(), google_sheets4::Error> {
-        let values = vec![vec![value]];
+    /// Appends rows below the table in `range` and returns the range they were
+    /// written to. Only rate limits are retried, those requests were refused without
+    /// being applied; a timed out append may have gone through and would be doubled.
+    pub async fn append(&self, range: &str, values: Vec<Vec<Value>>) -> Result<String, DataUploadError> {
+        let updated_range = self
+            .append_in(&self.document_id, range, &values)
+            .await?
+            .ok_or(DataUploadError::MissingOrUnexpectedResponse)?;
+        self.invalidate_records_cache().await;
+        self.mirror_append(range, values).await;
+
+        Ok(updated_range)
+    }
 
+    async fn append_in(
+        &self,
+        document_id: &str,
+        range: &str,
+        values: &[Vec<Value>],
+    ) -> Result<Option<String>, google_sheets4::Error> {
         let request: ValueRange = ValueRange {
             major_dimension: Some("ROWS".to_owned()),
-            range: Some(cell.clone()),
-            values: Some(values),
+            range: Some(range.to_owned()),
+            values: Some(values.to_vec()),
         };
 
-        let sheets = self
-            .sheets
-            .lock()
-            .await;
-
-        sheets
-            .spreadsheets()
-            .values_update(request, &self.document_id, &cell)
-            .value_input_option("RAW")
-            .doit()
+        let response = with_retries(&format!("append to {range}"), is_rate_limited, || {
+            let request = request.clone();
+            async move {
+                let sheets = self.sheets.lock().await;
+                sheets
+                    .spreadsheets()
+                    .values_append(request, document_id, range)
+                    .value_input_option("RAW")
+                    .doit()
+                    .await
+            }
+        })
+        .await?;
+
+        Ok(response.1.updates.and_then(|updates| updates.updated_range))
+    }
+
+    /// Drops the cached Records rows. Writes to any sheet call this, which is
+    /// cheaper than tracking which ranges belong to Records. The sheets lock has to
+    /// be released first, `Records::get_all_raw` takes the two in the other order.
+    pub async fn invalidate_records_cache(&self) {
+        *self.records_cache.lock().await = None;
+    }
+
+    /// Best-effort copy of an append to the backup document. Failures are only logged,
+    /// the primary document stays the source of truth.
+    pub async fn mirror_append(&self, range: &str, values: Vec<Vec<Value>>) {
+        let Some(backup_id) = &self.backup_document_id else {
+            return;
+        };
+
+        if let Err(why) = self.append_in(backup_id, range, &values).await {
+            eprintln!("backup append to {range} failed: {why}");
+        }
+    }
+
+    /// Row number of the backup row whose `id_column` holds `id`.
+    async fn backup_rownum(
+        &self,
+        backup_id: &str,
+        sheet_name: &str,
+        id_column: &str,
+        id: &str,
+    ) -> Result<Option<usize>, google_sheets4::Error> {
+        let column = self.read_column_in(backup_id, sheet_name, id_column).await?;
+        Ok(find_id_row(&column, id))
+    }
+
+    /// Every cell of one column, from row 1.
+    async fn read_column_in(
+        &self,
+        document_id: &str,
+        sheet_name: &str,
+        column: &str,
+    ) -> Result<Vec<Vec<Value>>, google_sheets4::Error> {
+        let range = format!("{sheet_name}!{column}:{column}");
+        let range = range.as_str();
+        let values = with_retries(&format!("read of {range}"), is_transient, || async move {
+            let sheets = self.sheets.lock().await;
+            sheets.spreadsheets().values_get(document_id, range).doit().await
+        })
+        .await?
+        .1
+        .values
+        .unwrap_or_default();
+
+        Ok(values)
+    }
+}
+
+impl GSheet {
+    const RECORD_LOG_RANGE: &'static str = "RecordLog!A:F";
+
+    /// Appends one row to the RecordLog sheet: when, who, which record (by bot
+    /// message id), which field and its old and new value.
+    pub async fn append_log(
+        &self,
+        editor_user_id: u64,
+        record_id: u64,
+        field: &str,
+        old_value: String,
+        new_value: String,
+    ) -> Result<(), DataUploadError> {
+        let row = vec![
+            timestamp_to_value(Timestamp::now())?,
+            Value::String(editor_user_id.to_string()),
+            Value::String(record_id.to_string()),
+            Value::String(field.to_owned()),
+            Value::String(old_value),
+            Value::String(new_value),
+        ];
+
+        self.append(Self::RECORD_LOG_RANGE, vec![row]).await?;
+
+        Ok(())
+    }
+
+    /// Removes a whole row, shifting the rows below it up by one. The row is only
+    /// deleted while its `key_column` cell still holds `key`; a row number read
+    /// earlier may point at another row by now, which fails with `RowChanged`. Row
+    /// numbers held by callers for later rows are stale afterwards.
+    pub async fn delete_row(
+        &self,
+        sheet_name: &str,
+        rownum: usize,
+        key_column: &str,
+        key: &str,
+    ) -> Result<(), DataUploadError> {
+        let sheet_id = self
+            .sheet_id_in(&self.document_id, sheet_name)
+            .await?
+            .ok_or(DataUploadError::MissingOrUnexpectedResponse)?;
+        let cell = format!("{sheet_name}!{key_column}{rownum}");
+        let deleted = self
+            .delete_row_in(&self.document_id, sheet_id, rownum, Some((&cell, key)))
             .await?;
+        if !deleted {
+            return Err(DataUploadError::RowChanged);
+        }
+
+        if let Some(backup_id) = &self.backup_document_id {
+            let backup_row = self.backup_rownum(backup_id, sheet_name, key_column, key).await;
+            let deleted = match backup_row {
+                Ok(Some(backup_row)) => match self.sheet_id_in(backup_id, sheet_name).await {
+                    Ok(Some(sheet_id)) => self.delete_row_in(backup_id, sheet_id, backup_row, None).await.map(|_| ()),
+                    Ok(None) => Err(DataUploadError::MissingOrUnexpectedResponse),
+                    Err(why) => Err(why.into()),
+                },
+                Ok(None) => {
+                    eprintln!("backup has no {sheet_name} row with id {key}, nothing to delete there");
+                    Ok(())
+                }
+                Err(why) => Err(why.into()),
+            };
+            if let Err(why) = deleted {
+                eprintln!("backup delete of {sheet_name} row with id {key} failed: {why}");
+            }
+        }
+        self.invalidate_records_cache().await;
 
         Ok(())
     }
+
+    /// Row deletion goes through `batch_update`, which addresses sheets by their
+    /// numeric id rather than their name.
+    async fn sheet_id_in(&self, document_id: &str, sheet_name: &str) -> Result<Option<i32>, google_sheets4::Error> {
+        let spreadsheet = with_retries(&format!("lookup of sheet {sheet_name}"), is_transient, || async move {
+            let sheets = self.sheets.lock().await;
+            sheets.spreadsheets().get(document_id).doit().await
+        })
+        .await?
+        .1;
+
+        Ok(spreadsheet
+            .sheets
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|sheet| sheet.properties)
+            .find(|properties| properties.title.as_deref() == Some(sheet_name))
+            .and_then(|properties| properties.sheet_id))
+    }
+
+    /// Deletes row `rownum`, returning whether it did. With `expected` set, the row is
+    /// only deleted while that cell (e.g. `Records!B5`) still holds that key; the
+    /// check and the delete run under one sheets lock. Deletes aren't repeatable, so
+    /// only rate limits are retried, and the key is checked again on every attempt.
+    async fn delete_row_in(
+        &self,
+        document_id: &str,
+        sheet_id: i32,
+        rownum: usize,
+        expected: Option<(&str, &str)>,
+    ) -> Result<bool, DataUploadError> {
+        let row_index =
+            i32::try_from(rownum - 1).map_err(|_| DataUploadError::MissingOrUnexpectedResponse)?;
+        let request = BatchUpdateSpreadsheetRequest {
+            requests: Some(vec![Request {
+                delete_dimension: Some(DeleteDimensionRequest {
+                    range: Some(DimensionRange {
+                        sheet_id: Some(sheet_id),
+                        dimension: Some("ROWS".to_owned()),
+                        start_index: Some(row_index),
+                        end_index: Some(row_index + 1),
+                    }),
+                }),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        let request = &request;
+
+        let deleted = with_retries(&format!("delete of row {rownum}"), is_rate_limited, || async move {
+            let sheets = self.sheets.lock().await;
+            if let Some((cell, key)) = expected {
+                let found = sheets
+                    .spreadsheets()
+                    .values_get(document_id, cell)
+                    .doit()
+                    .await?
+                    .1
+                    .values
+                    .unwrap_or_default()
+                    .into_iter()
+                    .next()
+                    .and_then(|row| row.into_iter().next());
+                if cell_text(found).trim() != key {
+                    return Ok(false);
+                }
+            }
+            sheets
+                .spreadsheets()
+                .batch_update(request.clone(), document_id)
+                .doit()
+                .await
+                .map(|_| true)
+        })
+        .await?;
+
+        Ok(deleted)
+    }
+}
+
+/// A write of adjacent cells in one row, starting at `column`. The primary document
+/// is addressed by `rownum`; the backup by the row whose `id_column` holds `id`, as a
+/// failed mirror append leaves its rows at other numbers than the primary's.
+#[derive(Debug, Clone)]
+pub struct CellWrite {
+    pub sheet: &'static str,
+    pub rownum: usize,
+    pub id_column: &'static str,
+    pub id: String,
+    pub column: &'static str,
+    pub values: Vec<Value>,
+}
+
+impl CellWrite {
+    fn range(&self, rownum: usize) -> String {
+        let last_column = column_letter(column_index(self.column) + self.values.len().max(1) - 1);
+        format!("{}!{}{}:{}{}", self.sheet, self.column, rownum, last_column, rownum)
+    }
+
+    fn value_range(&self, rownum: usize) -> ValueRange {
+        ValueRange {
+            major_dimension: Some("ROWS".to_owned()),
+            range: Some(self.range(rownum)),
+            values: Some(vec![self.values.clone()]),
+        }
+    }
+}
+
+/// Row number of the first cell in a single column read (starting at row 1) that
+/// holds `id`.
+pub fn find_id_row(column: &[Vec<Value>], id: &str) -> Option<usize> {
+    column
+        .iter()
+        .position(|row| cell_text(row.first().cloned()).trim() == id)
+        .map(|index| index + 1)
+}
+
+/// The formatted value of a cell, empty when the cell is blank.
+fn cell_text(value: Option<Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text,
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Finds the id of the spreadsheet titled `name` among the files shared with the
+/// service account, through the Drive API.
+async fn resolve_sheet_name(access_token: &str, name: &str) -> Result<String, SheetNameError> {
+    let escaped = name.replace('\\', "\\\\").replace('\'', "\\'");
+    let query = format!(
+        "name = '{escaped}' and mimeType = 'application/vnd.google-apps.spreadsheet' and trashed = false"
+    );
+
+    let list: DriveFileList = reqwest::Client::new()
+        .get("https://www.googleapis.com/drive/v3/files")
+        .bearer_auth(access_token)
+        .query(&[("q", query.as_str()), ("fields", "files(id,name)")])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    pick_spreadsheet_id(name, list.files)
+}
+
+/// The id of the only file named exactly `name`. More than one match is refused
+/// rather than guessed.
+pub fn pick_spreadsheet_id(name: &str, files: Vec<DriveFile>) -> Result<String, SheetNameError> {
+    let mut matching: Vec<DriveFile> = files.into_iter().filter(|f| f.name == name).collect();
+    match matching.len() {
+        0 => Err(SheetNameError::NotFound(name.to_string())),
+        1 => Ok(matching.remove(0).id),
+        count => Err(SheetNameError::Ambiguous { name: name.to_string(), count }),
+    }
 }
 
 impl<'a> GSheet {
@@ -110,6 +660,10 @@ impl<'a> GSheet {
     pub fn records(&'a self) -> Records<'a> {
         Records::new(self)
     }
+
+    pub fn settings(&'a self) -> Settings<'a> {
+        Settings::new(self)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -131,3 +685,99 @@ fn read_service_account_json(file_path: &str) -> Result<ServiceAccountKey, Servi
 
     Ok(acc)
 }
+
+#[cfg(test)]
+impl GSheet {
+    /// A GSheet whose requests are refused locally, for testing what sits in front of them.
+    pub fn unreachable() -> Self {
+        let client =
+            hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
+                .build(
+                    hyper_rustls::HttpsConnectorBuilder::new()
+                        .with_native_roots()
+                        .unwrap()
+                        .https_or_http()
+                        .enable_http1()
+                        .build(),
+                );
+        let mut sheets = Sheets::new(client, google_sheets4::common::NoToken);
+        sheets.base_url("http://127.0.0.1:9/".to_string());
+
+        GSheet {
+            sheets: Arc::new(Mutex::new(sheets)),
+            document_id: "unreachable".to_string(),
+            backup_document_id: None,
+            player_creation_lock: Mutex::new(()),
+            display_name_cache: Mutex::new(None),
+            records_cache: Mutex::new(None),
+            tracks_cache: Mutex::new(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id_column(ids: &[&str]) -> Vec<Vec<Value>> {
+        ids.iter()
+            .map(|id| vec![Value::String(id.to_string())])
+            .collect()
+    }
+
+    #[test]
+    fn range_spans_the_written_cells() {
+        let write = CellWrite {
+            sheet: "Players",
+            rownum: 4,
+            id_column: "A",
+            id: "11".to_string(),
+            column: "C",
+            values: vec![Value::Null; 3],
+        };
+        assert_eq!(write.range(4), "Players!C4:E4");
+        assert_eq!(write.range(9), "Players!C9:E9");
+    }
+
+    #[test]
+    fn finds_the_row_holding_the_id() {
+        let column = id_column(&["bot_message_id", "11", "22", "33"]);
+        assert_eq!(find_id_row(&column, "22"), Some(3));
+    }
+
+    #[test]
+    fn backup_missing_a_mirrored_append_shifts_later_rows() {
+        // Row 3 ("22") was never mirrored, so "33" sits on row 3 in the backup
+        // while it is row 4 in the primary document.
+        let backup = id_column(&["bot_message_id", "11", "33"]);
+        assert_eq!(find_id_row(&backup, "33"), Some(3));
+        assert_eq!(find_id_row(&backup, "22"), None);
+    }
+
+    #[test]
+    fn blank_and_numeric_cells_are_compared_as_text() {
+        let column = vec![vec![], vec![Value::Null], vec![Value::from(44u64)]];
+        assert_eq!(find_id_row(&column, "44"), Some(3));
+    }
+
+    fn drive_files(names: &[(&str, &str)]) -> Vec<DriveFile> {
+        names.iter()
+            .map(|(id, name)| DriveFile { id: id.to_string(), name: name.to_string() })
+            .collect()
+    }
+
+    #[test]
+    fn exact_name_picks_its_spreadsheet() {
+        let files = drive_files(&[("1", "Records (old)"), ("2", "Records")]);
+        assert_eq!(pick_spreadsheet_id("Records", files).unwrap(), "2");
+    }
+
+    #[test]
+    fn missing_or_repeated_name_is_refused() {
+        let missing = pick_spreadsheet_id("Records", drive_files(&[("1", "records")]));
+        assert!(matches!(missing, Err(SheetNameError::NotFound(_))));
+
+        let repeated = pick_spreadsheet_id("Records", drive_files(&[("1", "Records"), ("2", "Records")]));
+        assert!(matches!(repeated, Err(SheetNameError::Ambiguous { count: 2, .. })));
+    }
+}
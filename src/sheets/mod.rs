@@ -1,7 +1,10 @@
 pub mod gsheet;
+pub mod retry;
+pub mod schema;
 pub mod utils;
 pub mod errors;
 
 pub mod tracks;
 pub mod players;
 pub mod records;
+pub mod settings;
\ No newline at end of file
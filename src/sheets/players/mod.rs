@@ -1,7 +1,8 @@
 use crate::sheets::{errors::{DataFetchError, DataUploadError}, gsheet::GSheet};
-use google_sheets4::api::ValueRange;
 use serde_json::Value;
-mod player;
+use std::{collections::HashMap, time::{Duration, Instant}};
+use tokio::sync::Mutex;
+pub mod player;
 use super::utils::DataRanges;
 
 use player::Player;
@@ -13,7 +14,8 @@ pub struct Players<'a> {
 impl DataRanges for Players<'_> {
     const SHEET_NAME: &'static str = "Players";
     const FIRST_COLUMN: &'static str = "A";
-    const LAST_COLUMN: &'static str = "C";
+    const LAST_COLUMN: &'static str = "E";
+    const ID_COLUMN: &'static str = "A";
 }
 
 impl<'a> Players<'a> {
@@ -26,6 +28,10 @@ impl Players<'_> {
     pub const USER_ID_COLUMN: &'static str = "A";
     pub const DISPLAY_NAME_COLUMN: &'static str = "B";
     pub const CURRENT_TRACK_COLUMN: &'static str = "C";
+    pub const CURRENT_CATEGORY_COLUMN: &'static str = "D";
+    pub const CURRENT_PLATFORM_COLUMN: &'static str = "E";
+
+    const DISPLAY_NAME_CACHE_TTL: Duration = Duration::from_secs(60);
 
     pub async fn get_all(&self) -> Result<Vec<Player<'_>>, DataFetchError> {
         let sheets = self.gsheet.sheets.lock().await;
@@ -57,44 +63,136 @@ impl Players<'_> {
         Ok(player)
     }
 
-    pub async fn create(&self, user_id: u64, display_name: impl Into<String>, track_name: Option<String>) -> Result<Player<'_>, DataUploadError> {
-        if let Some(_) = self.get_by_user_id(user_id).await? {
-            return Err(DataUploadError::UniqueConstraint);
+    /// Looks up a player's stored display name, reading the sheet at most once per minute.
+    pub async fn cached_display_name(&self, user_id: u64) -> Result<Option<String>, DataFetchError> {
+        let mut cache = self.gsheet.display_name_cache.lock().await;
+        let fresh = cache
+            .as_ref()
+            .is_some_and(|(read_at, _)| read_at.elapsed() < Self::DISPLAY_NAME_CACHE_TTL);
+        if !fresh {
+            let names: HashMap<u64, String> = self
+                .get_all()
+                .await?
+                .into_iter()
+                .map(|p| (p.user_id, p.display_name))
+                .collect();
+            *cache = Some((Instant::now(), names));
         }
 
+        Ok(cache
+            .as_ref()
+            .and_then(|(_, names)| names.get(&user_id).cloned()))
+    }
+
+    pub async fn invalidate_display_names(&self) {
+        *self.gsheet.display_name_cache.lock().await = None;
+    }
+
+    pub async fn create(&self, user_id: u64, display_name: impl Into<String>, track_name: Option<String>, category: Option<String>, platform: Option<String>) -> Result<Player<'_>, DataUploadError> {
         let display_name: String = display_name.into();
 
         let row = vec![
             Value::String(user_id.to_string()),
             Value::String(display_name),
             Value::String(track_name.unwrap_or_default()),
+            Value::String(category.unwrap_or_default()),
+            Value::String(platform.unwrap_or_default()),
         ];
 
-        let values = vec![row.clone()];
-
-        let request: ValueRange = ValueRange {
-            major_dimension: Some("ROWS".to_string()),
-            range: Some(Self::table_range()),
-            values: Some(values),
-        };
+        let rownum = append_unique(&self.gsheet.player_creation_lock, self, user_id, row.clone()).await?;
+        self.invalidate_display_names().await;
 
-        let sheets = self.gsheet.sheets.lock().await;
-        let result = sheets
-            .spreadsheets()
-            .values_append(request, &self.gsheet.document_id, &Self::table_range())
-            .value_input_option("RAW")
-            .doit()
-            .await?
-            .1
-            .updates
-            .ok_or(DataUploadError::MissingOrUnexpectedResponse)?
-            .updated_range
-            .ok_or(DataUploadError::MissingOrUnexpectedResponse)?;
-        let rownum = Players::extract_rows_from_range(&result)
-            .ok_or(DataUploadError::MissingOrUnexpectedResponse)?
-            .0;
         let player = Player::from_row(rownum, row, self.gsheet)?;
         
         Ok(player)
     }
 }
+
+/// The Sheets side of `Players::create`.
+pub trait PlayerRows {
+    fn contains(&self, user_id: u64) -> impl Future<Output = Result<bool, DataFetchError>> + Send;
+
+    /// Appends the row and returns its row number.
+    fn append(&self, row: Vec<Value>) -> impl Future<Output = Result<usize, DataUploadError>> + Send;
+}
+
+impl PlayerRows for Players<'_> {
+    async fn contains(&self, user_id: u64) -> Result<bool, DataFetchError> {
+        Ok(self.get_by_user_id(user_id).await?.is_some())
+    }
+
+    async fn append(&self, row: Vec<Value>) -> Result<usize, DataUploadError> {
+        let result = self.gsheet.append(&Self::table_range(), vec![row]).await?;
+        Players::extract_rows_from_range(&result)
+            .map(|rows| rows.0)
+            .ok_or(DataUploadError::MissingOrUnexpectedResponse)
+    }
+}
+
+/// Appends `row` unless `user_id` already has one. `lock` is held across the check
+/// and the append, so two concurrent creates for the same user can't both pass the check.
+pub async fn append_unique<R: PlayerRows>(lock: &Mutex<()>, rows: &R, user_id: u64, row: Vec<Value>) -> Result<usize, DataUploadError> {
+    let _creation_guard = lock.lock().await;
+
+    if rows.contains(user_id).await? {
+        return Err(DataUploadError::UniqueConstraint);
+    }
+
+    rows.append(row).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Player rows in memory, yielding between the check and the append like a
+    /// Sheets round trip would.
+    #[derive(Default)]
+    struct FakeRows(std::sync::Mutex<Vec<Vec<Value>>>);
+
+    impl PlayerRows for FakeRows {
+        async fn contains(&self, user_id: u64) -> Result<bool, DataFetchError> {
+            let found = self.0.lock().unwrap().iter().any(|row| row[0] == Value::String(user_id.to_string()));
+            tokio::task::yield_now().await;
+            Ok(found)
+        }
+
+        async fn append(&self, row: Vec<Value>) -> Result<usize, DataUploadError> {
+            let mut rows = self.0.lock().unwrap();
+            rows.push(row);
+            Ok(rows.len() + 1)
+        }
+    }
+
+    fn row(user_id: u64) -> Vec<Value> {
+        vec![Value::String(user_id.to_string()), Value::String("Mario".to_string())]
+    }
+
+    #[tokio::test]
+    async fn concurrent_creates_append_one_row() {
+        let lock = Mutex::new(());
+        let rows = FakeRows::default();
+
+        let (first, second) = tokio::join!(
+            append_unique(&lock, &rows, 7, row(7)),
+            append_unique(&lock, &rows, 7, row(7)),
+        );
+
+        assert_eq!(rows.0.lock().unwrap().len(), 1);
+        assert!(first.is_ok() != second.is_ok());
+        assert!(matches!(first.or(second), Ok(2)));
+    }
+
+    #[tokio::test]
+    async fn other_players_are_still_created() {
+        let lock = Mutex::new(());
+        let rows = FakeRows::default();
+
+        append_unique(&lock, &rows, 7, row(7)).await.unwrap();
+        let existing = append_unique(&lock, &rows, 7, row(7)).await;
+        append_unique(&lock, &rows, 8, row(8)).await.unwrap();
+
+        assert!(matches!(existing, Err(DataUploadError::UniqueConstraint)));
+        assert_eq!(rows.0.lock().unwrap().len(), 2);
+    }
+}
@@ -1,7 +1,7 @@
 use serde_json::Value;
 
 use crate::sheets::{
-    errors::{DataUploadError, DeserializeValueError}, gsheet::GSheet, players::Players, utils::{get_string, get_u64, DataRanges}
+    errors::{DataUploadError, DeserializeValueError}, gsheet::GSheet, players::Players, utils::{get_string, get_u64, required_value, DataRanges}
 };
 
 #[derive(Debug)]
@@ -11,26 +11,30 @@ pub struct Player<'a> {
     pub user_id: u64,
     pub display_name: String,
     pub current_track: Option<String>,
+    pub current_category: Option<String>,
+    pub current_platform: Option<String>,
 }
 
 impl<'a> Player<'a> {
     pub fn from_row(rownum: usize, values: Vec<Value>, gsheet: &'a GSheet) -> Result<Self, DeserializeValueError> {
-        let user_id_value = values.get(0).ok_or(DeserializeValueError::MissingItem {
-            missing_index: 0,
-            expected_item_count: 2,
-        })?;
-        let user_id = get_u64(user_id_value)?;
-
-        let display_name_value = values
-            .get(1)
-            .ok_or(DeserializeValueError::MissingItem {
-            missing_index: 1,
-            expected_item_count: 2,
-        })?;
-        let display_name = get_string(display_name_value)?;
-
-        let current_track_value = values.get(2).unwrap_or(&Value::Null);
-        let current_track = get_string(current_track_value).ok();
+        let values = Players::pad_row(values);
+
+        let user_id = get_u64(required_value(&values, 0, 2)?)?;
+
+        let display_name = match &values[1] {
+            Value::Null => String::new(),
+            value => get_string(value)?,
+        };
+
+        let current_track = get_string(&values[2]).ok();
+
+        let current_category = get_string(&values[3])
+            .ok()
+            .filter(|category| !category.trim().is_empty());
+
+        let current_platform = get_string(&values[4])
+            .ok()
+            .filter(|platform| !platform.trim().is_empty());
 
         Ok({
             Player {
@@ -39,38 +43,116 @@ impl<'a> Player<'a> {
                 user_id,
                 display_name,
                 current_track,
+                current_category,
+                current_platform,
             }
         })
     }
 }
 
+/// What a player's next uploads count towards, set through `/play`.
+#[derive(Debug, Clone)]
+pub struct Selection {
+    pub track_name: String,
+    pub category: Option<String>,
+    pub platform: Option<String>,
+}
+
+impl Selection {
+    /// `Track (category, platform)`, leaving out whatever isn't set.
+    pub fn label(&self) -> String {
+        let details: Vec<&str> = [self.category.as_deref(), self.platform.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        if details.is_empty() {
+            self.track_name.clone()
+        } else {
+            format!("{} ({})", self.track_name, details.join(", "))
+        }
+    }
+}
+
 impl Player<'_> {
+    /// `None` until the player picked a track.
+    pub fn selection(&self) -> Option<Selection> {
+        Some(Selection {
+            track_name: self.current_track.clone()?,
+            category: self.current_category.clone(),
+            platform: self.current_platform.clone(),
+        })
+    }
+
+    /// Stores the whole selection. Unset category or platform clear the stored ones,
+    /// so every `/play` fully states what the next uploads count towards.
+    /// The three columns are adjacent and written in one update.
+    pub async fn select(&mut self, selection: &Selection) -> Result<(), DataUploadError> {
+        let values = vec![
+            Value::String(selection.track_name.clone()),
+            Value::String(selection.category.clone().unwrap_or_default()),
+            Value::String(selection.platform.clone().unwrap_or_default()),
+        ];
+        let write = Players::cells_write(self.rownum, self.user_id, Players::CURRENT_TRACK_COLUMN, values);
+        self.gsheet.write_cell(write).await?;
+        self.current_track = Some(selection.track_name.clone());
+        self.current_category = selection.category.clone();
+        self.current_platform = selection.platform.clone();
+        Ok(())
+    }
+
     pub async fn set_display_name(&mut self, display_name: String) -> Result<(), DataUploadError> {
-        let cell = Players::cell_range(self.rownum, Players::DISPLAY_NAME_COLUMN);
         let value = Value::String(display_name.clone());
-        self.gsheet.write_cell(cell, value).await?;
+        let write = Players::cell_write(self.rownum, self.user_id, Players::DISPLAY_NAME_COLUMN, value);
+        self.gsheet.write_cell(write).await?;
+        Players::new(self.gsheet).invalidate_display_names().await;
         self.display_name = display_name;
         Ok(())
     }
 
     pub async fn set_current_track(&mut self, track_name: String) -> Result<(), DataUploadError> {
-        let cell = Players::cell_range(self.rownum, Players::CURRENT_TRACK_COLUMN);
         let value = Value::String(track_name.clone());
-        self.gsheet.write_cell(cell, value).await?;
+        let write = Players::cell_write(self.rownum, self.user_id, Players::CURRENT_TRACK_COLUMN, value);
+        self.gsheet.write_cell(write).await?;
         self.current_track = Some(track_name);
         Ok(())
     }
+
+    pub async fn set_current_category(&mut self, category: Option<String>) -> Result<(), DataUploadError> {
+        let value = Value::String(category.clone().unwrap_or_default());
+        let write = Players::cell_write(self.rownum, self.user_id, Players::CURRENT_CATEGORY_COLUMN, value);
+        self.gsheet.write_cell(write).await?;
+        self.current_category = category;
+        Ok(())
+    }
+
+    pub async fn set_current_platform(&mut self, platform: Option<String>) -> Result<(), DataUploadError> {
+        let value = Value::String(platform.clone().unwrap_or_default());
+        let write = Players::cell_write(self.rownum, self.user_id, Players::CURRENT_PLATFORM_COLUMN, value);
+        self.gsheet.write_cell(write).await?;
+        self.current_platform = platform;
+        Ok(())
+    }
 }
 
-impl<'a> Into<Vec<Value>> for Player<'a> {
-    fn into(self) -> Vec<Value> {
-        let user_id = Value::String(self.user_id.to_string());
-        let display_name = Value::String(self.display_name);
-        let current_track = match self.current_track {
+impl From<Player<'_>> for Vec<Value> {
+    fn from(value: Player<'_>) -> Self {
+        let user_id = Value::String(value.user_id.to_string());
+        let display_name = Value::String(value.display_name);
+        let current_track = match value.current_track {
             Some(track_name) => Value::String(track_name),
             None => Value::Null,
         };
 
-        vec![user_id, display_name, current_track]
+        let current_category = match value.current_category {
+            Some(category) => Value::String(category),
+            None => Value::Null,
+        };
+
+        let current_platform = match value.current_platform {
+            Some(platform) => Value::String(platform),
+            None => Value::Null,
+        };
+
+        vec![user_id, display_name, current_track, current_category, current_platform]
     }
 }
@@ -1,11 +1,16 @@
-use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
 
-use crate::sheets::{errors::{DataFetchError, DataUploadError}, gsheet::GSheet, utils::{duration_to_value, timestamp_to_value}};
-use google_sheets4::api::ValueRange;
+use crate::{config::{day_timezone, env_flag, env_parse}, sheets::{errors::{DataFetchError, DataUploadError, DeserializeValueError, SerializeValueError}, gsheet::{CellWrite, GSheet}}};
+use chrono_tz::Tz;
 use serenity::{all::Timestamp, json::Value};
+pub mod pager;
 pub mod record;
-use super::utils::DataRanges;
-use record::Record;
+use super::utils::{timestamp_to_value, DataRanges};
+use pager::RecordPages;
+use record::{NewRecord, Record};
 
 pub struct Records<'a> {
     gsheet: &'a GSheet,
@@ -14,7 +19,8 @@ pub struct Records<'a> {
 impl DataRanges for Records<'_> {
     const SHEET_NAME: &'static str = "Records";
     const FIRST_COLUMN: &'static str = "A";
-    const LAST_COLUMN: &'static str = "F";
+    const LAST_COLUMN: &'static str = "J";
+    const ID_COLUMN: &'static str = "B";
 }
 
 impl<'a> Records<'a> {
@@ -30,13 +36,97 @@ impl<'a> Records<'a> {
     pub const DRIVER_USER_ID_COLUMN: &'static str = "D";
     pub const TRACK_NAME_COLUMN: &'static str = "E";
     pub const RACE_DURATION_COLUMN: &'static str = "F";
+    pub const CATEGORY_COLUMN: &'static str = "G";
+    pub const APPROVED_COLUMN: &'static str = "H";
+    pub const PLATFORM_COLUMN: &'static str = "I";
+    pub const LAST_MODIFIED_COLUMN: &'static str = "J";
 
+    /// Points the record on `rownum` at a new bot message. The bot message id is also
+    /// the column a write is checked against, so the check is made with the old id.
+    pub fn bot_message_id_write(rownum: usize, old: u64, new: u64) -> CellWrite {
+        Self::cell_write(rownum, old, Self::BOT_MESSAGE_ID_COLUMN, Value::String(new.to_string()))
+    }
+
+    /// An edit of `column` and the row's last_modified stamp, to be written in one batch.
+    /// The stamp is left out when `modified_at` can't be written, the edit still goes
+    /// through.
+    pub fn edit_writes(rownum: usize, id: u64, column: &'static str, value: Value, modified_at: Timestamp) -> Vec<CellWrite> {
+        let mut writes = vec![Self::cell_write(rownum, id, column, value)];
+        match timestamp_to_value(modified_at) {
+            Ok(stamp) => writes.push(Self::cell_write(rownum, id, Self::LAST_MODIFIED_COLUMN, stamp)),
+            Err(why) => eprintln!("could not stamp the edit of record {id}: {why}"),
+        }
+        writes
+    }
+
+    /// Every readable record. Rows that fail to parse are skipped with a warning, one
+    /// bad cell shouldn't hide every record; `/validate_records` lists them. Rows
+    /// repeating an earlier row's bot message id are left out with a warning, so
+    /// lookups and leaderboards only see the first one.
     pub async fn get_all(&self) -> Result<Vec<Record<'a>>, DataFetchError> {
+        let records: Vec<Record> = self
+            .get_all_raw()
+            .await?
+            .into_iter()
+            .filter_map(|(rownum, row)| match Record::from_row(rownum, row, self.gsheet) {
+                Ok(record) => Some(record),
+                Err(why) => {
+                    eprintln!("skipping Records row {rownum}, it could not be read: {why}");
+                    None
+                }
+            })
+            .collect();
+
+        let duplicates = duplicate_keys(records.iter().map(|r| (r.bot_message_id, r.rownum())));
+        if duplicates.is_empty() {
+            return Ok(records);
+        }
+
+        for (bot_message_id, rownums) in &duplicates {
+            eprintln!(
+                "Records rows {rownums:?} share bot message {bot_message_id}, only row {} is used",
+                rownums[0]
+            );
+        }
+        let mut seen = HashSet::new();
+        Ok(records
+            .into_iter()
+            .filter(|r| seen.insert(r.bot_message_id))
+            .collect())
+    }
+
+    /// Page through the records `page_size` rows at a time, see `RecordPages`.
+    pub fn stream(&self, page_size: usize) -> RecordPages<'a> {
+        RecordPages::new(self.gsheet, page_size)
+    }
+
+    /// The untouched cell values of every non-empty data row, paired with the row number.
+    ///
+    /// Served from a cache for `RECORDS_CACHE_TTL_SECS` (default 10, 0 disables), so
+    /// bursts of lookups share one read. Any write drops the cache.
+    pub async fn get_all_raw(&self) -> Result<Vec<(usize, Vec<Value>)>, DataFetchError> {
+        let ttl = Duration::from_secs(env_parse("RECORDS_CACHE_TTL_SECS", 10));
+        let mut cache = self.gsheet.records_cache.lock().await;
+        if let Some((read_at, rows)) = cache.as_ref()
+            && cache_fresh(*read_at, Instant::now(), ttl)
+        {
+            return Ok(rows.clone());
+        }
+
+        let rows = self.read_all_raw().await?;
+        if !ttl.is_zero() {
+            *cache = Some((Instant::now(), rows.clone()));
+        }
+
+        Ok(rows)
+    }
+
+    async fn read_all_raw(&self) -> Result<Vec<(usize, Vec<Value>)>, DataFetchError> {
         let sheets = self.gsheet.sheets.lock().await;
         let document_id = &self.gsheet.document_id;
         let table_range = &Records::table_range();
 
-        let records: Vec<Record> = sheets
+        let rows = sheets
             .spreadsheets()
             .values_get(document_id, table_range)
             .doit()
@@ -47,13 +137,29 @@ impl<'a> Records<'a> {
             .into_iter()
             .enumerate()
             .skip(1)
-            .filter_map(|(index, row)| Record::from_row(index + 1, row, self.gsheet).ok())
+            .filter(|(_, row)| !row.is_empty())
+            .map(|(index, row)| (index + 1, row))
             .collect();
 
-        Ok(records)
+        Ok(rows)
+    }
+
+    /// Like `get_all`, but keeps rows that fail to deserialize (paired with their
+    /// row number and error) instead of silently dropping them. Empty rows are skipped.
+    pub async fn get_all_strict(
+        &self,
+    ) -> Result<Vec<(usize, Result<Record<'a>, DeserializeValueError>)>, DataFetchError> {
+        let rows = self
+            .get_all_raw()
+            .await?
+            .into_iter()
+            .map(|(rownum, row)| (rownum, Record::from_row(rownum, row, self.gsheet)))
+            .collect();
+
+        Ok(rows)
     }
 
-    pub async fn get_by_bot_message_id(&self, bot_message_id: u64) -> Result<Option<Record<'_>>, DataFetchError> {
+    pub async fn get_by_bot_message_id(&self, bot_message_id: u64) -> Result<Option<Record<'a>>, DataFetchError> {
         let player_list = self.get_all().await?;
         let player = player_list
             .into_iter()
@@ -61,56 +167,479 @@ impl<'a> Records<'a> {
         Ok(player)
     }
 
-    pub async fn create(
+    /// Every record on the track, in any category.
+    pub async fn get_by_track(&self, track_name: &str) -> Result<Vec<Record<'a>>, DataFetchError> {
+        let record_list = self.get_all().await?;
+        let records = record_list
+            .into_iter()
+            .filter(|r| r.track_name == track_name)
+            .collect();
+        Ok(records)
+    }
+
+    /// Every record driven by the user.
+    pub async fn get_by_driver(&self, driver_user_id: u64) -> Result<Vec<Record<'a>>, DataFetchError> {
+        let record_list = self.get_all().await?;
+        let records = record_list
+            .into_iter()
+            .filter(|r| r.driver_user_id == driver_user_id)
+            .collect();
+        Ok(records)
+    }
+
+    /// Records reported within `[start, end)`.
+    pub async fn get_between(
+        &self,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<Vec<Record<'a>>, DataFetchError> {
+        let record_list = self.get_all().await?;
+        let records = record_list
+            .into_iter()
+            .filter(|r| r.report_timestamp >= start && r.report_timestamp < end)
+            .collect();
+        Ok(records)
+    }
+
+    /// The driver's most recently reported record on any track.
+    pub async fn get_latest_by_driver(
         &self,
-        user_message_id: u64,
-        bot_message_id: u64,
-        report_timestamp: Timestamp,
         driver_user_id: u64,
-        track_name: String,
-        race_duration: Duration,
-    ) -> Result<Record<'a>, DataUploadError> {
-        let user_message_id_value = Value::String(user_message_id.to_string());
-        let bot_message_id_value = Value::String(bot_message_id.to_string());
-        let report_timestamp_value = timestamp_to_value(report_timestamp).unwrap(); // TODO: handle this unwrap properly
-        let driver_user_id_value = Value::String(driver_user_id.to_string());
-        let track_name_value = Value::String(track_name);
-        let race_duration_value = duration_to_value(race_duration).unwrap(); // TODO: handle this unwrap properly
-
-        let row = vec![
-            user_message_id_value,
-            bot_message_id_value,
-            report_timestamp_value,
-            driver_user_id_value,
-            track_name_value,
-            race_duration_value,
-        ];
-
-        let values = vec![row.clone()];
-
-        let request: ValueRange = ValueRange {
-            major_dimension: Some("ROWS".to_string()),
-            range: Some(Self::table_range()),
-            values: Some(values),
-        };
+    ) -> Result<Option<Record<'a>>, DataFetchError> {
+        let record_list = self.get_by_driver(driver_user_id).await?;
+        let latest = record_list
+            .into_iter()
+            .max_by_key(|r| r.report_timestamp);
+        Ok(latest)
+    }
 
-        let sheets = self.gsheet.sheets.lock().await;
-        let result = sheets
-            .spreadsheets()
-            .values_append(request, &self.gsheet.document_id, &Self::table_range())
-            .value_input_option("RAW")
-            .doit()
+    pub async fn get_personal_best(
+        &self,
+        driver_user_id: u64,
+        track_name: &str,
+        category: Option<&str>,
+    ) -> Result<Option<Record<'a>>, DataFetchError> {
+        let record_list = self.get_by_driver(driver_user_id).await?;
+        let personal_best = best_per_driver(
+            record_list
+                .into_iter()
+                .filter(|r| r.is_on(track_name, category)),
+        )
+        .into_iter()
+        .next();
+        Ok(personal_best)
+    }
+
+    /// The row number of the record posted as `bot_message_id`.
+    pub async fn get_row_by_bot_message_id(&self, bot_message_id: u64) -> Result<Option<usize>, DataFetchError> {
+        let record = self.get_by_bot_message_id(bot_message_id).await?;
+        Ok(record.map(|r| r.rownum()))
+    }
+
+    /// Deletes the record's row. Rows below it move up, see `GSheet::delete_row`,
+    /// which also refuses when the row no longer holds this record.
+    pub async fn delete(&self, record: &Record<'_>) -> Result<(), DataUploadError> {
+        self.delete_row(record.rownum(), record.bot_message_id).await
+    }
+
+    async fn delete_row(&self, rownum: usize, bot_message_id: u64) -> Result<(), DataUploadError> {
+        self.gsheet
+            .delete_row(
+                Self::SHEET_NAME,
+                rownum,
+                Self::BOT_MESSAGE_ID_COLUMN,
+                &bot_message_id.to_string(),
+            )
+            .await
+    }
+
+    /// Applies `MAX_RECORDS_PER_TRACK` before `new_record` is appended: the driver's
+    /// records on the track that fall outside the cap once the new one is ranked
+    /// with them are deleted. Fails with `OutsideRecordCap` when the new record is
+    /// the one that doesn't make it, so it isn't stored.
+    async fn prune_for_new_record(&self, new_record: &NewRecord) -> Result<(), DataUploadError> {
+        let cap = env_parse::<usize>("MAX_RECORDS_PER_TRACK", 0);
+        if cap == 0 {
+            return Ok(());
+        }
+
+        let existing: Vec<Record> = self
+            .get_all()
             .await?
-            .1
-            .updates
-            .ok_or(DataUploadError::MissingOrUnexpectedResponse)?
-            .updated_range
-            .ok_or(DataUploadError::MissingOrUnexpectedResponse)?;
-        let rownum = Records::extract_rows_from_range(&result)
-            .ok_or(DataUploadError::MissingOrUnexpectedResponse)?
-            .0;
+            .into_iter()
+            .filter(|r| {
+                r.driver_user_id == new_record.driver_user_id
+                    && r.is_on(&new_record.track_name, new_record.category.as_deref())
+            })
+            .collect();
+
+        let plan = plan_prune(
+            existing
+                .iter()
+                .map(|r| (r.rownum(), r.race_duration, r.report_timestamp)),
+            (new_record.race_duration, new_record.report_timestamp),
+            cap,
+        );
+
+        for rownum in plan.delete {
+            let Some(record) = existing.iter().find(|r| r.rownum() == rownum) else {
+                continue;
+            };
+            self.delete_row(rownum, record.bot_message_id).await?;
+        }
+
+        if !plan.store_new {
+            return Err(DataUploadError::OutsideRecordCap(cap));
+        }
+        Ok(())
+    }
+
+    pub async fn create(&self, new_record: NewRecord) -> Result<Record<'a>, DataUploadError> {
+        // Pruned before appending, so the new row's number isn't shifted by the delete.
+        self.prune_for_new_record(&new_record).await?;
+
+        let row = Vec::<Value>::try_from(new_record)?;
+
+        let rownum = self.append_rows(vec![row.clone()]).await?.0;
+
         let record = Record::from_row(rownum, row, self.gsheet)?;
         
         Ok(record)
     }
+
+    /// Appends all records with a single request, for batch uploads. Unlike `create`
+    /// this doesn't apply `MAX_RECORDS_PER_TRACK`.
+    pub async fn create_many(
+        &self,
+        new_records: Vec<NewRecord>,
+    ) -> Result<Vec<Record<'a>>, DataUploadError> {
+        if new_records.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let rows = new_records
+            .into_iter()
+            .map(Vec::<Value>::try_from)
+            .collect::<Result<Vec<Vec<Value>>, SerializeValueError>>()?;
+        let (first_row, last_row) = self.append_rows(rows.clone()).await?;
+        if last_row + 1 - first_row != rows.len() {
+            return Err(DataUploadError::MissingOrUnexpectedResponse);
+        }
+
+        let records = rows
+            .into_iter()
+            .enumerate()
+            .map(|(index, row)| Record::from_row(first_row + index, row, self.gsheet))
+            .collect::<Result<Vec<Record>, DeserializeValueError>>()?;
+
+        Ok(records)
+    }
+
+    /// Appends the rows and returns the first and last row number they were written to.
+    async fn append_rows(&self, values: Vec<Vec<Value>>) -> Result<(usize, usize), DataUploadError> {
+        let result = self.gsheet.append(&Self::table_range(), values).await?;
+        let rows = Records::extract_rows_from_range(&result)
+            .ok_or(DataUploadError::MissingOrUnexpectedResponse)?;
+
+        Ok(rows)
+    }
+}
+
+/// Whether rows read at `read_at` may still be served, never with a zero `ttl`.
+pub fn cache_fresh(read_at: Instant, now: Instant, ttl: Duration) -> bool {
+    now.saturating_duration_since(read_at) < ttl
+}
+
+/// With `RECORDS_IMMUTABLE=1` records are locked against edits and deletion by anyone
+/// but admins. `RECORDS_LOCK_AFTER_DAYS` only locks records older than that, unset or
+/// 0 locks every record.
+pub fn is_locked(report_timestamp: Timestamp, now: Timestamp, admin: bool) -> bool {
+    let age_days = (*now - *report_timestamp).num_days();
+    lock_applies(
+        env_flag("RECORDS_IMMUTABLE"),
+        env_parse("RECORDS_LOCK_AFTER_DAYS", 0i64),
+        age_days,
+        admin,
+    )
+}
+
+pub fn lock_applies(immutable: bool, lock_after_days: i64, age_days: i64, admin: bool) -> bool {
+    !admin && immutable && (lock_after_days <= 0 || age_days >= lock_after_days)
+}
+
+/// `UPDATE_TIME_MAX_AGE_DAYS` when a record is older than that many calendar days in
+/// `config::day_timezone`, `None` while its time may still be changed. Admins have no
+/// limit, 0 (the default) disables it.
+pub fn edit_age_limit(report_timestamp: Timestamp, now: Timestamp, admin: bool) -> Option<i64> {
+    age_limit_exceeded(
+        env_parse("UPDATE_TIME_MAX_AGE_DAYS", 0i64),
+        record_age_days(report_timestamp, now, day_timezone()),
+        admin,
+    )
+}
+
+pub fn age_limit_exceeded(max_age_days: i64, age_days: i64, admin: bool) -> Option<i64> {
+    (max_age_days > 0 && !admin && age_days > max_age_days).then_some(max_age_days)
+}
+
+/// Number of calendar days between the two timestamps, counted in `tz`.
+pub fn record_age_days(report_timestamp: Timestamp, now: Timestamp, tz: Tz) -> i64 {
+    let reported = report_timestamp.with_timezone(&tz).date_naive();
+    let today = now.with_timezone(&tz).date_naive();
+    (today - reported).num_days()
+}
+
+/// Keys that occur on more than one row, with those rows in order, sorted by their
+/// first row. Takes `(key, rownum)` pairs in row order.
+pub fn duplicate_keys(keys: impl IntoIterator<Item = (u64, usize)>) -> Vec<(u64, Vec<usize>)> {
+    let mut rows_by_key: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (key, rownum) in keys {
+        rows_by_key.entry(key).or_default().push(rownum);
+    }
+
+    let mut duplicates: Vec<(u64, Vec<usize>)> = rows_by_key
+        .into_iter()
+        .filter(|(_, rownums)| rownums.len() > 1)
+        .collect();
+    duplicates.sort_by_key(|(_, rownums)| rownums[0]);
+    duplicates
+}
+
+/// With `RECORD_APPROVAL=1` new records start out pending and only approved records
+/// count towards leaderboards.
+pub fn approval_required() -> bool {
+    env_flag("RECORD_APPROVAL")
+}
+
+/// Whether a record shows up in leaderboards and stats, pending records are left out
+/// while `approved_only` (see `approval_required`).
+pub fn is_visible(approved: bool, approved_only: bool) -> bool {
+    approved || !approved_only
+}
+
+/// Keep only the fastest record of every driver, ordered fastest first. Equal times
+/// are decided by the earliest `report_timestamp`, so the first to set a time keeps it.
+/// Callers filter by track and category beforehand (see `Record::is_on`); this does not
+/// group by either.
+pub fn best_per_driver<'a>(records: impl IntoIterator<Item = Record<'a>>) -> Vec<Record<'a>> {
+    let mut best: HashMap<u64, Record<'a>> = HashMap::new();
+    for record in records {
+        match best.get(&record.driver_user_id) {
+            Some(current) if ranking_key(current) <= ranking_key(&record) => {}
+            _ => {
+                best.insert(record.driver_user_id, record);
+            }
+        }
+    }
+
+    let mut ranked: Vec<Record<'a>> = best.into_values().collect();
+    ranked.sort_by_key(ranking_key);
+    ranked
+}
+
+/// Times rank at millisecond precision, so sub-millisecond drift from the sheet
+/// can't break a tie that the displayed times show.
+fn ranking_key(record: &Record<'_>) -> (u128, Timestamp, u64) {
+    (record.race_duration.as_millis(), record.report_timestamp, record.driver_user_id)
+}
+
+/// What `MAX_RECORDS_PER_TRACK` leaves of one driver's records on one track once a
+/// new record joins them.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PrunePlan {
+    /// Whether the new record is among the `cap` fastest.
+    pub store_new: bool,
+    /// Rows of existing records outside the cap, bottom-up so deleting in order keeps
+    /// the numbers valid.
+    pub delete: Vec<usize>,
+}
+
+/// Ranks the existing records (`(rownum, time, reported at)`) together with the new
+/// one and keeps the `cap` fastest. Times rank at millisecond precision; on equal
+/// times the earlier upload ranks first and an existing record beats the new one.
+pub fn plan_prune(
+    existing: impl IntoIterator<Item = (usize, Duration, Timestamp)>,
+    new_record: (Duration, Timestamp),
+    cap: usize,
+) -> PrunePlan {
+    let mut ranked: Vec<(u128, Timestamp, Option<usize>)> = existing
+        .into_iter()
+        .map(|(rownum, time, reported)| (time.as_millis(), reported, Some(rownum)))
+        .collect();
+    let (time, reported) = new_record;
+    ranked.push((time.as_millis(), reported, None));
+    // Stable, so the new record stays behind existing records it ties with.
+    ranked.sort_by_key(|&(millis, reported, _)| (millis, reported));
+
+    let outside = ranked.split_off(cap.min(ranked.len()));
+    let store_new = outside.iter().all(|(_, _, rownum)| rownum.is_some());
+    let mut delete: Vec<usize> = outside.into_iter().filter_map(|(_, _, rownum)| rownum).collect();
+    delete.sort_unstable_by(|a, b| b.cmp(a));
+
+    PrunePlan { store_new, delete }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minute: u32) -> Timestamp {
+        Timestamp::parse(&format!("2026-05-01T18:{minute:02}:00Z")).unwrap()
+    }
+
+    fn secs(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn slower_upload_does_not_replace_the_personal_best() {
+        let plan = plan_prune([(2, secs(80_000), at(0))], (secs(90_000), at(5)), 1);
+
+        assert_eq!(plan, PrunePlan { store_new: false, delete: vec![] });
+    }
+
+    #[test]
+    fn faster_upload_pushes_out_the_slowest() {
+        let existing = [(2, secs(80_000), at(0)), (3, secs(85_000), at(1)), (4, secs(82_000), at(2))];
+
+        let plan = plan_prune(existing, (secs(81_000), at(5)), 3);
+
+        assert_eq!(plan, PrunePlan { store_new: true, delete: vec![3] });
+    }
+
+    #[test]
+    fn under_the_cap_nothing_is_deleted() {
+        let plan = plan_prune([(2, secs(80_000), at(0))], (secs(90_000), at(5)), 2);
+
+        assert_eq!(plan, PrunePlan { store_new: true, delete: vec![] });
+    }
+
+    #[test]
+    fn ties_keep_the_earlier_upload() {
+        let existing = [(2, secs(80_000), at(3)), (3, secs(80_000), at(1))];
+
+        let plan = plan_prune(existing, (secs(80_000), at(5)), 1);
+
+        assert_eq!(plan, PrunePlan { store_new: false, delete: vec![2] });
+    }
+
+    #[test]
+    fn sub_millisecond_differences_tie() {
+        let plan = plan_prune(
+            [(2, Duration::from_micros(80_000_400), at(0))],
+            (Duration::from_micros(80_000_100), at(5)),
+            1,
+        );
+
+        assert!(!plan.store_new);
+    }
+
+    #[test]
+    fn lowered_cap_deletes_bottom_up() {
+        let existing = [(2, secs(84_000), at(0)), (5, secs(83_000), at(1)), (7, secs(82_000), at(2))];
+
+        let plan = plan_prune(existing, (secs(81_000), at(5)), 1);
+
+        assert_eq!(plan, PrunePlan { store_new: true, delete: vec![7, 5, 2] });
+    }
+
+    #[test]
+    fn unique_keys_have_no_duplicates() {
+        assert!(duplicate_keys([(11, 2), (22, 3), (33, 4)]).is_empty());
+    }
+
+    #[test]
+    fn duplicates_list_every_row_of_the_key() {
+        let duplicates = duplicate_keys([(22, 2), (11, 3), (22, 4), (11, 5), (33, 6), (22, 7)]);
+
+        assert_eq!(duplicates, vec![(22, vec![2, 4, 7]), (11, vec![3, 5])]);
+    }
+
+    #[test]
+    fn records_are_only_locked_when_immutable() {
+        assert!(!lock_applies(false, 0, 400, false));
+        assert!(lock_applies(true, 0, 0, false));
+    }
+
+    #[test]
+    fn lock_waits_for_the_configured_age() {
+        assert!(!lock_applies(true, 7, 6, false));
+        assert!(lock_applies(true, 7, 7, false));
+    }
+
+    #[test]
+    fn admins_are_never_locked_out() {
+        assert!(!lock_applies(true, 0, 0, true));
+        assert!(!lock_applies(true, 7, 30, true));
+    }
+
+    #[test]
+    fn repost_write_is_checked_against_the_old_id() {
+        let write = Records::bot_message_id_write(5, 111, 222);
+
+        assert_eq!((write.rownum, write.column, write.id_column), (5, "B", "B"));
+        assert_eq!(write.id, "111");
+        assert_eq!(write.values, vec![Value::String("222".to_string())]);
+    }
+
+    #[test]
+    fn edits_are_stamped_in_the_same_batch() {
+        let now = Timestamp::parse("2026-05-01T18:00:00Z").unwrap();
+        let writes = Records::edit_writes(5, 111, Records::RACE_DURATION_COLUMN, Value::from(0.001), now);
+
+        assert_eq!(writes.len(), 2);
+        assert!(writes.iter().all(|write| write.rownum == 5 && write.id == "111"));
+        assert_eq!((writes[0].column, writes[1].column), ("F", "J"));
+        assert_eq!(writes[0].values, vec![Value::from(0.001)]);
+        assert_eq!(writes[1].values, vec![timestamp_to_value(now).unwrap()]);
+    }
+
+    #[test]
+    fn cached_rows_expire_after_the_ttl() {
+        let read_at = Instant::now();
+        let ttl = Duration::from_secs(10);
+        assert!(cache_fresh(read_at, read_at + Duration::from_secs(9), ttl));
+        assert!(!cache_fresh(read_at, read_at + ttl, ttl));
+        assert!(!cache_fresh(read_at, read_at, Duration::ZERO));
+    }
+
+    #[test]
+    fn pending_records_are_hidden_only_when_approval_is_required() {
+        assert!(is_visible(true, true));
+        assert!(!is_visible(false, true));
+        assert!(is_visible(true, false));
+        assert!(is_visible(false, false));
+    }
+
+    #[test]
+    fn age_is_counted_in_calendar_days() {
+        let tz = chrono_tz::Europe::Amsterdam;
+        let parse = |text: &str| Timestamp::parse(text).unwrap();
+        // 23:30 and 00:10 local are a day apart, 00:10 and 23:50 are not.
+        assert_eq!(record_age_days(parse("2026-05-01T21:30:00Z"), parse("2026-05-01T22:10:00Z"), tz), 1);
+        assert_eq!(record_age_days(parse("2026-05-01T22:10:00Z"), parse("2026-05-02T21:50:00Z"), tz), 0);
+        assert_eq!(record_age_days(parse("2026-05-01T21:30:00Z"), parse("2026-05-01T21:30:00Z"), chrono_tz::UTC), 0);
+    }
+
+    #[test]
+    fn edits_stop_after_the_max_age() {
+        assert_eq!(age_limit_exceeded(7, 7, false), None);
+        assert_eq!(age_limit_exceeded(7, 8, false), Some(7));
+        assert_eq!(age_limit_exceeded(7, 8, true), None);
+        assert_eq!(age_limit_exceeded(0, 400, false), None);
+    }
+
+    #[tokio::test]
+    async fn invalidated_cache_is_not_read_again() {
+        let gsheet = GSheet::unreachable();
+        let rows = vec![(2, vec![Value::String("11".to_string())])];
+        *gsheet.records_cache.lock().await = Some((Instant::now(), rows.clone()));
+
+        assert_eq!(gsheet.records().get_all_raw().await.unwrap(), rows);
+
+        gsheet.invalidate_records_cache().await;
+
+        assert!(gsheet.records().get_all_raw().await.is_err());
+        assert!(gsheet.records_cache.lock().await.is_none());
+    }
 }
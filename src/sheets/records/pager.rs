@@ -0,0 +1,128 @@
+use crate::sheets::{errors::DataFetchError, gsheet::GSheet, records::Records, utils::DataRanges};
+
+use super::record::Record;
+
+/// Reads the Records sheet in fixed-size batches instead of materializing it at once.
+/// The row count is fetched on the first page and used to know when to stop.
+pub struct RecordPages<'a> {
+    gsheet: &'a GSheet,
+    page_size: usize,
+    next_row: usize,
+    total_rows: Option<usize>,
+}
+
+impl<'a> RecordPages<'a> {
+    pub fn new(gsheet: &'a GSheet, page_size: usize) -> Self {
+        RecordPages {
+            gsheet,
+            page_size: page_size.max(1),
+            // row 1 holds the headers
+            next_row: 2,
+            total_rows: None,
+        }
+    }
+
+    /// Returns the next batch of records, or `None` once every row has been read.
+    /// Rows that fail to deserialize are skipped, like in `Records::get_all`.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<Record<'a>>>, DataFetchError> {
+        let total_rows = match self.total_rows {
+            Some(total_rows) => total_rows,
+            None => {
+                let total_rows = self.count_rows().await?;
+                self.total_rows = Some(total_rows);
+                total_rows
+            }
+        };
+
+        let Some((from, to)) = page_bounds(self.next_row, self.page_size, total_rows) else {
+            return Ok(None);
+        };
+        self.next_row = to + 1;
+
+        let sheets = self.gsheet.sheets.lock().await;
+        let records = sheets
+            .spreadsheets()
+            .values_get(&self.gsheet.document_id, &Records::rows_range(from, to))
+            .doit()
+            .await?
+            .1
+            .values
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, row)| Record::from_row(from + index, row, self.gsheet).ok())
+            .collect();
+
+        Ok(Some(records))
+    }
+
+    pub fn total_rows(&self) -> Option<usize> {
+        self.total_rows
+    }
+
+    async fn count_rows(&self) -> Result<usize, DataFetchError> {
+        let sheets = self.gsheet.sheets.lock().await;
+        let column_range = format!(
+            "{}!{}:{}",
+            Records::SHEET_NAME,
+            Records::FIRST_COLUMN,
+            Records::FIRST_COLUMN
+        );
+        let rows = sheets
+            .spreadsheets()
+            .values_get(&self.gsheet.document_id, &column_range)
+            .doit()
+            .await?
+            .1
+            .values
+            .unwrap_or_default()
+            .len();
+        Ok(rows)
+    }
+}
+
+/// Inclusive row range of the page starting at `next_row`, clamped to `total_rows`.
+pub fn page_bounds(next_row: usize, page_size: usize, total_rows: usize) -> Option<(usize, usize)> {
+    if next_row > total_rows {
+        return None;
+    }
+    let to = (next_row + page_size.max(1) - 1).min(total_rows);
+    Some((next_row, to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every page `RecordPages` would read, starting below the header row.
+    fn pages(page_size: usize, total_rows: usize) -> Vec<(usize, usize)> {
+        let mut next_row = 2;
+        std::iter::from_fn(|| {
+            let (from, to) = page_bounds(next_row, page_size, total_rows)?;
+            next_row = to + 1;
+            Some((from, to))
+        })
+        .collect()
+    }
+
+    #[test]
+    fn rows_fill_whole_pages() {
+        assert_eq!(pages(3, 7), vec![(2, 4), (5, 7)]);
+    }
+
+    #[test]
+    fn last_page_holds_the_remainder() {
+        assert_eq!(pages(3, 8), vec![(2, 4), (5, 7), (8, 8)]);
+    }
+
+    #[test]
+    fn sheet_with_only_headers_has_no_pages() {
+        assert!(pages(3, 1).is_empty());
+        assert!(pages(3, 0).is_empty());
+    }
+
+    #[test]
+    fn zero_page_size_reads_a_row_at_a_time() {
+        assert_eq!(pages(0, 4), vec![(2, 2), (3, 3), (4, 4)]);
+    }
+}
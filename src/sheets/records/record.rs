@@ -1,18 +1,20 @@
 use std::time::Duration;
 
 use serde_json::Value;
-use serenity::all::Timestamp;
+use serenity::all::{ChannelId, GuildId, MessageId, Timestamp};
 
+use crate::{config::env_flag, time_format};
 use crate::sheets::{
-    errors::{DataUploadError, DeserializeValueError},
+    errors::{DataUploadError, DeserializeValueError, SerializeValueError},
     gsheet::GSheet,
+    players::Players,
     records::Records,
     utils::{
-        duration_to_value, get_duration, get_string, get_timestamp, get_u64, timestamp_to_value, DataRanges
+        duration_to_value, get_bool, get_duration, get_string, get_timestamp, get_u64, required_value, timestamp_to_value, DataRanges
     },
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Record<'a> {
     gsheet: &'a GSheet,
     rownum: usize,
@@ -22,6 +24,16 @@ pub struct Record<'a> {
     pub driver_user_id: u64,
     pub track_name: String,
     pub race_duration: Duration,
+    /// Engine class or similar split, `None` for rows written before categories existed.
+    pub category: Option<String>,
+    /// Console, emulator, ... `None` when the driver didn't pick one.
+    pub platform: Option<String>,
+    /// Whether a moderator accepted the record, see `approval_required`. Rows without
+    /// a value predate approvals and count as approved.
+    pub approved: bool,
+    /// When the track, time or driver was last edited through the bot. `None` for
+    /// records that were never edited or were edited before the column existed.
+    pub last_modified: Option<Timestamp>,
 }
 
 impl<'a> Record<'a> {
@@ -30,95 +42,386 @@ impl<'a> Record<'a> {
         values: Vec<Value>,
         gsheet: &'a GSheet,
     ) -> Result<Self, DeserializeValueError> {
-        let user_message_id_value = values.get(0).ok_or(DeserializeValueError::MissingItem {
-            missing_index: 0,
-            expected_item_count: 6,
-        })?;
-        let bot_message_id_value = values.get(1).ok_or(DeserializeValueError::MissingItem {
-            missing_index: 1,
-            expected_item_count: 6,
-        })?;
-        let report_timestamp_value = values.get(2).ok_or(DeserializeValueError::MissingItem {
-            missing_index: 2,
-            expected_item_count: 6,
-        })?;
-        let driver_user_id_value = values.get(3).ok_or(DeserializeValueError::MissingItem {
-            missing_index: 3,
-            expected_item_count: 6,
-        })?;
-        let track_name_value = values.get(4).ok_or(DeserializeValueError::MissingItem {
-            missing_index: 4,
-            expected_item_count: 6,
-        })?;
-        let race_duration_value = values.get(5).ok_or(DeserializeValueError::MissingItem {
-            missing_index: 5,
-            expected_item_count: 6,
-        })?;
-
-        let user_message_id = get_u64(user_message_id_value)?;
-        let bot_message_id = get_u64(bot_message_id_value)?;
-        let report_timestamp = get_timestamp(report_timestamp_value)?;
-        let driver_user_id = get_u64(driver_user_id_value)?;
-        let track_name = get_string(track_name_value)?;
-        let race_duration = get_duration(race_duration_value)?;
+        let row = RecordRow::from_values(values)?;
 
         Ok({
             Record {
                 gsheet,
                 rownum,
-                user_message_id,
-                bot_message_id,
-                report_timestamp,
-                driver_user_id,
-                track_name,
-                race_duration,
+                user_message_id: row.user_message_id,
+                bot_message_id: row.bot_message_id,
+                report_timestamp: row.report_timestamp,
+                driver_user_id: row.driver_user_id,
+                track_name: row.track_name,
+                race_duration: row.race_duration,
+                category: row.category,
+                approved: row.approved,
+                platform: row.platform,
+                last_modified: row.last_modified,
             }
         })
     }
 }
 
+/// Jump link to the upload with id `user_message_id`, see `Record::source_message_url`.
+pub fn upload_url(user_message_id: u64, guild_id: GuildId, channel_id: ChannelId) -> String {
+    MessageId::new(user_message_id).link(channel_id, Some(guild_id))
+}
+
+/// The cells of a Records row, read apart from the sheet it came from.
+#[derive(Debug)]
+struct RecordRow {
+    user_message_id: u64,
+    bot_message_id: u64,
+    report_timestamp: Timestamp,
+    driver_user_id: u64,
+    track_name: String,
+    race_duration: Duration,
+    category: Option<String>,
+    platform: Option<String>,
+    approved: bool,
+    last_modified: Option<Timestamp>,
+}
+
+impl RecordRow {
+    fn from_values(values: Vec<Value>) -> Result<Self, DeserializeValueError> {
+        let values = Records::pad_row(values);
+
+        let user_message_id = get_u64(required_value(&values, 0, 6)?)?;
+        let bot_message_id = get_u64(required_value(&values, 1, 6)?)?;
+        let report_timestamp = get_timestamp(required_value(&values, 2, 6)?)?;
+        let driver_user_id = get_u64(required_value(&values, 3, 6)?)?;
+        let track_name = get_string(required_value(&values, 4, 6)?)?;
+        let race_duration = get_duration(required_value(&values, 5, 6)?)?;
+
+        let category = get_string(&values[6])
+            .ok()
+            .filter(|category| !category.trim().is_empty());
+
+        let platform = get_string(&values[8])
+            .ok()
+            .filter(|platform| !platform.trim().is_empty());
+
+        let last_modified = get_timestamp(&values[9]).ok();
+
+        let approved = match &values[7] {
+            Value::Null => true,
+            Value::String(text) if text.trim().is_empty() => true,
+            approved_value => get_bool(approved_value)?,
+        };
+
+        Ok(RecordRow {
+            user_message_id,
+            bot_message_id,
+            report_timestamp,
+            driver_user_id,
+            track_name,
+            race_duration,
+            category,
+            platform,
+            approved,
+            last_modified,
+        })
+    }
+}
+
 impl Record<'_> {
-    pub async fn set_driver_user_id(&mut self, user_id: u64) -> Result<(), DataUploadError> {
-        let cell = Records::cell_range(self.rownum, Records::DRIVER_USER_ID_COLUMN);
+    /// The driver's display name from the Players sheet, or a mention when the
+    /// driver has no (named) player row or the sheet can't be read.
+    pub async fn driver_display_name(&self) -> String {
+        match Players::new(self.gsheet)
+            .cached_display_name(self.driver_user_id)
+            .await
+        {
+            Ok(Some(name)) if !name.trim().is_empty() => name,
+            _ => format!("<@{}>", self.driver_user_id),
+        }
+    }
+
+    pub async fn set_driver_user_id(&mut self, user_id: u64, editor_user_id: u64) -> Result<(), DataUploadError> {
         let value = Value::String(user_id.to_string());
-        self.gsheet.write_cell(cell, value).await?;
+        self.write_edit(Records::DRIVER_USER_ID_COLUMN, value).await?;
+        self.log_change(editor_user_id, "driver_user_id", self.driver_user_id.to_string(), user_id.to_string())
+            .await;
         self.driver_user_id = user_id;
         Ok(())
     }
 
-    pub async fn set_track_name(&mut self, track_name: String) -> Result<(), DataUploadError> {
-        let cell = Records::cell_range(self.rownum, Records::TRACK_NAME_COLUMN);
+    pub async fn set_track_name(&mut self, track_name: String, editor_user_id: u64) -> Result<(), DataUploadError> {
         let value = Value::String(track_name.clone());
-        self.gsheet.write_cell(cell, value).await?;
+        self.write_edit(Records::TRACK_NAME_COLUMN, value).await?;
+        self.log_change(editor_user_id, "track_name", self.track_name.clone(), track_name.clone())
+            .await;
         self.track_name = track_name;
         Ok(())
     }
 
-    pub async fn set_race_duration(&mut self, race_duration: Duration) -> Result<(), DataUploadError> {
-        let cell = Records::cell_range(self.rownum, Records::RACE_DURATION_COLUMN);
-        let value = duration_to_value(race_duration).unwrap(); // TODO: handle this unwrap properly
-        self.gsheet.write_cell(cell, value).await?;
+    /// Writes an edit together with the row's last_modified stamp, in one request.
+    async fn write_edit(&mut self, column: &'static str, value: Value) -> Result<(), DataUploadError> {
+        let now = Timestamp::now();
+        let writes = Records::edit_writes(self.rownum, self.bot_message_id, column, value, now);
+        let stamped = writes.len() > 1;
+        self.gsheet.write_cells(writes).await?;
+        if stamped {
+            self.last_modified = Some(now);
+        }
+        Ok(())
+    }
+
+    /// Appends the edit to the RecordLog sheet when `RECORD_AUDIT_LOG=1`. The edit
+    /// itself already went through, so a failed log write is only reported.
+    async fn log_change(&self, editor_user_id: u64, field: &str, old_value: String, new_value: String) {
+        if !env_flag("RECORD_AUDIT_LOG") {
+            return;
+        }
+        let logged = self
+            .gsheet
+            .append_log(editor_user_id, self.bot_message_id, field, old_value, new_value)
+            .await;
+        if let Err(why) = logged {
+            eprintln!("could not log the {field} change of record {}: {why}", self.bot_message_id);
+        }
+    }
+
+    /// See `records::is_locked`, admins may still edit locked records.
+    pub fn is_locked_for(&self, admin: bool) -> bool {
+        super::is_locked(self.report_timestamp, Timestamp::now(), admin)
+    }
+
+    /// See `records::edit_age_limit`.
+    pub fn edit_age_limit_for(&self, admin: bool) -> Option<i64> {
+        super::edit_age_limit(self.report_timestamp, Timestamp::now(), admin)
+    }
+
+    /// Link to the screenshot the record was read from. Only the message id is stored,
+    /// so the guild and the channel the upload was posted in come from the caller,
+    /// see `Handler::source_message_url`.
+    pub fn source_message_url(&self, guild_id: GuildId, channel_id: ChannelId) -> String {
+        upload_url(self.user_message_id, guild_id, channel_id)
+    }
+
+    pub fn rownum(&self) -> usize {
+        self.rownum
+    }
+
+    /// Whether this record counts towards the leaderboard of `track_name` in `category`.
+    pub fn is_on(&self, track_name: &str, category: Option<&str>) -> bool {
+        self.track_name == track_name && self.category.as_deref() == category
+    }
+
+    /// See `records::is_visible`.
+    pub fn is_visible(&self, approved_only: bool) -> bool {
+        super::is_visible(self.approved, approved_only)
+    }
+
+    pub async fn set_approved(&mut self, approved: bool, editor_user_id: u64) -> Result<(), DataUploadError> {
+        let write = Records::cell_write(self.rownum, self.bot_message_id, Records::APPROVED_COLUMN, Value::Bool(approved));
+        self.gsheet.write_cell(write).await?;
+        self.log_change(editor_user_id, "approved", self.approved.to_string(), approved.to_string())
+            .await;
+        self.approved = approved;
+        Ok(())
+    }
+
+    /// Points the record at a new bot message, e.g. after `/repost`. The old id is
+    /// logged so the RecordLog can still be followed across the move.
+    pub async fn set_bot_message_id(&mut self, bot_message_id: u64, editor_user_id: u64) -> Result<(), DataUploadError> {
+        let write = Records::bot_message_id_write(self.rownum, self.bot_message_id, bot_message_id);
+        self.gsheet.write_cell(write).await?;
+        self.log_change(editor_user_id, "bot_message_id", self.bot_message_id.to_string(), bot_message_id.to_string())
+            .await;
+        self.bot_message_id = bot_message_id;
+        Ok(())
+    }
+
+    pub async fn set_race_duration(&mut self, race_duration: Duration, editor_user_id: u64) -> Result<(), DataUploadError> {
+        let value = duration_to_value(race_duration)?;
+        self.write_edit(Records::RACE_DURATION_COLUMN, value).await?;
+        self.log_change(
+            editor_user_id,
+            "race_duration",
+            time_format::format(self.race_duration),
+            time_format::format(race_duration),
+        )
+        .await;
         self.race_duration = race_duration;
         Ok(())
     }
 }
 
-impl<'a> Into<Vec<Value>> for Record<'a> {
-    fn into(self) -> Vec<Value> {
-        let user_message_id = Value::String(self.user_message_id.to_string());
-        let bot_message_id = Value::String(self.bot_message_id.to_string());
-        let report_timestamp = timestamp_to_value(self.report_timestamp).unwrap(); // TODO: handle this unwrap properly
-        let driver_user_id = Value::String(self.driver_user_id.to_string());
-        let track_name = Value::String(self.track_name);
-        let race_duration = duration_to_value(self.race_duration).unwrap(); // TODO: handle this unwrap properly
+impl TryFrom<Record<'_>> for Vec<Value> {
+    type Error = SerializeValueError;
 
-        vec![
+    fn try_from(value: Record<'_>) -> Result<Self, Self::Error> {
+        let user_message_id = Value::String(value.user_message_id.to_string());
+        let bot_message_id = Value::String(value.bot_message_id.to_string());
+        let report_timestamp = timestamp_to_value(value.report_timestamp)?;
+        let driver_user_id = Value::String(value.driver_user_id.to_string());
+        let track_name = Value::String(value.track_name);
+        let race_duration = duration_to_value(value.race_duration)?;
+        let category = Value::String(value.category.unwrap_or_default());
+        let approved = Value::Bool(value.approved);
+        let platform = Value::String(value.platform.unwrap_or_default());
+        let last_modified = match value.last_modified {
+            Some(timestamp) => timestamp_to_value(timestamp)?,
+            None => Value::String(String::new()),
+        };
+
+        Ok(vec![
             user_message_id,
             bot_message_id,
             report_timestamp,
             driver_user_id,
             track_name,
             race_duration,
+            category,
+            approved,
+            platform,
+            last_modified,
+        ])
+    }
+}
+
+/// A record that hasn't been written yet, see `Records::create_many`.
+#[derive(Debug, Clone)]
+pub struct NewRecord {
+    pub user_message_id: u64,
+    pub bot_message_id: u64,
+    pub report_timestamp: Timestamp,
+    pub driver_user_id: u64,
+    pub track_name: String,
+    pub race_duration: Duration,
+    pub category: Option<String>,
+    pub approved: bool,
+    pub platform: Option<String>,
+}
+
+impl TryFrom<NewRecord> for Vec<Value> {
+    type Error = SerializeValueError;
+
+    fn try_from(value: NewRecord) -> Result<Self, Self::Error> {
+        let user_message_id = Value::String(value.user_message_id.to_string());
+        let bot_message_id = Value::String(value.bot_message_id.to_string());
+        let report_timestamp = timestamp_to_value(value.report_timestamp)?;
+        let driver_user_id = Value::String(value.driver_user_id.to_string());
+        let track_name = Value::String(value.track_name);
+        let race_duration = duration_to_value(value.race_duration)?;
+        let category = Value::String(value.category.unwrap_or_default());
+        let approved = Value::Bool(value.approved);
+        let platform = Value::String(value.platform.unwrap_or_default());
+
+        Ok(vec![
+            user_message_id,
+            bot_message_id,
+            report_timestamp,
+            driver_user_id,
+            track_name,
+            race_duration,
+            category,
+            approved,
+            platform,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheets::records::is_visible;
+
+    fn full_row() -> Vec<Value> {
+        let reported = Timestamp::parse("2026-05-01T18:00:00Z").unwrap();
+        vec![
+            Value::String("111".to_string()),
+            Value::String("222".to_string()),
+            timestamp_to_value(reported).unwrap(),
+            Value::String("333".to_string()),
+            Value::String("Mario Circuit".to_string()),
+            duration_to_value(Duration::from_millis(83_456)).unwrap(),
+            Value::String("150cc".to_string()),
+            Value::Bool(false),
+            Value::String("Switch".to_string()),
+            timestamp_to_value(reported).unwrap(),
         ]
     }
+
+    #[test]
+    fn rows_missing_trailing_cells_still_parse() {
+        let mut values = full_row();
+        values.truncate(9);
+        let row = RecordRow::from_values(values).unwrap();
+        assert_eq!(row.platform.as_deref(), Some("Switch"));
+        assert_eq!(row.last_modified, None);
+
+        let mut values = full_row();
+        values.truncate(8);
+        let row = RecordRow::from_values(values).unwrap();
+        assert_eq!((row.platform, row.last_modified, row.approved), (None, None, false));
+    }
+
+    #[test]
+    fn rows_from_before_the_optional_columns_get_defaults() {
+        let mut values = full_row();
+        values.truncate(6);
+        let row = RecordRow::from_values(values).unwrap();
+
+        assert_eq!(row.bot_message_id, 222);
+        assert_eq!(row.race_duration.as_millis(), 83_456);
+        assert_eq!(row.category, None);
+        assert!(row.approved);
+    }
+
+    #[test]
+    fn missing_or_blank_required_cells_are_reported() {
+        let mut values = full_row();
+        values.truncate(5);
+        let missing = RecordRow::from_values(values).unwrap_err();
+        assert!(matches!(missing, DeserializeValueError::MissingItem { missing_index: 5, .. }));
+
+        let mut values = full_row();
+        values[4] = Value::String(" ".to_string());
+        let blank = RecordRow::from_values(values).unwrap_err();
+        assert!(matches!(blank, DeserializeValueError::MissingItem { missing_index: 4, .. }));
+    }
+
+    #[test]
+    fn new_record_rows_read_back() {
+        let new_record = NewRecord {
+            user_message_id: 111,
+            bot_message_id: 222,
+            report_timestamp: Timestamp::parse("2026-05-01T18:00:00Z").unwrap(),
+            driver_user_id: 333,
+            track_name: "Mario Circuit".to_string(),
+            race_duration: Duration::from_millis(83_456),
+            category: None,
+            approved: false,
+            platform: Some("Switch".to_string()),
+        };
+
+        let values = Vec::<Value>::try_from(new_record.clone()).unwrap();
+        let row = RecordRow::from_values(values).unwrap();
+
+        assert_eq!((row.user_message_id, row.bot_message_id, row.driver_user_id), (111, 222, 333));
+        assert_eq!(row.report_timestamp, new_record.report_timestamp);
+        assert_eq!(row.track_name, "Mario Circuit");
+        assert_eq!(row.race_duration.as_millis(), 83_456);
+        assert_eq!((row.category, row.approved, row.platform), (None, false, new_record.platform));
+    }
+
+    #[test]
+    fn upload_url_links_into_the_records_channel() {
+        let url = upload_url(111, GuildId::new(1), ChannelId::new(2));
+        assert_eq!(url, "https://discord.com/channels/1/2/111");
+    }
+
+    #[test]
+    fn approving_a_row_makes_it_visible() {
+        let mut values = full_row();
+        let pending = RecordRow::from_values(values.clone()).unwrap();
+        assert!(!is_visible(pending.approved, true));
+
+        values[7] = Value::Bool(true);
+        let approved = RecordRow::from_values(values).unwrap();
+        assert!(is_visible(approved.approved, true));
+    }
 }
@@ -0,0 +1,116 @@
+use std::{future::Future, time::Duration};
+
+use google_sheets4::Error;
+use serde_json::Value;
+
+const ATTEMPTS: u32 = 3;
+const BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Runs a Sheets call up to three times, doubling the delay between attempts, as long
+/// as `retry_if` accepts the error. Other errors are returned right away.
+pub async fn with_retries<T, F, Fut>(
+    operation: &str,
+    retry_if: fn(&Error) -> bool,
+    mut call: F,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut delay = BASE_DELAY;
+    let mut attempt = 1;
+    loop {
+        match call().await {
+            Err(why) if attempt < ATTEMPTS && retry_if(&why) => {
+                eprintln!(
+                    "{operation} failed (attempt {attempt}/{ATTEMPTS}), retrying in {}ms: {why}",
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Rate limits, server errors and dropped connections. Only for idempotent calls like
+/// cell updates; a request that timed out may still have been applied.
+pub fn is_transient(error: &Error) -> bool {
+    match error {
+        Error::HttpError(_) | Error::Io(_) => true,
+        _ => error_status(error).is_some_and(|status| status == 429 || (500..600).contains(&status)),
+    }
+}
+
+/// Rate limits only. Those requests were refused without being applied, so appends
+/// can be repeated without writing the rows twice.
+pub fn is_rate_limited(error: &Error) -> bool {
+    error_status(error) == Some(429)
+}
+
+fn error_status(error: &Error) -> Option<u16> {
+    match error {
+        Error::Failure(response) => Some(response.status().as_u16()),
+        Error::BadRequest(body) => body
+            .pointer("/error/code")
+            .and_then(Value::as_u64)
+            .and_then(|code| u16::try_from(code).ok()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn status(code: u16) -> Error {
+        Error::BadRequest(json!({ "error": { "code": code } }))
+    }
+
+    #[test]
+    fn appends_only_retry_rate_limits() {
+        assert!(is_rate_limited(&status(429)));
+        assert!(!is_rate_limited(&status(503)));
+        assert!(!is_rate_limited(&Error::Io(std::io::ErrorKind::TimedOut.into())));
+    }
+
+    #[test]
+    fn updates_retry_every_transient_error() {
+        assert!(is_transient(&status(429)));
+        assert!(is_transient(&status(503)));
+        assert!(is_transient(&Error::Io(std::io::ErrorKind::TimedOut.into())));
+        assert!(!is_transient(&status(400)));
+    }
+
+    #[tokio::test]
+    async fn retries_until_the_call_succeeds() {
+        let calls = Cell::new(0);
+        let result = with_retries("test", is_transient, || {
+            calls.set(calls.get() + 1);
+            let attempt = calls.get();
+            async move { if attempt == 1 { Err(status(503)) } else { Ok(attempt) } }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_returned_right_away() {
+        let calls = Cell::new(0);
+        let result: Result<(), Error> = with_retries("test", is_transient, || {
+            calls.set(calls.get() + 1);
+            async { Err(status(400)) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+}
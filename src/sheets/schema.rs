@@ -0,0 +1,142 @@
+use serde_json::Value;
+
+use super::players::Players;
+use super::records::Records;
+use super::tracks::Tracks;
+use super::utils::{DataRanges, column_index};
+
+/// The header a column should carry in row 1. Optional columns were added after
+/// the first sheets were set up, so a blank header is accepted for them.
+pub struct ExpectedHeader {
+    pub column: &'static str,
+    pub label: &'static str,
+    pub optional: bool,
+}
+
+const fn header(column: &'static str, label: &'static str) -> ExpectedHeader {
+    ExpectedHeader { column, label, optional: false }
+}
+
+const fn optional(column: &'static str, label: &'static str) -> ExpectedHeader {
+    ExpectedHeader { column, label, optional: true }
+}
+
+/// A header in row 1 that doesn't match what the bot expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderMismatch {
+    pub column: String,
+    pub expected: String,
+    pub found: String,
+}
+
+/// Sheet name and expected headers of every sheet whose layout the
+/// bot relies on.
+pub fn expected_headers() -> Vec<(&'static str, Vec<ExpectedHeader>)> {
+    vec![
+        (Records::SHEET_NAME, vec![
+            header(Records::USER_MESSAGE_ID_COLUMN, "User message id"),
+            header(Records::BOT_MESSAGE_ID_COLUMN, "Bot message id"),
+            header(Records::REPORT_TIMESTAMP_COLUMN, "Report timestamp"),
+            header(Records::DRIVER_USER_ID_COLUMN, "Driver user id"),
+            header(Records::TRACK_NAME_COLUMN, "Track name"),
+            header(Records::RACE_DURATION_COLUMN, "Race duration"),
+            optional(Records::CATEGORY_COLUMN, "Category"),
+            optional(Records::APPROVED_COLUMN, "Approved"),
+            optional(Records::PLATFORM_COLUMN, "Platform"),
+            optional(Records::LAST_MODIFIED_COLUMN, "Last modified"),
+        ]),
+        (Players::SHEET_NAME, vec![
+            header(Players::USER_ID_COLUMN, "User id"),
+            header(Players::DISPLAY_NAME_COLUMN, "Display name"),
+            header(Players::CURRENT_TRACK_COLUMN, "Current track"),
+            optional(Players::CURRENT_CATEGORY_COLUMN, "Current category"),
+            optional(Players::CURRENT_PLATFORM_COLUMN, "Current platform"),
+        ]),
+        (Tracks::SHEET_NAME, vec![
+            header(Tracks::NAME_COLUMN, "Name"),
+            header(Tracks::ICON_FILE_URL_COLUMN, "Icon file url"),
+            optional(Tracks::COLOR_COLUMN, "Color"),
+        ]),
+    ]
+}
+
+/// The header `column` of `sheet` is checked against at startup, if any.
+pub fn expected_label(sheet: &str, column: &str) -> Option<&'static str> {
+    expected_headers()
+        .into_iter()
+        .find(|(name, _)| *name == sheet)?
+        .1
+        .into_iter()
+        .find(|header| header.column == column)
+        .map(|header| header.label)
+}
+
+/// Compares a sheet's header row, starting at column A, with the expected headers
+/// and returns the first one that differs. Case, spaces and punctuation are
+/// ignored, so "Bot Message ID" and "bot_message_id" both pass.
+pub fn check_headers(expected: &[ExpectedHeader], row: &[Value]) -> Option<HeaderMismatch> {
+    expected.iter().find_map(|header| {
+        let found = row
+            .get(column_index(header.column))
+            .and_then(|value| value.as_str())
+            .unwrap_or_default()
+            .trim();
+
+        let matches = normalize(found) == normalize(header.label)
+            || (header.optional && found.is_empty());
+
+        (!matches).then(|| HeaderMismatch {
+            column: header.column.to_string(),
+            expected: header.label.to_string(),
+            found: found.to_string(),
+        })
+    })
+}
+
+fn normalize(label: &str) -> String {
+    label
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(headers: &[&str]) -> Vec<Value> {
+        headers.iter().map(|h| Value::String(h.to_string())).collect()
+    }
+
+    fn tracks() -> Vec<ExpectedHeader> {
+        expected_headers()
+            .into_iter()
+            .find(|(name, _)| *name == Tracks::SHEET_NAME)
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn headers_match_ignoring_case_and_punctuation() {
+        assert_eq!(check_headers(&tracks(), &row(&["NAME", "icon_file_url", "Color"])), None);
+    }
+
+    #[test]
+    fn blank_optional_header_passes() {
+        assert_eq!(check_headers(&tracks(), &row(&["Name", "Icon file URL"])), None);
+    }
+
+    #[test]
+    fn swapped_columns_are_reported() {
+        let mismatch = check_headers(&tracks(), &row(&["Icon file URL", "Name"])).unwrap();
+        assert_eq!(mismatch.column, "A");
+        assert_eq!(mismatch.found, "Icon file URL");
+    }
+
+    #[test]
+    fn expected_label_by_sheet_and_column() {
+        assert_eq!(expected_label("Records", "B"), Some("Bot message id"));
+        assert_eq!(expected_label("Settings", "A"), None);
+    }
+}
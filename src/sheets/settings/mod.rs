@@ -0,0 +1,79 @@
+use crate::sheets::{errors::{DataFetchError, DataUploadError}, gsheet::GSheet};
+use serde_json::Value;
+mod setting;
+use super::utils::DataRanges;
+
+use setting::Setting;
+
+pub struct Settings<'a> {
+    gsheet: &'a GSheet,
+}
+
+impl DataRanges for Settings<'_> {
+    const SHEET_NAME: &'static str = "Settings";
+    const FIRST_COLUMN: &'static str = "A";
+    const LAST_COLUMN: &'static str = "B";
+    const ID_COLUMN: &'static str = "A";
+}
+
+impl<'a> Settings<'a> {
+    pub fn new(gsheet: &'a GSheet) -> Self {
+        Settings { gsheet }
+    }
+}
+
+impl Settings<'_> {
+    pub const KEY_COLUMN: &'static str = "A";
+    pub const VALUE_COLUMN: &'static str = "B";
+
+    pub const RECORDS_CHANNEL_ID: &'static str = "records_channel_id";
+
+    pub async fn get_all(&self) -> Result<Vec<Setting<'_>>, DataFetchError> {
+        let sheets = self.gsheet.sheets.lock().await;
+        let document_id = &self.gsheet.document_id;
+        let table_range = &Settings::table_range();
+
+        let settings: Vec<Setting> = sheets
+            .spreadsheets()
+            .values_get(document_id, table_range)
+            .doit()
+            .await?
+            .1
+            .values
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .skip(1)
+            .filter_map(|(index, row)| Setting::from_row(index + 1, row, self.gsheet).ok())
+            .collect();
+
+        Ok(settings)
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<String>, DataFetchError> {
+        let setting_list = self.get_all().await?;
+        let value = setting_list
+            .into_iter()
+            .find(|s| s.key == key)
+            .map(|s| s.value);
+        Ok(value)
+    }
+
+    /// Overwrites the value of an existing key, or appends a new row for it.
+    pub async fn set(&self, key: &str, value: String) -> Result<(), DataUploadError> {
+        let existing = self
+            .get_all()
+            .await?
+            .into_iter()
+            .find(|s| s.key == key);
+
+        if let Some(mut setting) = existing {
+            return setting.set_value(value).await;
+        }
+
+        let values = vec![vec![Value::String(key.to_string()), Value::String(value)]];
+        self.gsheet.append(&Self::table_range(), values).await?;
+
+        Ok(())
+    }
+}
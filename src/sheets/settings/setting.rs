@@ -0,0 +1,48 @@
+use serde_json::Value;
+
+use crate::sheets::{
+    errors::{DataUploadError, DeserializeValueError}, gsheet::GSheet, settings::Settings, utils::{get_string, DataRanges}
+};
+
+#[derive(Debug)]
+pub struct Setting<'a> {
+    gsheet: &'a GSheet,
+    rownum: usize,
+    pub key: String,
+    pub value: String,
+}
+
+impl<'a> Setting<'a> {
+    pub fn from_row(rownum: usize, values: Vec<Value>, gsheet: &'a GSheet) -> Result<Self, DeserializeValueError> {
+        let key_value = values.first().ok_or(DeserializeValueError::MissingItem {
+            missing_index: 0,
+            expected_item_count: 2,
+        })?;
+        let key = get_string(key_value)?;
+
+        let value_value = values.get(1).unwrap_or(&Value::Null);
+        let value = match value_value {
+            Value::Null => String::new(),
+            Value::Number(number) => number.to_string(),
+            val => get_string(val)?,
+        };
+
+        Ok({
+            Setting {
+                gsheet,
+                rownum,
+                key,
+                value,
+            }
+        })
+    }
+}
+
+impl Setting<'_> {
+    pub async fn set_value(&mut self, value: String) -> Result<(), DataUploadError> {
+        let write = Settings::cell_write(self.rownum, &self.key, Settings::VALUE_COLUMN, Value::String(value.clone()));
+        self.gsheet.write_cell(write).await?;
+        self.value = value;
+        Ok(())
+    }
+}
@@ -1,4 +1,15 @@
-use crate::sheets::{errors::DataFetchError, gsheet::GSheet};
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    config::env_parse,
+    sheets::{errors::DataFetchError, gsheet::{GSheet, RawRow}},
+};
 mod track;
 use super::utils::DataRanges;
 use track::Track;
@@ -11,7 +22,8 @@ pub struct Tracks<'a> {
 impl DataRanges for Tracks<'_> {
     const SHEET_NAME: &'static str = "Tracks";
     const FIRST_COLUMN: &'static str = "A";
-    const LAST_COLUMN: &'static str = "B";
+    const LAST_COLUMN: &'static str = "C";
+    const ID_COLUMN: &'static str = "A";
 }
 
 impl<'a> Tracks<'a> {
@@ -23,17 +35,63 @@ impl<'a> Tracks<'a> {
 impl Tracks<'_> {
     pub const NAME_COLUMN: &'static str = "A";
     pub const ICON_FILE_URL_COLUMN: &'static str = "B";
+    pub const COLOR_COLUMN: &'static str = "C";
 
     pub async fn get_all(&self) -> Result<Vec<Track<'_>>, DataFetchError> {
-        let sheets = self
-            .gsheet
-            .sheets
-            .lock()
-            .await;
+        let tracks = self
+            .get_all_raw()
+            .await?
+            .into_iter()
+            .filter_map(|(rownum, row)| Track::from_row(rownum, row, self.gsheet).ok())
+            .collect();
+
+        Ok(tracks)
+    }
+
+    /// The track named exactly `track_name`, only its own row is parsed.
+    pub async fn get_by_name(&self, track_name: &str) -> Result<Option<Track<'_>>, DataFetchError> {
+        let track = self
+            .get_all_raw()
+            .await?
+            .into_iter()
+            .find(|(_, row)| row.first().and_then(Value::as_str) == Some(track_name))
+            .and_then(|(rownum, row)| Track::from_row(rownum, row, self.gsheet).ok());
+
+        Ok(track)
+    }
+
+    /// The icon URL stored for the track, `None` for an unknown track. The URL isn't
+    /// checked here, `resolve_icon_url` falls back for unusable ones.
+    pub async fn get_icon_url(&self, track_name: &str) -> Result<Option<String>, DataFetchError> {
+        let track = self.get_by_name(track_name).await?;
+        Ok(track.map(|t| t.icon_url))
+    }
+
+    /// Served from a cache for `TRACKS_REFRESH_SECS` (default 300, 0 disables), since
+    /// the Tracks sheet rarely changes. `refresh` drops the cache right away.
+    async fn get_all_raw(&self) -> Result<Vec<RawRow>, DataFetchError> {
+        let ttl = Duration::from_secs(env_parse("TRACKS_REFRESH_SECS", 300));
+        let mut cache = self.gsheet.tracks_cache.lock().await;
+        if let Some((read_at, rows)) = cache.as_ref()
+            && read_at.elapsed() < ttl
+        {
+            return Ok(rows.clone());
+        }
+
+        let rows = self.read_all_raw().await?;
+        if !ttl.is_zero() {
+            *cache = Some((Instant::now(), rows.clone()));
+        }
+
+        Ok(rows)
+    }
+
+    async fn read_all_raw(&self) -> Result<Vec<RawRow>, DataFetchError> {
+        let sheets = self.gsheet.sheets.lock().await;
         let document_id = &self.gsheet.document_id;
         let table_range = &Tracks::table_range();
 
-        let tracks: Vec<Track> = sheets
+        let rows = sheets
             .spreadsheets()
             .values_get(document_id, table_range)
             .doit()
@@ -44,9 +102,238 @@ impl Tracks<'_> {
             .into_iter()
             .enumerate()
             .skip(1)
-            .filter_map(|(index, row)| Track::from_row(index + 1, row, self.gsheet).ok())
+            .map(|(index, row)| (index + 1, row))
+            .collect();
+
+        Ok(rows)
+    }
+
+    /// Drops the cached rows, the next read goes to the sheet.
+    pub async fn refresh(&self) {
+        *self.gsheet.tracks_cache.lock().await = None;
+    }
+
+    /// Like `get_all`, but leaves out rows that aren't tracks, see `Track::is_listed`.
+    pub async fn get_listed(&self) -> Result<Vec<Track<'_>>, DataFetchError> {
+        let ignore = ignore_pattern();
+        let tracks = self
+            .get_all()
+            .await?
+            .into_iter()
+            .filter(|t| t.is_listed(ignore.as_ref()))
             .collect();
 
         Ok(tracks)
     }
-}
\ No newline at end of file
+}
+
+/// `TRACK_IGNORE_PATTERN` is a regex for names of rows that should never be offered
+/// as a track, e.g. `^(Cup|---)`. An invalid pattern is logged and ignored.
+fn ignore_pattern() -> Option<Regex> {
+    parse_ignore_pattern(&env::var("TRACK_IGNORE_PATTERN").ok()?)
+}
+
+fn parse_ignore_pattern(pattern: &str) -> Option<Regex> {
+    if pattern.trim().is_empty() {
+        return None;
+    }
+    match Regex::new(pattern) {
+        Ok(regex) => Some(regex),
+        Err(why) => {
+            eprintln!("Ignoring invalid TRACK_IGNORE_PATTERN: {why}");
+            None
+        }
+    }
+}
+
+/// Matches a typed or detected track name against the known names, ignoring case,
+/// spacing and punctuation. An exact match wins; otherwise the input must be part of
+/// exactly one name, so ambiguous fragments like "Circuit" resolve to nothing.
+pub fn resolve_track_name<'n>(input: &str, names: &'n [String]) -> Option<&'n str> {
+    let wanted = normalize_track_name(input);
+    if wanted.is_empty() {
+        return None;
+    }
+
+    if let Some(name) = names.iter().find(|n| normalize_track_name(n) == wanted) {
+        return Some(name);
+    }
+
+    let mut partial = names
+        .iter()
+        .filter(|n| normalize_track_name(n).contains(&wanted));
+    match (partial.next(), partial.next()) {
+        (Some(name), None) => Some(name),
+        _ => None,
+    }
+}
+
+/// The track a capture tool named the file after, e.g. `rainbow_road_result.png`.
+/// Runs of the filename's words are matched through `resolve_track_name`, the same
+/// matcher `/play` uses, longest run first, so "ds_rainbow_road" gives "DS Rainbow
+/// Road" rather than "Rainbow Road". Runs of one length naming different tracks give
+/// `None`.
+pub fn track_from_filename<'n>(filename: &str, names: &'n [String]) -> Option<&'n str> {
+    let stem = filename.rsplit_once('.').map_or(filename, |(stem, _)| stem);
+    let words: Vec<&str> = stem
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    for len in (1..=words.len()).rev() {
+        let mut found = words
+            .windows(len)
+            .filter_map(|run| resolve_track_name(&run.join(" "), names));
+        let Some(first) = found.next() else {
+            continue;
+        };
+        return found.all(|other| other == first).then_some(first);
+    }
+    None
+}
+
+fn normalize_track_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Parses an embed colour written as `#RRGGBB`, `RRGGBB` or `0xRRGGBB`.
+pub fn parse_hex_color(text: &str) -> Option<u32> {
+    let text = text.trim();
+    let hex = text
+        .strip_prefix('#')
+        .or_else(|| text.strip_prefix("0x"))
+        .unwrap_or(text);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+/// The form colours are stored in the Tracks sheet, `#RRGGBB`.
+pub fn format_hex_color(color: u32) -> String {
+    format!("#{:06X}", color & 0xFF_FF_FF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        ["Rainbow Road", "DS Rainbow Road", "Mario Circuit", "Luigi Circuit"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn filename_names_a_track() {
+        let names = names();
+        assert_eq!(track_from_filename("rainbow_road_result.png", &names), Some("Rainbow Road"));
+        assert_eq!(track_from_filename("MarioCircuit.jpg", &names), Some("Mario Circuit"));
+    }
+
+    #[test]
+    fn longest_track_in_the_filename_wins() {
+        let names = names();
+        assert_eq!(track_from_filename("ds-rainbow-road.png", &names), Some("DS Rainbow Road"));
+    }
+
+    #[test]
+    fn filename_naming_two_tracks_gives_none() {
+        let names = names();
+        assert_eq!(track_from_filename("mario_circuit_vs_luigi_circuit.png", &names), None);
+        assert_eq!(track_from_filename("screenshot_0001.png", &names), None);
+    }
+
+    #[test]
+    fn filename_matches_like_play_does() {
+        let names = names();
+        for typed in ["mario circuit", "Luigi", "DS Rainbow"] {
+            assert_eq!(
+                track_from_filename(&format!("{typed}.png"), &names),
+                resolve_track_name(typed, &names)
+            );
+        }
+    }
+
+    #[test]
+    fn exact_name_ignores_case_and_punctuation() {
+        let names = names();
+        assert_eq!(resolve_track_name("rainbow-road", &names), Some("Rainbow Road"));
+        assert_eq!(resolve_track_name("  DS RAINBOW ROAD ", &names), Some("DS Rainbow Road"));
+    }
+
+    #[test]
+    fn fragment_of_one_track_resolves() {
+        assert_eq!(resolve_track_name("luigi", &names()), Some("Luigi Circuit"));
+    }
+
+    #[test]
+    fn ambiguous_or_blank_input_resolves_to_nothing() {
+        let names = names();
+        assert_eq!(resolve_track_name("Circuit", &names), None);
+        assert_eq!(resolve_track_name("Bowser", &names), None);
+        assert_eq!(resolve_track_name(" - ", &names), None);
+    }
+
+    #[test]
+    fn hex_colours_are_parsed_in_every_spelling() {
+        for text in ["#FF8800", "0xFF8800", "FF8800", " #ff8800 "] {
+            assert_eq!(parse_hex_color(text), Some(0xFF8800), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_colours_are_refused() {
+        for text in ["", "#", "#FF880", "#FF88001", "#GG8800", "orange", "0XFF8800", "#+F8800"] {
+            assert_eq!(parse_hex_color(text), None, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn colours_are_stored_as_hash_hex() {
+        assert_eq!(format_hex_color(0xFF8800), "#FF8800");
+        assert_eq!(format_hex_color(0x00B0F4), "#00B0F4");
+        assert_eq!(parse_hex_color(&format_hex_color(0x0000FF)), Some(0x0000FF));
+    }
+
+    fn listed(ignore: Option<&Regex>) -> Vec<String> {
+        let gsheet = GSheet::unreachable();
+        let rows = [
+            ("Mushroom Cup", ""),
+            ("Mario Bros. Circuit", "https://example.com/mbc.png"),
+            ("Cup: Flower", "https://example.com/flower.png"),
+            ("---", "https://example.com/spacer.png"),
+            ("Crown City", "https://example.com/cc.png"),
+        ];
+        rows.iter()
+            .enumerate()
+            .map(|(index, (name, icon))| {
+                let values = vec![Value::String(name.to_string()), Value::String(icon.to_string())];
+                Track::from_row(index + 2, values, &gsheet).unwrap()
+            })
+            .filter(|track| track.is_listed(ignore))
+            .map(|track| track.name)
+            .collect()
+    }
+
+    #[test]
+    fn ignore_pattern_filters_tracks() {
+        let ignore = parse_ignore_pattern("^(Cup|---)");
+        assert_eq!(listed(ignore.as_ref()), vec!["Mario Bros. Circuit", "Crown City"]);
+    }
+
+    #[test]
+    fn rows_without_an_icon_are_never_listed() {
+        assert_eq!(listed(None), vec!["Mario Bros. Circuit", "Cup: Flower", "---", "Crown City"]);
+    }
+
+    #[test]
+    fn blank_or_invalid_patterns_ignore_nothing() {
+        assert!(parse_ignore_pattern("  ").is_none());
+        assert!(parse_ignore_pattern("(Cup").is_none());
+    }
+}
@@ -1,13 +1,21 @@
+use regex::Regex;
 use serde_json::Value;
 
-use crate::sheets::{errors::DeserializeValueError, gsheet::GSheet};
+use crate::sheets::{
+    errors::{DataUploadError, DeserializeValueError},
+    gsheet::GSheet,
+    tracks::{format_hex_color, parse_hex_color, Tracks},
+    utils::DataRanges,
+};
 
 #[derive(Debug)]
 pub struct Track<'a> {
-    _gsheet: &'a GSheet,
-    _rownum: usize,
+    gsheet: &'a GSheet,
+    rownum: usize,
     pub name: String,
     pub icon_url: String,
+    /// Embed colour of the track's records, `None` uses the default.
+    pub color: Option<u32>,
 }
 
 impl<'a> Track<'a> {
@@ -16,10 +24,9 @@ impl<'a> Track<'a> {
         values: Vec<Value>,
         gsheet: &'a GSheet,
     ) -> Result<Self, DeserializeValueError> {
-        let name = match values.get(0).ok_or(DeserializeValueError::MissingItem {
-            missing_index: 0,
-            expected_item_count: 2,
-        })? {
+        let values = Tracks::pad_row(values);
+
+        let name = match &values[0] {
             Value::String(name) => name,
             val => {
                 return Err(DeserializeValueError::UnexpectedValueType {
@@ -31,14 +38,10 @@ impl<'a> Track<'a> {
         }
         .to_owned();
 
-        let icon_url = match values
-            .get(1)
-            .ok_or(DeserializeValueError::MissingItem {
-            missing_index: 1,
-            expected_item_count: 2,
-        })?
-        {
+        let icon_url = match &values[1] {
             Value::String(icon_url) => icon_url,
+            // a track without icon, see `is_listed`
+            Value::Null => "",
             val => {
                 return Err(DeserializeValueError::UnexpectedValueType {
                     input_value: val.clone(),
@@ -49,19 +52,45 @@ impl<'a> Track<'a> {
         }
         .to_owned();
 
+        let color = match &values[2] {
+            Value::String(text) => parse_hex_color(text),
+            _ => None,
+        };
+
         Ok({
             Track {
-                _gsheet: gsheet,
-                _rownum: rownum,
+                gsheet,
+                rownum,
                 name,
                 icon_url,
+                color,
             }
         })
     }
 }
 
-impl Into<Vec<Value>> for Track<'_> {
-    fn into(self) -> Vec<Value> {
-        vec![Value::String(self.name), Value::String(self.icon_url)]
+impl Track<'_> {
+    pub async fn set_color(&mut self, color: Option<u32>) -> Result<(), DataUploadError> {
+        let value = Value::String(color.map(format_hex_color).unwrap_or_default());
+        let write = Tracks::cell_write(self.rownum, &self.name, Tracks::COLOR_COLUMN, value);
+        self.gsheet.write_cell(write).await?;
+        self.gsheet.tracks().refresh().await;
+        self.color = color;
+        Ok(())
+    }
+
+    /// Section headers and spacers in the Tracks sheet have no icon, those rows and
+    /// names matching `ignore` are not offered as tracks.
+    pub fn is_listed(&self, ignore: Option<&Regex>) -> bool {
+        !self.name.trim().is_empty()
+            && !self.icon_url.trim().is_empty()
+            && !ignore.is_some_and(|pattern| pattern.is_match(&self.name))
+    }
+}
+
+impl From<Track<'_>> for Vec<Value> {
+    fn from(value: Track<'_>) -> Self {
+        let color = Value::String(value.color.map(format_hex_color).unwrap_or_default());
+        vec![Value::String(value.name), Value::String(value.icon_url), color]
     }
 }
@@ -5,12 +5,19 @@ use chrono_tz::Europe::Amsterdam;
 use serde_json::{Number, Value};
 use serenity::all::Timestamp;
 
-use crate::sheets::errors::{DeserializeValueError, SerializeValueError};
+use crate::{
+    sheets::gsheet::CellWrite,
+    sheets::errors::{DeserializeValueError, SerializeValueError},
+    time_format,
+};
 
 pub trait DataRanges {
     const SHEET_NAME: &'static str;
     const FIRST_COLUMN: &'static str;
     const LAST_COLUMN: &'static str;
+    /// The column holding each row's unique id, used to find the same row in the
+    /// backup document.
+    const ID_COLUMN: &'static str;
 
     fn table_range() -> String {
         format!(
@@ -43,10 +50,41 @@ pub trait DataRanges {
         )
     }
 
+    /// Number of columns between `FIRST_COLUMN` and `LAST_COLUMN`, both included.
+    fn column_count() -> usize {
+        column_index(Self::LAST_COLUMN) + 1 - column_index(Self::FIRST_COLUMN)
+    }
+
+    /// Sheets leaves trailing empty cells out of a row, pad those back in as `Null`
+    /// so optional trailing columns parse like blanks instead of going missing.
+    fn pad_row(mut values: Vec<Value>) -> Vec<Value> {
+        if values.len() < Self::column_count() {
+            values.resize(Self::column_count(), Value::Null);
+        }
+        values
+    }
+
     fn cell_range(row: usize, col: &str) -> String {
         format!("{}!{}{}:{}{}", Self::SHEET_NAME, col, row, col, row)
     }
 
+    /// A write of `value` to `col` of the row `row`, whose `ID_COLUMN` holds `id`.
+    fn cell_write(row: usize, id: impl ToString, col: &'static str, value: Value) -> CellWrite {
+        Self::cells_write(row, id, col, vec![value])
+    }
+
+    /// Like `cell_write`, for `values` in adjacent columns starting at `col`.
+    fn cells_write(row: usize, id: impl ToString, col: &'static str, values: Vec<Value>) -> CellWrite {
+        CellWrite {
+            sheet: Self::SHEET_NAME,
+            rownum: row,
+            id_column: Self::ID_COLUMN,
+            id: id.to_string(),
+            column: col,
+            values,
+        }
+    }
+
     fn extract_rows_from_range(range: &str) -> Option<(usize, usize)> {
         let pattern = regex::Regex::new(r"^[^!]+![A-Z]+(\d+):[A-Z]+(\d+)$").ok()?;
         let captures = pattern.captures(range)?;
@@ -56,6 +94,43 @@ pub trait DataRanges {
     }
 }
 
+/// Zero based index of a column letter (`A` is 0, `AA` is 26).
+pub(crate) fn column_index(column: &str) -> usize {
+    column
+        .bytes()
+        .fold(0, |index, letter| index * 26 + usize::from(letter - b'A' + 1))
+        - 1
+}
+
+/// Column letter of a zero based index, the inverse of `column_index`.
+pub(crate) fn column_letter(index: usize) -> String {
+    let mut letters = Vec::new();
+    let mut rest = index + 1;
+    while rest > 0 {
+        letters.push(char::from(b'A' + ((rest - 1) % 26) as u8));
+        rest = (rest - 1) / 26;
+    }
+    letters.iter().rev().collect()
+}
+
+/// A cell the row can't do without. Rows are padded first, see `pad_row`, so a
+/// cell Sheets left out reads as blank and is reported as missing.
+pub fn required_value(
+    values: &[Value],
+    index: usize,
+    expected_item_count: usize,
+) -> Result<&Value, DeserializeValueError> {
+    let missing = DeserializeValueError::MissingItem {
+        missing_index: index,
+        expected_item_count,
+    };
+    match values.get(index) {
+        None | Some(Value::Null) => Err(missing),
+        Some(Value::String(text)) if text.trim().is_empty() => Err(missing),
+        Some(value) => Ok(value),
+    }
+}
+
 pub fn get_u64(value: &Value) -> Result<u64, DeserializeValueError> {
     match value {
         Value::Number(number) => number.as_u64().ok_or(DeserializeValueError::ExtractValue {
@@ -87,32 +162,55 @@ pub fn get_string(value: &Value) -> Result<String, DeserializeValueError> {
     }
 }
 
+/// Sheets returns checkboxes and booleans as `TRUE`/`FALSE` when formatted.
+pub fn get_bool(value: &Value) -> Result<bool, DeserializeValueError> {
+    match value {
+        Value::Bool(flag) => Ok(*flag),
+        Value::String(text) if text.eq_ignore_ascii_case("true") => Ok(true),
+        Value::String(text) if text.eq_ignore_ascii_case("false") => Ok(false),
+        Value::String(text) => Err(DeserializeValueError::TypeConversion {
+            input: text.to_string(),
+            output_type: "bool",
+        }),
+        val => Err(DeserializeValueError::UnexpectedValueType {
+            input_value: val.clone(),
+            allowed_inputs: "Bool, String",
+            intended_output: "bool",
+        }),
+    }
+}
+
+/// Reads a timestamp cell in whichever form it comes back in, trying in order:
+/// the `%d-%m-%Y %H:%M:%S` display format, RFC 3339, and serial days (a number,
+/// or a string holding one after a hand edit). The display format and serial days
+/// are Amsterdam wall-clock time, like `timestamp_to_value` writes them.
 pub fn get_timestamp(value: &Value) -> Result<Timestamp, DeserializeValueError> {
     match value {
         Value::String(s) => {
-            let naive = NaiveDateTime::parse_from_str(s, "%d-%m-%Y %H:%M:%S").map_err(|_| {
-                DeserializeValueError::ExtractValue {
+            let text = s.trim();
+            NaiveDateTime::parse_from_str(text, "%d-%m-%Y %H:%M:%S")
+                .ok()
+                .and_then(amsterdam_to_timestamp)
+                .or_else(|| {
+                    DateTime::parse_from_rfc3339(text)
+                        .ok()
+                        .map(|datetime| Timestamp::from(datetime.with_timezone(&Utc)))
+                })
+                .or_else(|| text.parse::<f64>().ok().and_then(serial_days_to_timestamp))
+                .ok_or_else(|| DeserializeValueError::ExtractValue {
                     input_value: value.clone(),
                     output_type: "Timestamp",
-                }
-            })?;
-            let datetime: DateTime<Utc> = TimeZone::from_utc_datetime(&Utc, &naive);
-            Ok(Timestamp::from(datetime))
+                })
         }
         Value::Number(n) => {
             let serial_days = n.as_f64().ok_or(DeserializeValueError::ExtractValue {
                 input_value: value.clone(),
                 output_type: "f64",
             })?;
-            let unix_seconds = (serial_days - SHEETS_EPOCH_UNIX_DAYS) * SECS_PER_DAY;
-            let datetime = DateTime::from_timestamp(unix_seconds as i64, 0).ok_or_else(|| {
-                DeserializeValueError::TypeConversion {
-                    input: unix_seconds.to_string(),
-                    output_type: "DateTime",
-                }
-            })?;
-            // let datetime_utc: DateTime<Utc> = DateTime::from_utc(datetime, Utc);
-            Ok(Timestamp::from(datetime))
+            serial_days_to_timestamp(serial_days).ok_or_else(|| DeserializeValueError::TypeConversion {
+                input: serial_days.to_string(),
+                output_type: "DateTime",
+            })
         }
         val => Err(DeserializeValueError::UnexpectedValueType {
             input_value: val.clone(),
@@ -122,6 +220,24 @@ pub fn get_timestamp(value: &Value) -> Result<Timestamp, DeserializeValueError>
     }
 }
 
+/// Serial days are written as Amsterdam wall-clock time by `timestamp_to_value`,
+/// rounded to whole seconds to absorb floating point drift.
+fn serial_days_to_timestamp(serial_days: f64) -> Option<Timestamp> {
+    let local_seconds = ((serial_days - SHEETS_EPOCH_UNIX_DAYS) * SECS_PER_DAY).round();
+    if !local_seconds.is_finite() {
+        return None;
+    }
+    let naive_local = DateTime::from_timestamp(local_seconds as i64, 0)?.naive_utc();
+    amsterdam_to_timestamp(naive_local)
+}
+
+/// The earlier instant is picked for wall-clock times that occur twice when the
+/// clocks go back.
+fn amsterdam_to_timestamp(naive_local: NaiveDateTime) -> Option<Timestamp> {
+    let datetime = Amsterdam.from_local_datetime(&naive_local).earliest()?;
+    Some(Timestamp::from(datetime.with_timezone(&Utc)))
+}
+
 pub fn get_duration(value: &Value) -> Result<Duration, DeserializeValueError> {
     match value {
         Value::Number(number) => {
@@ -129,59 +245,22 @@ pub fn get_duration(value: &Value) -> Result<Duration, DeserializeValueError> {
                 input_value: value.clone(),
                 output_type: "f64",
             })?;
-            let seconds = time * SECS_PER_DAY;
-            Ok(Duration::from_secs_f64(seconds))
-        }
-        Value::String(string) => {
-            let parts: Vec<&str> = string.split(':').collect();
-            if parts.len() != 2 {
-                return Err(DeserializeValueError::InvalidFormat {
-                    input: string.clone(),
+            // Round to whole milliseconds, the serial-day float rarely lands exactly on one.
+            let millis = (time * SECS_PER_DAY * 1000.0).round();
+            if !millis.is_finite() || millis < 0.0 {
+                return Err(DeserializeValueError::TypeConversion {
+                    input: time.to_string(),
                     output_type: "Duration",
-                    message: "String must contain exactly one colon, between the minutes and seconds place".to_owned(),
                 });
             }
-
-            let minutes: u64 =
-                parts[0]
-                    .parse()
-                    .map_err(|_| DeserializeValueError::InvalidFormat {
-                        input: parts[0].to_owned(),
-                        output_type: "u64",
-                        message: "Minutes part must represent a valid number".to_owned(),
-                    })?;
-
-            let sec_parts: Vec<&str> = parts[1].split('.').collect();
-            if sec_parts.len() != 2 {
-                return Err(DeserializeValueError::InvalidFormat {
-                    input: string.clone(),
-                    output_type: "Duration",
-                    message: "String must contain exactly one period, between the seconds and milliseconds place".to_owned(),
-                });
-            }
-
-            let seconds: u64 =
-                sec_parts[0]
-                    .parse()
-                    .map_err(|_| DeserializeValueError::InvalidFormat {
-                        input: parts[0].to_owned(),
-                        output_type: "u64",
-                        message: "Seconds part must represent a valid number".to_owned(),
-                    })?;
-
-            let millis: u64 = if sec_parts.len() > 1 {
-                sec_parts[1]
-                    .parse::<u64>()
-                    .map_err(|_| DeserializeValueError::InvalidFormat {
-                        input: parts[0].to_owned(),
-                        output_type: "u64",
-                        message: "Milliseconds part must represent a valid number".to_owned(),
-                    })?
-            } else {
-                0
-            };
-
-            Ok(Duration::from_secs(minutes * 60 + seconds) + Duration::from_millis(millis))
+            Ok(Duration::from_millis(millis as u64))
+        }
+        Value::String(string) => {
+            time_format::parse(string).map_err(|e| DeserializeValueError::InvalidFormat {
+                input: string.clone(),
+                output_type: "Duration",
+                message: e.to_string(),
+            })
         }
         _ => Err(DeserializeValueError::UnexpectedValueType {
             input_value: value.clone(),
@@ -207,10 +286,104 @@ pub fn timestamp_to_value(timestamp: Timestamp) -> Result<Value, SerializeValueE
 }
 
 pub fn duration_to_value(duration: Duration) -> Result<Value, SerializeValueError> {
-    let serial_days = duration.as_secs_f64() / SECS_PER_DAY;
+    // Only whole milliseconds are meaningful for race times.
+    let serial_days = duration.as_millis() as f64 / 1000.0 / SECS_PER_DAY;
     let number = Number::from_f64(serial_days).ok_or(SerializeValueError::ParseError {
         input: serial_days.to_string(),
         message: "Number may not be NaN or Infinite".to_owned(),
     })?;
     Ok(Value::Number(number))
 }
+
+/// Whether a stored serial-day number differs from its canonical re-serialization by
+/// more than `tolerance_secs`. Non-numeric values are never considered drifted.
+pub fn serial_value_drifted(stored: &Value, canonical: &Value, tolerance_secs: f64) -> bool {
+    match (stored.as_f64(), canonical.as_f64()) {
+        (Some(stored), Some(canonical)) => (stored - canonical).abs() * SECS_PER_DAY > tolerance_secs,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_letters_round_trip() {
+        for (letter, index) in [("A", 0), ("E", 4), ("Z", 25), ("AA", 26), ("AZ", 51), ("BA", 52)] {
+            assert_eq!(column_index(letter), index);
+            assert_eq!(column_letter(index), letter);
+        }
+    }
+
+    fn utc(text: &str) -> Timestamp {
+        Timestamp::parse(text).unwrap()
+    }
+
+    #[test]
+    fn stored_serial_timestamps_round_trip() {
+        // Winter, summer and the hours around both DST switches in Amsterdam.
+        for text in [
+            "2024-01-15T18:30:05Z",
+            "2024-07-01T09:00:00Z",
+            "2024-03-31T00:59:59Z",
+            "2024-03-31T01:00:00Z",
+            "2024-10-27T00:30:00Z",
+            "2024-10-27T02:00:00Z",
+        ] {
+            let timestamp = utc(text);
+            let stored = timestamp_to_value(timestamp).unwrap();
+            assert_eq!(get_timestamp(&stored).unwrap(), timestamp, "{text}");
+        }
+    }
+
+    #[test]
+    fn repeated_hour_reads_back_as_its_first_occurrence() {
+        // 01:30 UTC is the second 02:30 in Amsterdam that night, the stored wall clock
+        // can't tell the two apart.
+        let stored = timestamp_to_value(utc("2024-10-27T01:30:00Z")).unwrap();
+        assert_eq!(get_timestamp(&stored).unwrap(), utc("2024-10-27T00:30:00Z"));
+    }
+
+    #[test]
+    fn serial_days_are_amsterdam_wall_clock() {
+        // 45292.5 is noon on 2024-01-01 in the sheet, 11:00 UTC in winter.
+        let serial = Value::Number(Number::from_f64(45292.5).unwrap());
+        assert_eq!(get_timestamp(&serial).unwrap(), utc("2024-01-01T11:00:00Z"));
+        assert_eq!(get_timestamp(&Value::String("45292.5".to_string())).unwrap(), utc("2024-01-01T11:00:00Z"));
+    }
+
+    #[test]
+    fn timestamps_are_read_from_text() {
+        let expected = utc("2024-07-01T09:00:00Z");
+        assert_eq!(get_timestamp(&Value::String("01-07-2024 11:00:00".to_string())).unwrap(), expected);
+        assert_eq!(get_timestamp(&Value::String("2024-07-01T11:00:00+02:00".to_string())).unwrap(), expected);
+        assert!(get_timestamp(&Value::String("yesterday".to_string())).is_err());
+    }
+
+    struct ThreeColumns;
+
+    impl DataRanges for ThreeColumns {
+        const SHEET_NAME: &'static str = "Test";
+        const FIRST_COLUMN: &'static str = "A";
+        const LAST_COLUMN: &'static str = "C";
+        const ID_COLUMN: &'static str = "A";
+    }
+
+    #[test]
+    fn short_rows_are_padded_with_blanks() {
+        let padded = ThreeColumns::pad_row(vec![Value::String("a".to_string())]);
+        assert_eq!(padded, vec![Value::String("a".to_string()), Value::Null, Value::Null]);
+
+        let full = vec![Value::from(1), Value::from(2), Value::from(3), Value::from(4)];
+        assert_eq!(ThreeColumns::pad_row(full.clone()), full);
+    }
+
+    #[test]
+    fn blank_required_cells_are_missing() {
+        let values = ThreeColumns::pad_row(vec![Value::from(1), Value::String(String::new())]);
+        assert_eq!(required_value(&values, 0, 3).unwrap(), &Value::from(1));
+        assert!(matches!(required_value(&values, 1, 3), Err(DeserializeValueError::MissingItem { missing_index: 1, .. })));
+        assert!(matches!(required_value(&values, 2, 3), Err(DeserializeValueError::MissingItem { missing_index: 2, .. })));
+    }
+}
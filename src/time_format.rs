@@ -0,0 +1,182 @@
+//! The single source of truth for how race times are written and read back.
+//! The canonical form is `m:ss.mmm`, e.g. `1:02.345`.
+
+use std::{cmp::Ordering, env, time::Duration};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::config::env_flag;
+
+static CANONICAL_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d+):([0-5]\d)\.(\d{3})$").unwrap());
+static CUSTOM_EMOJI_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^<a?:\w{2,32}:\d{17,20}>$").unwrap());
+
+/// `TIME_PREFIX`, read once and dropped with a warning when it isn't an emoji.
+static TIME_PREFIX: Lazy<Option<String>> = Lazy::new(|| {
+    let prefix = env::var("TIME_PREFIX").ok()?;
+    let prefix = prefix.trim();
+    if prefix.is_empty() {
+        return None;
+    }
+    if !is_valid_prefix(prefix) {
+        eprintln!("Ignoring TIME_PREFIX {prefix:?}, expected a single emoji like ⏱ or <:name:id>");
+        return None;
+    }
+    Some(prefix.to_string())
+});
+
+#[derive(Debug, thiserror::Error)]
+pub enum TimeFormatError {
+    #[error("invalid time format: {0}, expected m:ss.mmm")]
+    InvalidFormat(String),
+
+    #[error("time out of range: {0}")]
+    OutOfRange(String),
+}
+
+/// Parse a canonical `m:ss.mmm` time. Leniency (missing minutes, short milliseconds,
+/// surrounding text) belongs to the caller's normalization step, not here.
+pub fn parse(s: &str) -> Result<Duration, TimeFormatError> {
+    let s = s.trim();
+    let caps = CANONICAL_RE
+        .captures(s)
+        .ok_or_else(|| TimeFormatError::InvalidFormat(s.to_string()))?;
+
+    let minutes = caps[1]
+        .parse::<u64>()
+        .map_err(|_| TimeFormatError::OutOfRange(s.to_string()))?;
+    let seconds = caps[2]
+        .parse::<u64>()
+        .map_err(|_| TimeFormatError::OutOfRange(s.to_string()))?;
+    let millis = caps[3]
+        .parse::<u64>()
+        .map_err(|_| TimeFormatError::OutOfRange(s.to_string()))?;
+
+    let total_secs = minutes
+        .checked_mul(60)
+        .and_then(|m| m.checked_add(seconds))
+        .ok_or_else(|| TimeFormatError::OutOfRange(s.to_string()))?;
+
+    Ok(Duration::from_secs(total_secs) + Duration::from_millis(millis))
+}
+
+/// Compare two times at the millisecond precision they are shown in. Durations read
+/// back from serial days can differ by a fraction of a millisecond, which must not
+/// decide a personal best or a ranking.
+pub fn cmp_millis(a: Duration, b: Duration) -> Ordering {
+    a.as_millis().cmp(&b.as_millis())
+}
+
+/// The gap between two times, at millisecond precision.
+pub fn abs_diff_millis(a: Duration, b: Duration) -> Duration {
+    let millis = a.as_millis().abs_diff(b.as_millis());
+    Duration::from_millis(u64::try_from(millis).unwrap_or(u64::MAX))
+}
+
+/// Format a duration as `m:ss.mmm`, truncating anything below a millisecond.
+pub fn format(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let seconds = duration.as_secs() - minutes * 60;
+    let millis = duration.subsec_millis();
+    format!("{minutes}:{seconds:0>2}.{millis:0>3}")
+}
+
+/// A time as shown to players in embeds: `format` with the `TIME_PREFIX` emoji in
+/// front, in bold when `TIME_BOLD=1`. Sheet values and code-block tables use the
+/// plain `format`, emoji break their alignment.
+pub fn format_display(duration: Duration) -> String {
+    style(&format(duration), TIME_PREFIX.as_deref(), env_flag("TIME_BOLD"))
+}
+
+/// Puts the optional prefix and bold markers around an already formatted time.
+pub fn style(time: &str, prefix: Option<&str>, bold: bool) -> String {
+    let time = if bold { format!("**{time}**") } else { time.to_string() };
+    match prefix {
+        Some(prefix) => format!("{prefix} {time}"),
+        None => time,
+    }
+}
+
+/// A Discord custom emoji (`<:name:id>`), or a few characters without letters,
+/// digits, whitespace or markdown, which covers unicode emoji including joined and
+/// variation-selector sequences.
+pub fn is_valid_prefix(prefix: &str) -> bool {
+    if CUSTOM_EMOJI_RE.is_match(prefix) {
+        return true;
+    }
+    let chars = prefix.chars().count();
+    (1..=8).contains(&chars)
+        && prefix
+            .chars()
+            .all(|c| !c.is_ascii() && !c.is_alphanumeric() && !c.is_whitespace())
+}
+
+/// Format a time difference as `s.mmm`, falling back to `m:ss.mmm` past a minute.
+pub fn format_delta(delta: Duration) -> String {
+    if delta.as_secs() >= 60 {
+        return format(delta);
+    }
+    format!("{}.{:0>3}", delta.as_secs(), delta.subsec_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unicode_and_custom_emoji_are_valid_prefixes() {
+        assert!(is_valid_prefix("⏱️"));
+        assert!(is_valid_prefix("🏳️‍🌈"));
+        assert!(is_valid_prefix("<:stopwatch:123456789012345678>"));
+        assert!(is_valid_prefix("<a:spin:123456789012345678>"));
+    }
+
+    #[test]
+    fn text_and_markdown_are_not_prefixes() {
+        assert!(!is_valid_prefix(""));
+        assert!(!is_valid_prefix("Time:"));
+        assert!(!is_valid_prefix("**"));
+        assert!(!is_valid_prefix("⏱ ⏱"));
+        assert!(!is_valid_prefix("⏱⏱⏱⏱⏱⏱⏱⏱⏱"));
+    }
+
+    #[test]
+    fn prefix_goes_before_the_bold_time() {
+        assert_eq!(style("1:23.456", Some("⏱️"), true), "⏱️ **1:23.456**");
+        assert_eq!(style("1:23.456", None, false), "1:23.456");
+    }
+
+    #[test]
+    fn canonical_times_round_trip() {
+        for time in ["1:02.345", "10:00.000", "0:00.001"] {
+            assert_eq!(format(parse(time).unwrap()), time);
+        }
+        assert_eq!(parse("1:02.345").unwrap(), Duration::from_millis(62_345));
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_ignored() {
+        assert_eq!(parse("  1:02.345\n").unwrap(), Duration::from_millis(62_345));
+    }
+
+    #[test]
+    fn non_canonical_times_are_rejected() {
+        for time in ["1:60.000", "1:2.345", "1:02.34", "1:02.3456", "62.345", "", "1:02.345s", "abc"] {
+            assert!(matches!(parse(time), Err(TimeFormatError::InvalidFormat(_))), "{time:?}");
+        }
+    }
+
+    #[test]
+    fn format_truncates_below_a_millisecond() {
+        assert_eq!(format(Duration::from_micros(62_345_999)), "1:02.345");
+    }
+
+    #[test]
+    fn comparisons_ignore_sub_millisecond_noise() {
+        let a = Duration::from_micros(62_345_100);
+        let b = Duration::from_micros(62_345_900);
+        assert_eq!(cmp_millis(a, b), Ordering::Equal);
+        assert_eq!(cmp_millis(a, Duration::from_millis(62_346)), Ordering::Less);
+        assert_eq!(cmp_millis(Duration::from_millis(62_346), b), Ordering::Greater);
+    }
+}